@@ -0,0 +1,24 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::num::NonZeroU8;
+use sudoku_pi::sudoku::{get_x_and_y_from_pos, Sudoku};
+
+fuzz_target!(|data: [u8; 81]| {
+    let mut sudoku = Sudoku::new();
+    for (pos, byte) in data.into_iter().enumerate() {
+        if let Some(n) = NonZeroU8::new(byte % 10) {
+            let (x, y) = get_x_and_y_from_pos(pos);
+            sudoku = sudoku.set(x, y, n);
+        }
+    }
+
+    if let Some(solution) = sudoku.find_unique_solution() {
+        assert!(solution.is_solved());
+        for pos in 0..81 {
+            if let Some(n) = sudoku.get_by_pos(pos) {
+                assert_eq!(solution.get_by_pos(pos), Some(n));
+            }
+        }
+    }
+});
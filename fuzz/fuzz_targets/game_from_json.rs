@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sudoku_pi::sudoku::Game;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(game) = Game::from_json(data) {
+        // Whatever we accepted, we should be able to save again.
+        let json = game.to_json().expect("Parsed game should be serializable");
+        Game::from_json(&json).expect("Serialized game should be parseable");
+    }
+});
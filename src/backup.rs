@@ -0,0 +1,428 @@
+//! Exports all of the player's data to a single file, and imports it again,
+//! so players can keep a backup or move to another machine.
+
+use crate::highscores::Highscores;
+use crate::library::Library;
+use crate::persistence::{self, Migration, Persistence};
+use crate::settings::Settings;
+use crate::statistics::Statistics;
+use crate::storage::find_flag_value;
+use crate::sudoku::Game;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::ffi::OsString;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The file in the data dir that backups are exported to, and imported from,
+/// within the game.
+pub const BACKUP_FILE: &str = "sudoku-backup.json";
+
+/// The migrations for backups, see `persistence::from_versioned_json()`.
+const BACKUP_MIGRATIONS: &[Migration] = &[];
+
+/// How imported data is combined with the data the player already has.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MergeStrategy {
+    /// Keeps the best of both sides: highscores are ranked together, and of
+    /// games in the same slot the one with the most progress is kept.
+    KeepBest,
+    /// Replaces all of the player's data with the imported data.
+    Replace,
+    /// Only adds what the player doesn't have yet.
+    Skip,
+}
+
+impl MergeStrategy {
+    fn from_flag(flag: &str) -> Option<Self> {
+        match flag {
+            "keep-best" => Some(Self::KeepBest),
+            "replace" => Some(Self::Replace),
+            "skip" => Some(Self::Skip),
+            _ => None,
+        }
+    }
+}
+
+/// All of the player's data.
+#[derive(Default)]
+pub struct PlayerData {
+    pub settings: Settings,
+    pub highscores: Highscores,
+    pub statistics: Statistics,
+    pub library: Library,
+    pub saved_games: Vec<Game>,
+    pub archived_games: Vec<Game>,
+}
+
+/// The player's data as it's exported.
+///
+/// Every part keeps the schema version it was written with, so that backups
+/// from older versions are migrated the same way their files would be.
+#[derive(Deserialize, Serialize)]
+struct Backup {
+    /// When the backup was made, in seconds since the Unix epoch.
+    exported_at: u64,
+    settings: Value,
+    highscores: Value,
+    statistics: Value,
+    library: Value,
+    saved_games: Vec<Value>,
+    archived_games: Vec<Value>,
+}
+
+impl PlayerData {
+    /// Loads all of the player's data from disk.
+    ///
+    /// Every saved game is read, so this is left to the sync in the
+    /// background, and to exports and imports the player asked for.
+    pub fn load(persistence: &Persistence) -> Self {
+        Self {
+            settings: Settings::load(persistence),
+            highscores: Highscores::load(persistence),
+            statistics: Statistics::load(persistence),
+            library: Library::load(persistence),
+            saved_games: Game::list_saved(persistence),
+            archived_games: Game::list_archived(persistence),
+        }
+    }
+
+    /// Saves all of the player's data to disk.
+    fn save(&self, persistence: &Persistence) {
+        self.settings.save(persistence);
+        self.highscores.save(persistence);
+        self.statistics.save(persistence);
+        self.library.save(persistence);
+
+        // Archived games are saved last, so a game that was completed on
+        // either side doesn't linger as a game in progress.
+        for game in self.saved_games.iter().chain(&self.archived_games) {
+            game.save(persistence);
+        }
+    }
+
+    /// Returns the slots of all the games, whether in progress or completed.
+    fn slots(&self) -> impl Iterator<Item = u64> + '_ {
+        self.saved_games
+            .iter()
+            .chain(&self.archived_games)
+            .map(|game| game.slot)
+    }
+
+    pub fn to_backup_json(&self) -> Result<Vec<u8>, anyhow::Error> {
+        let backup = Backup {
+            exported_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            settings: serde_json::from_slice(&self.settings.to_json()?)?,
+            highscores: serde_json::from_slice(&self.highscores.to_json()?)?,
+            statistics: serde_json::from_slice(&self.statistics.to_json()?)?,
+            library: serde_json::from_slice(&self.library.to_json()?)?,
+            saved_games: games_to_values(&self.saved_games)?,
+            archived_games: games_to_values(&self.archived_games)?,
+        };
+        persistence::to_versioned_json(&backup, BACKUP_MIGRATIONS)
+    }
+
+    pub fn from_backup_json(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        let backup: Backup = persistence::from_versioned_json(bytes, BACKUP_MIGRATIONS)?;
+        Ok(Self {
+            settings: Settings::from_json(&serde_json::to_vec(&backup.settings)?)
+                .context("Invalid settings")?,
+            highscores: Highscores::from_json(&serde_json::to_vec(&backup.highscores)?)
+                .context("Invalid highscores")?,
+            statistics: Statistics::from_json(&serde_json::to_vec(&backup.statistics)?)
+                .context("Invalid statistics")?,
+            library: Library::from_json(&serde_json::to_vec(&backup.library)?)
+                .context("Invalid library")?,
+            saved_games: games_from_values(backup.saved_games)?,
+            archived_games: games_from_values(backup.archived_games)?,
+        })
+    }
+
+    /// Describes what's in the data, so the player knows what they're about
+    /// to import.
+    pub fn summarize(&self) -> String {
+        let num_highscores: usize = self
+            .highscores
+            .difficulties
+            .values()
+            .map(|highscores| highscores.best_scores.len())
+            .sum();
+        format!(
+            "{} games in progress, {} completed games,\n{} highscores, {} games in the statistics\nand {} puzzles in the library.",
+            self.saved_games.len(),
+            self.archived_games.len(),
+            num_highscores,
+            self.statistics.games.len(),
+            self.library.puzzles.len()
+        )
+    }
+
+    /// Combines the other data with this data, using the given strategy.
+    pub fn merge(mut self, other: PlayerData, strategy: MergeStrategy) -> Self {
+        // Settings are a matter of taste, so they're only taken over when
+        // replacing.
+        match strategy {
+            MergeStrategy::Replace => return other,
+            MergeStrategy::KeepBest => {
+                self.highscores.merge(other.highscores);
+            }
+            MergeStrategy::Skip => {
+                for (difficulty, highscores) in other.highscores.difficulties {
+                    self.highscores
+                        .difficulties
+                        .entry(difficulty)
+                        .or_insert(highscores);
+                }
+                if self.highscores.legacy.is_empty() {
+                    self.highscores.legacy = other.highscores.legacy;
+                }
+            }
+        }
+
+        self.statistics.merge(other.statistics);
+        self.library.merge(other.library);
+        merge_games(&mut self.archived_games, other.archived_games, strategy);
+        merge_games(&mut self.saved_games, other.saved_games, strategy);
+
+        // Games that were completed on either side are no longer in progress.
+        let archived_games = &self.archived_games;
+        self.saved_games.retain(|game| {
+            !archived_games
+                .iter()
+                .any(|archived| archived.slot == game.slot)
+        });
+
+        self
+    }
+}
+
+fn games_to_values(games: &[Game]) -> Result<Vec<Value>, anyhow::Error> {
+    games
+        .iter()
+        .map(|game| Ok(serde_json::from_slice(&game.to_json()?)?))
+        .collect()
+}
+
+fn games_from_values(values: Vec<Value>) -> Result<Vec<Game>, anyhow::Error> {
+    values
+        .into_iter()
+        .map(|value| Game::from_json(&serde_json::to_vec(&value)?).context("Invalid game"))
+        .collect()
+}
+
+/// Adds the other games to the given ones. Of games in the same slot, the
+/// one with the most progress is kept if the strategy is to keep the best.
+fn merge_games(games: &mut Vec<Game>, other: Vec<Game>, strategy: MergeStrategy) {
+    for game in other {
+        match games.iter_mut().find(|known| known.slot == game.slot) {
+            Some(known)
+                if strategy == MergeStrategy::KeepBest && game.progress() > known.progress() =>
+            {
+                *known = game
+            }
+            Some(_) => {}
+            None => games.push(game),
+        }
+    }
+}
+
+/// Exports all of the player's data, including the progress of the games
+/// that were saved last.
+pub fn export(persistence: &Persistence) -> Result<Vec<u8>, anyhow::Error> {
+    PlayerData::load(persistence).to_backup_json()
+}
+
+/// Reads the backup that was placed in the data dir, see `BACKUP_FILE`.
+pub fn read_backup(persistence: &Persistence) -> Result<PlayerData, anyhow::Error> {
+    let bytes = persistence.read(Path::new(BACKUP_FILE))?;
+    PlayerData::from_backup_json(&bytes)
+}
+
+/// Merges the imported data with the player's data and saves the result,
+/// which is returned so it can replace the data that's in use.
+pub fn import(
+    persistence: &Persistence,
+    imported: PlayerData,
+    strategy: MergeStrategy,
+) -> PlayerData {
+    let local = PlayerData::load(persistence);
+    let previous_slots: Vec<u64> = local.slots().collect();
+
+    let merged = local.merge(imported, strategy);
+    for slot in previous_slots {
+        // The player chose to replace these games, so other devices that
+        // sync with this one drop them as well.
+        if !merged.slots().any(|merged_slot| merged_slot == slot) {
+            Game::delete_slot(persistence, slot);
+            Game::remember_deletion(persistence, slot);
+        }
+    }
+    merged.save(persistence);
+    merged
+}
+
+/// Handles the `--export <file>` and `--import <file>` command line flags,
+/// which export or import the player's data without starting the game.
+///
+/// How imported data is merged can be chosen with `--merge`, as either
+/// `keep-best` (the default), `replace` or `skip`.
+///
+/// Returns whether one of the commands was given.
+pub fn run_command_line(persistence: &Persistence) -> bool {
+    let args: Vec<OsString> = std::env::args_os().skip(1).collect();
+
+    if let Some(path) = find_flag_value(args.clone(), "--export") {
+        let result = export(persistence)
+            .and_then(|json| std::fs::write(&path, json).context("Can't write file"));
+        match result {
+            Ok(()) => println!("Exported data to {}", Path::new(&path).display()),
+            Err(err) => println!("Can't export data: {err}"),
+        }
+        true
+    } else if let Some(path) = find_flag_value(args.clone(), "--import") {
+        let strategy = match find_flag_value(args, "--merge") {
+            None => MergeStrategy::KeepBest,
+            Some(flag) => match flag.to_str().and_then(MergeStrategy::from_flag) {
+                Some(strategy) => strategy,
+                None => {
+                    println!("Unknown merge option: {}", flag.to_string_lossy());
+                    return true;
+                }
+            },
+        };
+
+        let result = std::fs::read(&path)
+            .context("Can't read file")
+            .and_then(|bytes| PlayerData::from_backup_json(&bytes));
+        match result {
+            Ok(imported) => {
+                println!("Importing {}", imported.summarize());
+                import(persistence, imported, strategy);
+                println!("Imported data from {}", Path::new(&path).display());
+            }
+            Err(err) => println!("Can't import data: {err}"),
+        }
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::highscores::{GameMode, HighscoreRecord};
+    use crate::sudoku::strategies::arb_game;
+    use crate::sudoku::Difficulty;
+    use proptest::prelude::*;
+
+    fn record(score: u32) -> HighscoreRecord {
+        HighscoreRecord {
+            finished_at: score as u64,
+            score,
+            elapsed_secs: 100.,
+            num_mistakes: 0,
+            num_hints: 0,
+            puzzle_id: score as u64,
+            mode: GameMode::RevealMistakes,
+        }
+    }
+
+    fn with_highscores(scores: &[u32]) -> PlayerData {
+        let mut data = PlayerData::default();
+        for &score in scores {
+            data.highscores.insert(Difficulty::Easy, record(score));
+        }
+        data
+    }
+
+    fn best_score(data: &PlayerData) -> Option<u32> {
+        data.highscores
+            .get(Difficulty::Easy)
+            .and_then(|highscores| highscores.best_scores.first())
+            .map(|record| record.score)
+    }
+
+    fn copy_game(game: &Game) -> Game {
+        Game::from_json(&game.to_json().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn highscores_are_merged_by_strategy() {
+        let merge =
+            |strategy| with_highscores(&[100, 200]).merge(with_highscores(&[300]), strategy);
+
+        assert_eq!(best_score(&merge(MergeStrategy::KeepBest)), Some(300));
+        assert_eq!(best_score(&merge(MergeStrategy::Replace)), Some(300));
+        assert_eq!(best_score(&merge(MergeStrategy::Skip)), Some(200));
+
+        let merged = with_highscores(&[]).merge(with_highscores(&[300]), MergeStrategy::Skip);
+        assert_eq!(best_score(&merged), Some(300));
+
+        let merged = with_highscores(&[100, 200])
+            .merge(with_highscores(&[100, 200]), MergeStrategy::KeepBest);
+        let num_scores = merged
+            .highscores
+            .get(Difficulty::Easy)
+            .map(|highscores| highscores.best_scores.len());
+        assert_eq!(num_scores, Some(2));
+    }
+
+    proptest! {
+        #[test]
+        fn backups_survive_a_round_trip(game in arb_game()) {
+            let mut data = with_highscores(&[100, 200]);
+            data.saved_games.push(game);
+
+            let json = data.to_backup_json().unwrap();
+            let restored = PlayerData::from_backup_json(&json).unwrap();
+            prop_assert_eq!(restored.summarize(), data.summarize());
+            prop_assert_eq!(restored.to_backup_json().unwrap().len(), json.len());
+        }
+
+        #[test]
+        fn games_are_merged_by_strategy(game in arb_game()) {
+            prop_assume!(game.progress() < 1.);
+
+            let mut local = PlayerData::default();
+            let mut local_game = copy_game(&game);
+            local_game.slot = 1;
+            local.saved_games.push(local_game);
+
+            // The other side made more progress in the same slot, and has
+            // another game besides.
+            let mut other = PlayerData::default();
+            let mut other_game = copy_game(&game);
+            other_game.slot = 1;
+            other_game.current = other_game.solution.clone();
+            other.saved_games.push(other_game);
+            let mut new_game = copy_game(&game);
+            new_game.slot = 2;
+            other.saved_games.push(new_game);
+
+            let merge = |local: &PlayerData, other: &PlayerData, strategy| {
+                let local = PlayerData::from_backup_json(&local.to_backup_json().unwrap()).unwrap();
+                let other = PlayerData::from_backup_json(&other.to_backup_json().unwrap()).unwrap();
+                local.merge(other, strategy)
+            };
+
+            let merged = merge(&local, &other, MergeStrategy::KeepBest);
+            prop_assert_eq!(merged.saved_games.len(), 2);
+            prop_assert_eq!(merged.saved_games[0].progress(), 1.);
+
+            let merged = merge(&local, &other, MergeStrategy::Skip);
+            prop_assert_eq!(merged.saved_games.len(), 2);
+            prop_assert_eq!(merged.saved_games[0].progress(), game.progress());
+
+            let mut local = merge(&local, &PlayerData::default(), MergeStrategy::Skip);
+            local.saved_games[0].slot = 3;
+            let merged = merge(&local, &other, MergeStrategy::Replace);
+            let slots: Vec<u64> = merged.slots().collect();
+            prop_assert_eq!(slots, vec![1, 2]);
+        }
+    }
+}
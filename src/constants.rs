@@ -42,6 +42,16 @@ pub const COLOR_TERNARY_BUTTON_BORDER_PRESS: Color = Color::WHITE;
 pub const COLOR_TIMER_BORDER: Color = COLOR_MAIN;
 pub const COLOR_TIMER_TEXT: Color = COLOR_MAIN_DARKEST;
 
+// Modal dialog colors.
+pub const COLOR_MODAL_BACKDROP: Color = Color::srgba(92. / 255., 84. / 255., 30. / 255., 0.6);
+pub const COLOR_MODAL_BACKGROUND: Color = COLOR_CREAM;
+
+// Toast colors.
+pub const COLOR_TOAST_TEXT: Color = Color::WHITE;
+pub const COLOR_TOAST_INFO: Color = COLOR_MAIN;
+pub const COLOR_TOAST_WARNING: Color = COLOR_BAMBOO_SHOOT;
+pub const COLOR_TOAST_ERROR: Color = COLOR_POP_DARK;
+
 // Score color.
 pub const COLOR_SCORE_TEXT: Color = COLOR_POP_DARK;
 
@@ -61,6 +71,19 @@ pub const COLOR_CELL_SELECTION: Color = COLOR_BAMBOO_SHOOT;
 pub const COLOR_CELL_SAME_NUMBER: Color = COLOR_MUSTARD;
 pub const COLOR_CELL_HIGHLIGHT: Color = COLOR_EGGSHELL;
 
+// Branch colors, for the board edges and the numbers filled in on a branch.
+pub const COLOR_BRANCH: Color = Color::srgb(38. / 255., 139. / 255., 160. / 255.);
+
+// Colors that cells can be painted with in color mode.
+pub const COLOR_CELL_PAINT: [Color; 6] = [
+    Color::srgba(235. / 255., 87. / 255., 87. / 255., 0.5),
+    Color::srgba(242. / 255., 153. / 255., 74. / 255., 0.5),
+    Color::srgba(111. / 255., 207. / 255., 151. / 255., 0.5),
+    Color::srgba(86. / 255., 204. / 255., 242. / 255., 0.5),
+    Color::srgba(47. / 255., 128. / 255., 237. / 255., 0.5),
+    Color::srgba(187. / 255., 107. / 255., 217. / 255., 0.5),
+];
+
 // Cell size.
 pub const CELL_SIZE: f32 = 0.111111;
 
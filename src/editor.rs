@@ -0,0 +1,469 @@
+use crate::import::{import_sudoku, ImportedSudoku};
+use crate::{constants::*, game::*, library::Library, persistence::Persistence, sudoku::*};
+use crate::{ui::*, utils::*};
+use crate::{ScreenState, Transition};
+use anyhow::Context;
+use bevy::prelude::*;
+use bevy::tasks::{futures::check_ready, AsyncComputeTaskPool, Task};
+use bevy::window::FileDragAndDrop;
+use std::fs;
+use std::num::NonZeroU8;
+
+const EMPTY_INSTRUCTION: &str = if cfg!(any(target_os = "android", target_os = "ios")) {
+    "Fill in the starting numbers."
+} else {
+    "Fill in the numbers, or drop a photo here."
+};
+
+#[derive(Clone, Component, Copy)]
+pub enum EditorButtonAction {
+    BackToMain,
+    Clear,
+    Save,
+    Play,
+}
+
+#[derive(Component)]
+pub struct EditorStatus;
+
+#[derive(Component)]
+pub struct EditorDetails;
+
+/// The puzzle that is being entered in the editor, along with live feedback
+/// about its solutions.
+#[derive(Default, Resource)]
+pub struct PuzzleEditor {
+    pub puzzle: Sudoku,
+    pub analysis: PuzzleAnalysis,
+
+    /// The analysis that is running in the background, which is replaced
+    /// whenever the puzzle changes.
+    analysis_task: Option<Task<PuzzleAnalysis>>,
+
+    /// The image that is being imported in the background, if any.
+    import_task: Option<Task<anyhow::Result<ImportedSudoku>>>,
+
+    /// Cells that were imported from an image, but may have been recognized
+    /// incorrectly.
+    uncertain_cells: Vec<usize>,
+
+    /// Message about the outcome of the last action, if any.
+    message: Option<&'static str>,
+}
+
+#[derive(Default)]
+pub enum PuzzleAnalysis {
+    #[default]
+    Empty,
+    /// The puzzle is still being analyzed in the background.
+    Pending,
+    NoSolution,
+    Unique {
+        solution: Sudoku,
+        difficulty: Difficulty,
+        /// The canonical hash of the puzzle, see `Sudoku::canonical_hash()`.
+        puzzle_id: u64,
+    },
+    Multiple(Sudoku, Sudoku),
+}
+
+impl PuzzleEditor {
+    /// Fills in a starting number, replacing any number that was there.
+    pub fn set(&mut self, x: u8, y: u8, n: NonZeroU8) {
+        self.puzzle = self.puzzle.set(x, y, n);
+        self.uncertain_cells.retain(|&pos| pos != get_pos(x, y));
+        self.analyze();
+    }
+
+    /// Clears the starting number from a cell.
+    pub fn unset(&mut self, x: u8, y: u8) {
+        self.puzzle = self.puzzle.unset(x, y);
+        self.uncertain_cells.retain(|&pos| pos != get_pos(x, y));
+        self.analyze();
+    }
+
+    /// Replaces the puzzle with one that was recognized in an image.
+    pub fn import(&mut self, imported: ImportedSudoku) {
+        self.puzzle = imported.sudoku;
+        self.uncertain_cells = imported.uncertain_cells;
+        self.analyze();
+
+        if !self.uncertain_cells.is_empty() {
+            self.message = Some("Check the highlighted numbers before you play.");
+        }
+    }
+
+    /// Returns whether the solutions differ in the cell at the given position.
+    ///
+    /// This only happens if the puzzle has multiple solutions.
+    pub fn is_ambiguous(&self, pos: usize) -> bool {
+        match &self.analysis {
+            PuzzleAnalysis::Multiple(a, b) => a.get_by_pos(pos) != b.get_by_pos(pos),
+            _ => false,
+        }
+    }
+
+    /// Returns whether the number in the cell at the given position may have
+    /// been imported incorrectly.
+    pub fn is_uncertain(&self, pos: usize) -> bool {
+        self.uncertain_cells.contains(&pos)
+    }
+
+    /// Returns a game that can be used to show the puzzle on the board.
+    ///
+    /// Where the puzzle has multiple solutions, the numbers from two of the
+    /// solutions are shown as notes.
+    pub fn to_board_game(&self) -> Game {
+        let mut notes = Notes::new();
+        let solution = match &self.analysis {
+            PuzzleAnalysis::Unique { solution, .. } => solution.clone(),
+            PuzzleAnalysis::Multiple(a, b) => {
+                for pos in 0..81 {
+                    if let (Some(n_a), Some(n_b)) = (a.get_by_pos(pos), b.get_by_pos(pos)) {
+                        if n_a != n_b {
+                            let (x, y) = get_x_and_y_from_pos(pos);
+                            notes.set(x, y, n_a);
+                            notes.set(x, y, n_b);
+                        }
+                    }
+                }
+                a.clone()
+            }
+            PuzzleAnalysis::Empty | PuzzleAnalysis::Pending | PuzzleAnalysis::NoSolution => {
+                self.puzzle.clone()
+            }
+        };
+
+        Game {
+            start: self.puzzle.clone(),
+            current: self.puzzle.clone(),
+            solution,
+            notes,
+            ..default()
+        }
+    }
+
+    fn analyze(&mut self) {
+        self.message = None;
+
+        if self.puzzle.num_filled_cells() == 0 {
+            self.analysis = PuzzleAnalysis::Empty;
+            self.analysis_task = None;
+            return;
+        }
+
+        // Looking for solutions of a sparse puzzle takes a while, which would
+        // hold up typing. Dropping the previous task cancels it.
+        let puzzle = self.puzzle.clone();
+        self.analysis = PuzzleAnalysis::Pending;
+        self.analysis_task =
+            Some(AsyncComputeTaskPool::get().spawn(async move { analyze_puzzle(puzzle) }));
+    }
+
+    fn get_status(&self) -> &'static str {
+        match self.analysis {
+            PuzzleAnalysis::Empty => "",
+            PuzzleAnalysis::Pending => "Checking...",
+            PuzzleAnalysis::NoSolution => "No solution",
+            PuzzleAnalysis::Unique { .. } => "Unique solution",
+            PuzzleAnalysis::Multiple(..) => "Multiple solutions",
+        }
+    }
+
+    fn get_details(&self) -> String {
+        if let Some(message) = self.message {
+            return message.to_owned();
+        }
+
+        match self.analysis {
+            PuzzleAnalysis::Empty => EMPTY_INSTRUCTION.to_owned(),
+            PuzzleAnalysis::Pending => String::new(),
+            PuzzleAnalysis::NoSolution => "Some numbers can't be right.".to_owned(),
+            PuzzleAnalysis::Unique { difficulty, .. } => {
+                format!("Difficulty: {}", format_difficulty(difficulty))
+            }
+            PuzzleAnalysis::Multiple(..) => {
+                "The highlighted cells have more than one option.".to_owned()
+            }
+        }
+    }
+}
+
+/// Finds out whether the puzzle has a unique solution, and how hard it is.
+fn analyze_puzzle(puzzle: Sudoku) -> PuzzleAnalysis {
+    let mut solutions = puzzle.find_solutions(2).into_iter();
+    match (solutions.next(), solutions.next()) {
+        (None, _) => PuzzleAnalysis::NoSolution,
+        (Some(solution), None) => PuzzleAnalysis::Unique {
+            difficulty: rate_difficulty(puzzle.clone()).unwrap_or_default(),
+            puzzle_id: puzzle.canonical_hash(),
+            solution,
+        },
+        (Some(a), Some(b)) => PuzzleAnalysis::Multiple(a, b),
+    }
+}
+
+pub fn editor_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
+    use EditorButtonAction::*;
+
+    fragment5(
+        row(
+            preferred_size(Val::Percent(100.), Val::Pixel(80)),
+            (),
+            text(
+                "Create a Puzzle",
+                (
+                    justify(Justify::Center),
+                    font_bold,
+                    font_size(50.),
+                    text_color(COLOR_MAIN_DARKER),
+                ),
+            ),
+        ),
+        // Back and clear buttons.
+        button_row(fragment(
+            secondary_button(
+                BackToMain,
+                game_screen_top_row_button_size,
+                text("Back", button_text),
+            ),
+            secondary_button(
+                Clear,
+                game_screen_top_row_button_size,
+                text("Clear", button_text),
+            ),
+        )),
+        // Live feedback.
+        column(
+            preferred_size(Val::Percent(100.), Val::Pixel(80)),
+            (),
+            fragment(
+                row(
+                    available_size,
+                    (),
+                    text_t(
+                        EditorStatus,
+                        "",
+                        (font_bold, font_size(41.7), text_color(COLOR_MAIN_DARKER)),
+                    ),
+                ),
+                row(
+                    available_size,
+                    (),
+                    text_t(
+                        EditorDetails,
+                        EMPTY_INSTRUCTION,
+                        (font_medium, font_size(33.3), text_color(COLOR_MAIN_DARKER)),
+                    ),
+                ),
+            ),
+        ),
+        board(ScreenState::Editor),
+        // Save and play buttons.
+        button_row(fragment(
+            secondary_button(
+                Save,
+                game_screen_top_row_button_size,
+                text("Save", button_text),
+            ),
+            selected_button(
+                Play,
+                game_screen_top_row_button_size,
+                text("Play", button_text),
+            ),
+        )),
+    )
+}
+
+fn button_row<B: Bundle>(
+    child: impl Into<BundleWithChildren<B>>,
+) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    row(
+        (
+            game_screen_top_row_size,
+            margin(Size::new(Val::None, Val::Pixel(15))),
+        ),
+        gap(Val::Auto),
+        child,
+    )
+}
+
+pub fn editor_keyboard_input(
+    mut editor: ResMut<PuzzleEditor>,
+    mut selection: ResMut<Selection>,
+    keys: Res<ButtonInput<KeyCode>>,
+) {
+    for key in keys.get_just_pressed() {
+        use KeyCode::*;
+        match key {
+            ArrowUp => move_selection_relative(&mut selection, 0, -1),
+            ArrowRight => move_selection_relative(&mut selection, 1, 0),
+            ArrowDown => move_selection_relative(&mut selection, 0, 1),
+            ArrowLeft => move_selection_relative(&mut selection, -1, 0),
+
+            Backspace | Delete => {
+                if let Some((x, y)) = selection.selected_cell {
+                    editor.unset(x, y);
+                }
+            }
+
+            key => {
+                if let (Some(n), Some((x, y))) = (get_number_for_key(*key), selection.selected_cell)
+                {
+                    editor.set(x, y, n);
+                }
+            }
+        }
+    }
+}
+
+pub fn editor_button_actions(
+    mut editor: ResMut<PuzzleEditor>,
+    mut library: ResMut<Library>,
+    mut selection: ResMut<Selection>,
+    mut transitions: MessageWriter<Transition>,
+    persistence: Res<Persistence>,
+    query: Query<(&Interaction, &EditorButtonAction), Changed<Interaction>>,
+) {
+    for (interaction, action) in &query {
+        if *interaction == Interaction::Pressed {
+            match action {
+                EditorButtonAction::BackToMain => {
+                    transitions.write(Transition::Exit);
+                }
+                EditorButtonAction::Clear => {
+                    *editor = PuzzleEditor::default();
+                    selection.clear();
+                }
+                EditorButtonAction::Save => {
+                    editor.message = Some(match editor.analysis {
+                        PuzzleAnalysis::Unique {
+                            difficulty,
+                            puzzle_id,
+                            ..
+                        } => {
+                            if library.add(
+                                &persistence,
+                                puzzle_id,
+                                editor.puzzle.clone(),
+                                difficulty,
+                            ) {
+                                "Saved to your library."
+                            } else {
+                                "This puzzle is already in your library."
+                            }
+                        }
+                        PuzzleAnalysis::Pending => "Still checking the puzzle.",
+                        _ => "Only puzzles with a unique solution can be saved.",
+                    });
+                }
+                EditorButtonAction::Play => match editor.analysis {
+                    PuzzleAnalysis::Unique { .. } => {
+                        transitions.write(Transition::PlayPuzzle);
+                    }
+                    PuzzleAnalysis::Pending => {
+                        editor.message = Some("Still checking the puzzle.");
+                    }
+                    _ => {
+                        editor.message = Some("Only puzzles with a unique solution can be played.");
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Shows the analysis of the puzzle once it's done.
+pub fn on_editor_analyzed(mut editor: ResMut<PuzzleEditor>) {
+    // Polling shouldn't trigger change detection, or the board would be
+    // rerendered every frame.
+    let Some(task) = editor.bypass_change_detection().analysis_task.as_mut() else {
+        return;
+    };
+    let Some(analysis) = check_ready(task) else {
+        return;
+    };
+
+    editor.analysis_task = None;
+    editor.analysis = analysis;
+}
+
+/// Shows the puzzle on the board whenever it changes.
+pub fn on_editor_changed(
+    mut game: ResMut<Game>,
+    editor: Res<PuzzleEditor>,
+    screen: Res<State<ScreenState>>,
+) {
+    // The game is only borrowed for showing the puzzle while the editor is
+    // open. See `Transition::OpenEditor`.
+    if editor.is_changed() && screen.get() == &ScreenState::Editor {
+        *game = editor.to_board_game();
+    }
+}
+
+pub fn render_editor_status(
+    mut status: Query<&mut Text2d, (With<EditorStatus>, Without<EditorDetails>)>,
+    mut details: Query<&mut Text2d, (With<EditorDetails>, Without<EditorStatus>)>,
+    editor: Res<PuzzleEditor>,
+) {
+    if !editor.is_changed() {
+        return;
+    }
+
+    for mut status in &mut status {
+        status.0 = editor.get_status().to_owned();
+    }
+
+    for mut details in &mut details {
+        details.0 = editor.get_details();
+    }
+}
+
+/// Imports a puzzle from a photo or screenshot that is dropped onto the
+/// window.
+///
+/// This is the only entry point for importing, so it only works on desktop.
+/// Decoding and recognizing the image takes a while, so it happens in the
+/// background, see `on_image_imported()`.
+pub fn on_file_drop(mut drops: MessageReader<FileDragAndDrop>, mut editor: ResMut<PuzzleEditor>) {
+    for file_drop in drops.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = file_drop else {
+            continue;
+        };
+
+        // Only the last image that was dropped is imported, since dropping
+        // the previous task cancels it.
+        let path = path_buf.clone();
+        editor.import_task = Some(AsyncComputeTaskPool::get().spawn(async move {
+            fs::read(path)
+                .context("Can't read file")
+                .and_then(|bytes| import_sudoku(&bytes))
+        }));
+        editor.message = Some("Looking for a puzzle in the image...");
+    }
+}
+
+/// Replaces the puzzle with the one that was found in a dropped image, once
+/// it has been recognized.
+pub fn on_image_imported(mut editor: ResMut<PuzzleEditor>, mut selection: ResMut<Selection>) {
+    // Polling shouldn't trigger change detection, or the board would be
+    // rerendered every frame.
+    let Some(task) = editor.bypass_change_detection().import_task.as_mut() else {
+        return;
+    };
+    let Some(result) = check_ready(task) else {
+        return;
+    };
+
+    editor.import_task = None;
+    match result {
+        Ok(imported) => {
+            editor.import(imported);
+            selection.clear();
+        }
+        Err(err) => {
+            warn!("Can't import puzzle: {err}");
+            editor.message = Some("Couldn't find a puzzle in that image.");
+        }
+    }
+}
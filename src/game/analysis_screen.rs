@@ -0,0 +1,228 @@
+use bevy::prelude::*;
+
+use crate::sudoku::{get_pos, get_x_and_y_from_pos, Analysis, Game, Replay, TechniqueAnalysis};
+use crate::{constants::*, ui::*, utils::*, ResourceBag, ScreenState};
+
+/// The number of cells the player spent the longest on that are listed.
+const NUM_STALLS: usize = 3;
+
+/// Room for every technique, plus the guesses.
+const NUM_TECHNIQUE_ROWS: usize = 7;
+
+#[derive(Component)]
+pub enum AnalysisButtonAction {
+    Back,
+}
+
+/// A cell in the heatmap, showing the time spent on the cell at the given
+/// position.
+#[derive(Component)]
+pub struct HeatmapCell(usize);
+
+#[derive(Clone, Copy, Component)]
+pub enum AnalysisText {
+    /// The cell with the given rank among the ones the player spent the
+    /// longest on.
+    Stall(usize),
+    /// The technique with the given index, from the easiest to the hardest.
+    Technique(usize),
+}
+
+pub fn analysis_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
+    fragment6(
+        row(
+            fixed_size(Val::Percent(100.), Val::Vmin(10.)),
+            (),
+            text("Analysis", title_text_style),
+        ),
+        heatmap(),
+        row(
+            fixed_size(Val::Percent(100.), Val::Vmin(6.)),
+            (),
+            text(
+                "The darker the cell, the longer it took to fill in.",
+                detail_text_style,
+            ),
+        ),
+        analysis_section("Longest stalls:", NUM_STALLS, AnalysisText::Stall),
+        analysis_section(
+            "Time per technique:",
+            NUM_TECHNIQUE_ROWS,
+            AnalysisText::Technique,
+        ),
+        column(
+            available_size,
+            padding(Sides::vertical(Val::Auto)),
+            secondary_button(
+                AnalysisButtonAction::Back,
+                button_size_settings,
+                text("Back", button_text),
+            ),
+        ),
+    )
+}
+
+fn heatmap() -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    row(
+        (
+            fixed_size(Val::Vmin(45.), Val::Vmin(45.)),
+            align_self(Alignment::Centered),
+        ),
+        (
+            background_color(COLOR_BOARD_LINE_MEDIUM),
+            gap(Val::Pixel(1)),
+            padding(Sides::all(Val::Pixel(1))),
+        ),
+        |props: &Props, spawner: &mut ChildSpawnerCommands| {
+            for x in 0..9 {
+                spawner.spawn_with_children(
+                    props,
+                    column(
+                        available_size,
+                        gap(Val::Pixel(1)),
+                        move |_props: &Props, spawner: &mut ChildSpawnerCommands| {
+                            for y in 0..9 {
+                                spawner.spawn((
+                                    HeatmapCell(get_pos(x, y)),
+                                    rect(COLOR_CREAM, available_size),
+                                ));
+                            }
+                        },
+                    ),
+                );
+            }
+        },
+    )
+}
+
+fn analysis_section(
+    title: &'static str,
+    num_rows: usize,
+    marker: fn(usize) -> AnalysisText,
+) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    column(
+        available_size,
+        (),
+        fragment(
+            row(
+                fixed_size(Val::Percent(100.), Val::Vmin(7.)),
+                (),
+                text(title, title_text_style),
+            ),
+            move |props: &Props, spawner: &mut ChildSpawnerCommands| {
+                for index in 0..num_rows {
+                    spawner.spawn_with_children(
+                        props,
+                        row(
+                            fixed_size(Val::Percent(100.), Val::Vmin(5.)),
+                            (),
+                            text_t(marker(index), "", detail_text_style),
+                        ),
+                    );
+                }
+            },
+        ),
+    )
+}
+
+fn title_text_style(bundle: &mut FlexTextBundle, resources: &ResourceBag) {
+    button_text_size(bundle, resources);
+    font_bold(bundle, resources);
+    text_color(COLOR_MAIN_DARKER)(bundle, resources);
+}
+
+fn detail_text_style(bundle: &mut FlexTextBundle, resources: &ResourceBag) {
+    font_medium(bundle, resources);
+    font_size(25.)(bundle, resources);
+    text_color(COLOR_MAIN_DARKER)(bundle, resources);
+}
+
+pub fn analysis_button_actions(
+    query: Query<(&Interaction, &AnalysisButtonAction), (Changed<Interaction>, With<Button>)>,
+    mut screen_state: ResMut<NextState<ScreenState>>,
+) {
+    for (interaction, action) in &query {
+        if *interaction == Interaction::Pressed {
+            match action {
+                AnalysisButtonAction::Back => screen_state.set(ScreenState::Highscores),
+            }
+        }
+    }
+}
+
+/// Analyzes the finished game whenever the analysis is opened.
+pub fn render_analysis(
+    mut cells: Query<(&HeatmapCell, &mut Sprite)>,
+    mut texts: Query<(&mut Text2d, &AnalysisText)>,
+    screen_state: Res<State<ScreenState>>,
+    game: Res<Game>,
+) {
+    if !screen_state.is_changed() || screen_state.get() != &ScreenState::Analysis {
+        return;
+    }
+
+    let analysis = Analysis::new(&Replay::new(&game));
+
+    let max_secs_spent = analysis.max_secs_spent();
+    for (cell, mut sprite) in &mut cells {
+        let cell = &analysis.cells[cell.0];
+        sprite.color = if cell.is_given {
+            COLOR_BOARD_LINE_THIN
+        } else if max_secs_spent > 0. {
+            COLOR_CREAM.mix(&COLOR_POP_DARK, cell.secs_spent / max_secs_spent)
+        } else {
+            COLOR_CREAM
+        };
+    }
+
+    let stalls = analysis.longest_stalls(NUM_STALLS);
+    let techniques = analysis.techniques();
+    for (mut text, kind) in &mut texts {
+        text.0 = match *kind {
+            AnalysisText::Stall(rank) => stalls
+                .get(rank)
+                .map(|&pos| format_stall(&analysis, pos))
+                .unwrap_or_default(),
+            AnalysisText::Technique(index) => techniques
+                .get(index)
+                .map(format_technique_analysis)
+                .unwrap_or_default(),
+        };
+    }
+}
+
+fn format_stall(analysis: &Analysis, pos: usize) -> String {
+    let (x, y) = get_x_and_y_from_pos(pos);
+    let cell = &analysis.cells[pos];
+    format!(
+        "Row {}, column {}: {}, needed {}, {}",
+        y + 1,
+        x + 1,
+        format_time(cell.secs_spent),
+        cell.technique.map_or("a guess", format_technique),
+        format_mistakes(cell.num_mistakes)
+    )
+}
+
+fn format_technique_analysis(analysis: &TechniqueAnalysis) -> String {
+    format!(
+        "{}: {} {} in {}, {}",
+        analysis.technique.map_or("Guesses", format_technique),
+        analysis.num_cells,
+        if analysis.num_cells == 1 {
+            "cell"
+        } else {
+            "cells"
+        },
+        format_time(analysis.secs_spent),
+        format_mistakes(analysis.num_mistakes)
+    )
+}
+
+fn format_mistakes(num_mistakes: u32) -> String {
+    match num_mistakes {
+        0 => "no mistakes".to_owned(),
+        1 => "1 mistake".to_owned(),
+        n => format!("{n} mistakes"),
+    }
+}
@@ -1,12 +1,16 @@
 use bevy::prelude::*;
 
-use crate::{constants::*, ui::*, ScreenState};
+use crate::{constants::*, ui::*, Game, ScreenState};
 
 use super::{board_numbers, wheel::wheel};
 
 #[derive(Component)]
 pub struct Board;
 
+/// The outer lines of the board, which change color while on a branch.
+#[derive(Component)]
+pub struct BoardEdge;
+
 #[derive(Clone, Component, Default)]
 pub struct MistakeCellBorders;
 
@@ -17,12 +21,26 @@ pub struct MistakeCellBordersBundle {
     pub visibility: Visibility,
 }
 
+/// Borders around a cell that breaks the rules, shown if conflicts are
+/// highlighted.
+#[derive(Component)]
+pub struct ConflictCellBorders {
+    pub x: u8,
+    pub y: u8,
+}
+
 pub fn board(screen: ScreenState) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
     row_t(
         (Board, screen),
         board_size,
         (),
-        fragment4(board_lines, board_numbers, wheel(screen), mistake_borders()),
+        fragment5(
+            board_lines,
+            board_numbers,
+            wheel(screen),
+            mistake_borders(),
+            conflict_borders,
+        ),
     )
 }
 
@@ -41,7 +59,7 @@ fn board_lines(_props: &Props, spawner: &mut ChildSpawnerCommands) {
     use Orientation::*;
     use Thickness::*;
 
-    spawner.spawn(line(0, Horizontal, Thick));
+    spawner.spawn((line(0, Horizontal, Thick), BoardEdge));
     spawner.spawn(line(1, Horizontal, Thin));
     spawner.spawn(line(2, Horizontal, Thin));
     spawner.spawn(line(3, Horizontal, Medium));
@@ -50,8 +68,8 @@ fn board_lines(_props: &Props, spawner: &mut ChildSpawnerCommands) {
     spawner.spawn(line(6, Horizontal, Medium));
     spawner.spawn(line(7, Horizontal, Thin));
     spawner.spawn(line(8, Horizontal, Thin));
-    spawner.spawn(line(9, Horizontal, Thick));
-    spawner.spawn(line(0, Vertical, Thick));
+    spawner.spawn((line(9, Horizontal, Thick), BoardEdge));
+    spawner.spawn((line(0, Vertical, Thick), BoardEdge));
     spawner.spawn(line(1, Vertical, Thin));
     spawner.spawn(line(2, Vertical, Thin));
     spawner.spawn(line(3, Vertical, Medium));
@@ -60,7 +78,7 @@ fn board_lines(_props: &Props, spawner: &mut ChildSpawnerCommands) {
     spawner.spawn(line(6, Vertical, Medium));
     spawner.spawn(line(7, Vertical, Thin));
     spawner.spawn(line(8, Vertical, Thin));
-    spawner.spawn(line(9, Vertical, Thick));
+    spawner.spawn((line(9, Vertical, Thick), BoardEdge));
 }
 
 fn line(n: u8, orientation: Orientation, thickness: Thickness) -> impl Bundle {
@@ -92,6 +110,20 @@ fn line(n: u8, orientation: Orientation, thickness: Thickness) -> impl Bundle {
     )
 }
 
+pub(super) fn render_board_edges(mut edges: Query<&mut Sprite, With<BoardEdge>>, game: Res<Game>) {
+    let color = if game.is_branching() {
+        COLOR_BRANCH
+    } else {
+        COLOR_BOARD_LINE_THICK
+    };
+
+    for mut sprite in &mut edges {
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
 fn mistake_borders() -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
     let bundle = MistakeCellBordersBundle {
         transform: Transform::from_translation(Vec3::new(0., 0., 8.)),
@@ -99,14 +131,34 @@ fn mistake_borders() -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerComma
         ..default()
     };
 
-    let spawn_children = fragment4(
+    (bundle, mistake_lines())
+}
+
+fn conflict_borders(props: &Props, spawner: &mut ChildSpawnerCommands) {
+    for y in 0..9 {
+        for x in 0..9 {
+            let bundle = (
+                ConflictCellBorders { x, y },
+                Transform::from_translation(Vec3::new(
+                    (x as f32 - 4.) * CELL_SIZE,
+                    -(y as f32 - 4.) * CELL_SIZE,
+                    8.,
+                )),
+                Visibility::Hidden,
+            );
+
+            spawner.spawn_with_children(props, (bundle, mistake_lines()));
+        }
+    }
+}
+
+fn mistake_lines() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
+    fragment4(
         mistake_line(-0.5, Orientation::Horizontal),
         mistake_line(0.5, Orientation::Horizontal),
         mistake_line(-0.5, Orientation::Vertical),
         mistake_line(0.5, Orientation::Vertical),
-    );
-
-    (bundle, spawn_children)
+    )
 }
 
 fn mistake_line(edge: f32, orientation: Orientation) -> impl Bundle {
@@ -1,15 +1,26 @@
 use std::num::NonZeroU8;
 
 use bevy::prelude::*;
+use bevy::sprite::Anchor;
 
-use crate::{constants::*, sudoku::*, ui::*, utils::*, Fonts, ScreenSizing, Settings};
+use crate::editor::PuzzleEditor;
+use crate::{constants::*, sudoku::*, ui::*, utils::*, Fonts, ScreenSizing, ScreenState, Settings};
 
-use super::{MistakeCellBorders, Note, NoteAnimationKind, Number, Selection};
+use super::{
+    BoardCheck, CellColorLayer, ConflictCellBorders, CornerNote, MistakeCellBorders, Note,
+    NoteAnimationKind, Number, Selection,
+};
 
 const NUMBER_FONT_SIZE: f32 = 66.7;
 const NUMBER_FONT_SIZE_IPAD: f32 = 83.3;
 const NOTE_FONT_SIZE: f32 = 25.;
 const NOTE_FONT_SIZE_IPAD: f32 = 33.3;
+const CORNER_NOTE_FONT_SIZE: f32 = 18.3;
+const CORNER_NOTE_FONT_SIZE_IPAD: f32 = 25.;
+
+/// The corner note slots for every column and row within a cell, such that
+/// the corners are filled before the edges.
+const CORNER_NOTE_SLOTS: [[usize; 3]; 3] = [[0, 6, 2], [4, 8, 5], [1, 7, 3]];
 
 #[derive(Clone, Copy)]
 pub(super) enum CellHighlightKind {
@@ -82,6 +93,14 @@ fn cell(x: u8, y: u8) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerComm
         );
         let note_color = Color::NONE;
 
+        // The color layer is not a flex item, so it simply covers the cell,
+        // between its highlight and its contents.
+        spawner.spawn((
+            CellColorLayer(x, y),
+            Sprite::from_color(get_cell_paint_color(game, x, y), Vec2::ONE),
+            Transform::from_xyz(0., 0., 0.5),
+        ));
+
         spawner
             .spawn(FlexBundle::new(
                 FlexItemStyle::available_size()
@@ -116,6 +135,51 @@ fn cell(x: u8, y: u8) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerComm
                     }
                 });
         }
+
+        let corner_note_font = TextFont::from(resources.fonts.medium.clone()).with_font_size(
+            if resources.screen_sizing.is_tablet() {
+                CORNER_NOTE_FONT_SIZE_IPAD
+            } else {
+                CORNER_NOTE_FONT_SIZE
+            },
+        );
+
+        spawner
+            .spawn(FlexBundle::new(
+                FlexItemStyle::available_size().without_occupying_space(),
+                FlexContainerStyle::row(),
+            ))
+            .with_children(|corner_notes| {
+                for (slots, anchor) in CORNER_NOTE_SLOTS.into_iter().zip([
+                    Anchor::CENTER_LEFT,
+                    Anchor::CENTER,
+                    Anchor::CENTER_RIGHT,
+                ]) {
+                    corner_notes
+                        .spawn(FlexBundle::new(
+                            FlexItemStyle::available_size(),
+                            FlexContainerStyle::default(),
+                        ))
+                        .with_children(|corner_note_column| {
+                            for slot in slots {
+                                corner_note_column
+                                    .spawn(FlexBundle::new(
+                                        FlexItemStyle::available_size(),
+                                        FlexContainerStyle::default(),
+                                    ))
+                                    .with_children(|corner_note_cell| {
+                                        corner_note_cell.spawn(corner_note(
+                                            x,
+                                            y,
+                                            slot,
+                                            anchor,
+                                            corner_note_font.clone(),
+                                        ));
+                                    });
+                            }
+                        });
+                }
+            });
     };
 
     (bundle, spawn_children)
@@ -144,6 +208,15 @@ fn note(x: u8, y: u8, n: NonZeroU8, note_font: TextFont, note_color: Color) -> i
     (Note::new(x, y, n), text_bundle)
 }
 
+fn corner_note(x: u8, y: u8, slot: usize, anchor: Anchor, font: TextFont) -> impl Bundle {
+    let mut text_bundle = FlexTextBundle::from_text("");
+    text_bundle.anchor = anchor;
+    text_bundle.color = Color::NONE.into();
+    text_bundle.font = font;
+
+    (CornerNote { x, y, slot }, text_bundle)
+}
+
 pub(super) fn render_numbers(
     mut numbers: Query<(&Number, &mut Text2d, &mut TextColor, &mut TextFont)>,
     fonts: Res<Fonts>,
@@ -175,16 +248,26 @@ pub(super) fn render_numbers(
 }
 
 fn get_number_color(game: &Game, settings: &Settings, x: u8, y: u8) -> Color {
-    if settings.show_mistakes {
+    // Numbers that were filled in on a branch stand out until it's committed.
+    let is_on_branch = game
+        .checkpoints
+        .first()
+        .is_some_and(|checkpoint| checkpoint.current.get(x, y) != game.current.get(x, y));
+
+    if settings.reveals_mistakes() {
         // If we show mistakes, there's no reason to visually differentiate
         // between starting numbers and numbers filled in correctly.
         if game.current.get(x, y) != game.solution.get(x, y) {
             COLOR_POP_DARK
+        } else if is_on_branch {
+            COLOR_BRANCH
         } else {
             Color::BLACK
         }
     } else if game.start.has(x, y) {
         Color::BLACK
+    } else if is_on_branch {
+        COLOR_BRANCH
     } else {
         Color::linear_rgb(0., 0., 1.)
     }
@@ -203,7 +286,8 @@ pub(super) fn render_notes(
 
         let current_color = text_color.0;
         let new_color =
-            if settings.show_mistakes && game.mistakes.has(x, y, n) && !game.current.has(x, y) {
+            if settings.reveals_mistakes() && game.mistakes.has(x, y, n) && !game.current.has(x, y)
+            {
                 COLOR_POP_DARK
             } else if note.animation_kind == Some(NoteAnimationKind::MistakeInCell) {
                 let (ratio, _) = get_mistake_animation_ratio(note.animation_timer);
@@ -235,11 +319,48 @@ pub(super) fn render_notes(
     }
 }
 
+pub(super) fn render_corner_notes(
+    mut corner_notes: Query<(&CornerNote, &mut Text2d, &mut TextColor)>,
+    game: Res<Game>,
+    highlights: Res<Highlights>,
+) {
+    if !game.is_changed() && !highlights.is_changed() {
+        return;
+    }
+
+    for (corner_note, mut text, mut text_color) in &mut corner_notes {
+        let CornerNote { x, y, slot } = *corner_note;
+
+        let n = if game.current.has(x, y) {
+            None
+        } else {
+            (1..=9)
+                .filter_map(NonZeroU8::new)
+                .filter(|&n| game.corner_notes.has(x, y, n))
+                .nth(slot)
+        };
+
+        let (new_text, new_color) = match n {
+            Some(n) if highlights.selected_number == Some(n) => (n.to_string(), COLOR_POP_FOCUS),
+            Some(n) => (n.to_string(), COLOR_MAIN_DARKER),
+            None => (String::new(), Color::NONE),
+        };
+        if text.0 != new_text {
+            text.0 = new_text;
+        }
+        if text_color.0 != new_color {
+            text_color.0 = new_color;
+        }
+    }
+}
+
 #[derive(Resource)]
 pub(super) struct Highlights {
     cell_highlights: [Option<CellHighlightKind>; 81],
     note_highlights: [Option<NoteHighlightKind>; 81],
     selected_number: Option<NonZeroU8>,
+    wrong_notes: Notes,
+    conflicts: Vec<(u8, u8)>,
 }
 
 impl Default for Highlights {
@@ -248,6 +369,8 @@ impl Default for Highlights {
             cell_highlights: [None; 81],
             note_highlights: [None; 81],
             selected_number: None,
+            wrong_notes: Notes::default(),
+            conflicts: Vec::new(),
         }
     }
 }
@@ -255,10 +378,17 @@ impl Default for Highlights {
 pub(super) fn calculate_highlights(
     mut highlights_resource: ResMut<Highlights>,
     game: Res<Game>,
+    editor: Res<PuzzleEditor>,
+    screen: Res<State<ScreenState>>,
     settings: Res<Settings>,
     selection: Res<Selection>,
+    board_check: Res<BoardCheck>,
 ) {
-    if !game.is_changed() && !selection.is_changed() && !settings.is_changed() {
+    if !game.is_changed()
+        && !selection.is_changed()
+        && !settings.is_changed()
+        && !board_check.is_changed()
+    {
         return;
     }
 
@@ -276,7 +406,7 @@ pub(super) fn calculate_highlights(
             // Find all the cells with notes or mistakes containing the same number.
             for (pos, highlight) in note_highlights.iter_mut().enumerate() {
                 let (x, y) = get_x_and_y_from_pos(pos);
-                if settings.show_mistakes && game.mistakes.has(x, y, n) {
+                if settings.reveals_mistakes() && game.mistakes.has(x, y, n) {
                     *highlight = Some(NoteHighlightKind::Mistake);
                 } else if game.notes.has(x, y, n) {
                     *highlight = Some(NoteHighlightKind::Note);
@@ -314,17 +444,100 @@ pub(super) fn calculate_highlights(
             cell_highlights[selected_pos] = Some(CellHighlightKind::Selection);
         }
     }
+    if !game.is_solved() {
+        for &(x, y) in &selection.extra_cells {
+            cell_highlights[get_pos(x, y)] = Some(CellHighlightKind::Selection);
+        }
+    }
     if let Some((x, y)) = selection.hint {
         cell_highlights[get_pos(x, y)] = Some(CellHighlightKind::Hint);
     }
+    // Point out the problems that were found when checking the notes, for
+    // as long as they haven't been fixed.
+    let mut wrong_notes = Notes::default();
+    if let Some(check) = &selection.notes_check {
+        for pos in 0..81 {
+            let (x, y) = get_x_and_y_from_pos(pos);
+            for n in 1..=9 {
+                let n = NonZeroU8::new(n).unwrap();
+                if check.wrong_notes.has(x, y, n) && game.notes.has(x, y, n) {
+                    wrong_notes.set(x, y, n);
+                }
+            }
+        }
+
+        for &(x, y) in &check.incomplete_cells {
+            let is_incomplete = !game.current.has(x, y)
+                && game.notes.has_some_number(get_pos(x, y))
+                && game
+                    .solution
+                    .get(x, y)
+                    .is_some_and(|n| !game.notes.has(x, y, n));
+            let highlight = &mut cell_highlights[get_pos(x, y)];
+            if is_incomplete && !matches!(highlight, Some(CellHighlightKind::Selection)) {
+                *highlight = Some(CellHighlightKind::Hint);
+            }
+        }
+    }
+    // Point out the wrong numbers that were found when checking the board, for
+    // as long as they haven't been fixed.
+    if let Some(wrong_cells) = &board_check.wrong_cells {
+        if settings.highlight_checked_cells {
+            for &(x, y) in wrong_cells {
+                let is_wrong =
+                    game.current.has(x, y) && game.current.get(x, y) != game.solution.get(x, y);
+                let highlight = &mut cell_highlights[get_pos(x, y)];
+                if is_wrong && !matches!(highlight, Some(CellHighlightKind::Selection)) {
+                    *highlight = Some(CellHighlightKind::Hint);
+                }
+            }
+        }
+    }
+    if screen.get() == &ScreenState::Editor {
+        // Point out the cells where the solutions of the puzzle differ, and
+        // the ones that may have been imported incorrectly.
+        for (pos, highlight) in cell_highlights.iter_mut().enumerate() {
+            if (editor.is_ambiguous(pos) || editor.is_uncertain(pos))
+                && !matches!(highlight, Some(CellHighlightKind::Selection))
+            {
+                *highlight = Some(CellHighlightKind::Hint);
+            }
+        }
+    }
+
+    let conflicts = if settings.highlights_conflicts() && screen.get() != &ScreenState::Editor {
+        game.current.find_conflicts()
+    } else {
+        Vec::new()
+    };
 
     *highlights_resource = Highlights {
         cell_highlights,
         note_highlights,
         selected_number,
+        wrong_notes,
+        conflicts,
     };
 }
 
+pub(super) fn render_conflicts(
+    mut borders: Query<(&ConflictCellBorders, &mut Visibility)>,
+    highlights: Res<Highlights>,
+) {
+    if !highlights.is_changed() {
+        return;
+    }
+
+    for (cell, mut visibility) in &mut borders {
+        let is_conflict = highlights.conflicts.contains(&(cell.x, cell.y));
+        visibility.set_if_neq(if is_conflict {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
 pub(super) fn render_cell_highlights(
     mut cells: Query<(&Number, &mut Sprite)>,
     highlights: Res<Highlights>,
@@ -349,6 +562,29 @@ pub(super) fn render_cell_highlights(
     }
 }
 
+pub(super) fn render_cell_colors(
+    mut layers: Query<(&CellColorLayer, &mut Sprite)>,
+    game: Res<Game>,
+) {
+    if !game.is_changed() {
+        return;
+    }
+
+    for (layer, mut sprite) in &mut layers {
+        let color = get_cell_paint_color(&game, layer.0, layer.1);
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+fn get_cell_paint_color(game: &Game, x: u8, y: u8) -> Color {
+    game.colors
+        .get(x, y)
+        .map(|color| COLOR_CELL_PAINT[color.get() as usize - 1])
+        .unwrap_or(Color::NONE)
+}
+
 pub(super) fn render_note_highlights(
     mut notes: Query<(&mut Note, &mut FlexItemStyle, &mut Sprite)>,
     mut mistake_borders: Query<(&mut Transform, &mut Visibility), With<MistakeCellBorders>>,
@@ -357,7 +593,9 @@ pub(super) fn render_note_highlights(
     time: Res<Time>,
 ) {
     for (note, flex_item_style, mut sprite) in &mut notes {
-        let highlight_kind = if highlights.selected_number == Some(note.n) {
+        let highlight_kind = if highlights.wrong_notes.has(note.x, note.y, note.n) {
+            Some(NoteHighlightKind::Mistake)
+        } else if highlights.selected_number == Some(note.n) {
             highlights.note_highlights[get_pos(note.x, note.y)]
         } else {
             None
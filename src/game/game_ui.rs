@@ -1,16 +1,61 @@
 use super::board;
-use super::mode_slider::mode_slider;
+use super::highscore_screen::{HighscorePage, NewPersonalBest};
+use super::mode_slider::{mode_slider, ModeState};
+use super::{BoardCheck, PaintColor};
+use crate::highscores::{GameMode, HighscoreRecord};
+use crate::persistence::Persistence;
+use crate::statistics::{GameOutcome, Statistics};
 use crate::{constants::*, ui::*, utils::*};
-use crate::{Game, GameTimer, Highscores, Images, ScreenState};
+use crate::{Game, GameTimer, Highscores, Images, ScreenState, Settings};
 use bevy::prelude::*;
+use std::num::NonZeroU8;
 
 #[derive(Component)]
 pub enum UiButtonAction {
     BackToMain,
     GoToSettings,
     Hint,
+    FillNotes,
+    FillMissingNotes,
+    CheckNotes,
+    CheckBoard,
+    DismissBoardCheck,
+    SelectColor(NonZeroU8),
+    ClearColor,
+    AddCheckpoint,
+    CommitBranch,
+    DiscardBranch,
+    Reveal,
+    Restart,
+    GiveUp,
 }
 
+/// The rows with actions that affect the game as a whole, only one of which is
+/// shown at a time.
+#[derive(Clone, Copy, Component, Eq, PartialEq)]
+pub enum ActionRow {
+    Game,
+    Branch,
+    BoardCheck,
+}
+
+/// Reports how many wrong numbers were found when checking the board.
+#[derive(Component)]
+pub struct BoardCheckText;
+
+/// The row with the color palette, which replaces the notes buttons while in
+/// color mode.
+#[derive(Component)]
+pub struct ColorPalette;
+
+/// The row with the buttons for filling in and checking notes, and for
+/// checking the board.
+#[derive(Component)]
+pub struct NotesTools;
+
+#[derive(Component)]
+pub struct PaintSwatch(NonZeroU8);
+
 #[derive(Component)]
 pub struct Score;
 
@@ -21,7 +66,7 @@ pub struct SettingsIcon;
 pub struct Timer;
 
 pub fn game_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
-    fragment5(
+    fragment7(
         // Row with settings icon.
         top_row(fragment(leaf(available_size), settings_icon())),
         // Timer row.
@@ -42,11 +87,174 @@ pub fn game_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
         )),
         // Game board.
         board(ScreenState::Game),
+        // Notes buttons, or the color palette in color mode.
+        fragment(color_palette(), notes_tools()),
+        // Game actions, which change while on a branch, or after checking the
+        // board.
+        fragment3(
+            action_row(
+                ActionRow::Game,
+                fragment4(
+                    action_button(UiButtonAction::AddCheckpoint, "Branch"),
+                    action_button(UiButtonAction::Reveal, "Reveal"),
+                    action_button(UiButtonAction::Restart, "Restart"),
+                    action_button(UiButtonAction::GiveUp, "Give Up"),
+                ),
+            ),
+            action_row(
+                ActionRow::Branch,
+                fragment4(
+                    action_button(UiButtonAction::AddCheckpoint, "Branch"),
+                    action_button(UiButtonAction::Reveal, "Reveal"),
+                    action_button(UiButtonAction::CommitBranch, "Keep"),
+                    action_button(UiButtonAction::DiscardBranch, "Discard"),
+                ),
+            ),
+            action_row(
+                ActionRow::BoardCheck,
+                fragment(
+                    action_message(BoardCheckText),
+                    action_button(UiButtonAction::DismissBoardCheck, "OK"),
+                ),
+            ),
+        ),
         // Mode slider.
         mode_slider,
     )
 }
 
+fn notes_tools() -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    row_t(
+        NotesTools,
+        (
+            game_screen_top_row_size,
+            margin(Size::new(Val::None, Val::Pixel(15))),
+        ),
+        gap(Val::Auto),
+        fragment4(
+            action_button(UiButtonAction::FillNotes, "Fill Notes"),
+            action_button(UiButtonAction::FillMissingNotes, "Add Missing"),
+            action_button(UiButtonAction::CheckNotes, "Check Notes"),
+            action_button(UiButtonAction::CheckBoard, "Check Board"),
+        ),
+    )
+}
+
+/// Returns one of the overlapping rows of game actions. Only the board check
+/// row occupies space, since it comes last.
+fn action_row<B: Bundle>(
+    kind: ActionRow,
+    child: impl Into<BundleWithChildren<B>>,
+) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    let occupies_space = kind == ActionRow::BoardCheck;
+    row_t(
+        kind,
+        (
+            game_screen_top_row_size,
+            margin(Size::new(Val::None, Val::Pixel(15))),
+            move |style: &mut FlexItemStyle| style.occupies_space = occupies_space,
+        ),
+        gap(Val::Auto),
+        child,
+    )
+}
+
+/// Returns the text within a row of game actions, which is filled in when the
+/// row is shown.
+fn action_message(
+    marker: impl Component,
+) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    row(
+        game_screen_action_message_size,
+        (),
+        text_t(
+            marker,
+            "",
+            (
+                font_medium,
+                game_screen_notes_button_text_size,
+                text_color(COLOR_MAIN_DARKER),
+            ),
+        ),
+    )
+}
+
+fn action_button(
+    action: UiButtonAction,
+    label: &'static str,
+) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    ternary_button(
+        action,
+        game_screen_action_button_size,
+        text(
+            label,
+            (
+                button_text_color,
+                button_text_font,
+                game_screen_notes_button_text_size,
+            ),
+        ),
+    )
+}
+
+fn notes_button(
+    action: UiButtonAction,
+    label: &'static str,
+) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    ternary_button(
+        action,
+        game_screen_notes_button_size,
+        text(
+            label,
+            (
+                button_text_color,
+                button_text_font,
+                game_screen_notes_button_text_size,
+            ),
+        ),
+    )
+}
+
+fn color_palette() -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    row_t(
+        ColorPalette,
+        (
+            game_screen_top_row_size,
+            margin(Size::new(Val::None, Val::Pixel(15))),
+            without_occupying_space,
+        ),
+        gap(Val::Auto),
+        fragment7(
+            paint_swatch(1),
+            paint_swatch(2),
+            paint_swatch(3),
+            paint_swatch(4),
+            paint_swatch(5),
+            paint_swatch(6),
+            notes_button(UiButtonAction::ClearColor, "Clear"),
+        ),
+    )
+}
+
+fn paint_swatch(color: u8) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    let color = NonZeroU8::new(color).unwrap();
+    ternary_button(
+        UiButtonAction::SelectColor(color),
+        game_screen_paint_swatch_size,
+        swatch_rect(color),
+    )
+}
+
+fn swatch_rect(color: NonZeroU8) -> impl Bundle {
+    (
+        PaintSwatch(color),
+        rect(
+            COLOR_CELL_PAINT[color.get() as usize - 1],
+            (available_size, margin(Size::all(Val::Pixel(3)))),
+        ),
+    )
+}
+
 fn settings_icon() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
     |props: &Props, cb: &mut ChildSpawnerCommands| {
         cb.spawn_with_children(
@@ -124,8 +332,14 @@ fn score() -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
 pub fn on_score_changed(
     mut score: Query<&mut Text2d, With<Score>>,
     mut highscores: ResMut<Highscores>,
+    mut new_personal_best: ResMut<NewPersonalBest>,
+    mut highscore_page: ResMut<HighscorePage>,
+    mut statistics: ResMut<Statistics>,
+    mut toasts: ResMut<Toasts>,
     mut screen_state: ResMut<NextState<ScreenState>>,
     game: Res<Game>,
+    persistence: Res<Persistence>,
+    settings: Res<Settings>,
 ) {
     if game.is_changed() {
         for mut score_text in &mut score {
@@ -133,7 +347,27 @@ pub fn on_score_changed(
         }
 
         if game.is_solved() {
-            highscores.add(game.score, game.elapsed_secs);
+            let outcome = if game.gave_up {
+                GameOutcome::GaveUp
+            } else {
+                GameOutcome::Solved
+            };
+            statistics.record_end(&persistence, &game, outcome);
+
+            new_personal_best.0 = if game.is_assisted() {
+                false
+            } else {
+                let mode = GameMode::from(settings.mistake_highlighting);
+                highscores.add(
+                    &persistence,
+                    game.difficulty,
+                    HighscoreRecord::new(&game, mode),
+                )
+            };
+            if new_personal_best.0 {
+                toasts.push(Severity::Info, "New personal best!");
+            }
+            *highscore_page = HighscorePage::ThisGame;
             screen_state.set(ScreenState::Highscores);
         }
     }
@@ -147,6 +381,84 @@ pub fn on_time_changed(mut timer: Query<&mut Text2d, With<Timer>>, game_timer: R
     }
 }
 
+/// Shows the color palette in color mode, and the notes buttons otherwise.
+pub fn render_tool_rows(
+    mut palette: Query<&mut Visibility, (With<ColorPalette>, Without<NotesTools>)>,
+    mut notes_tools: Query<&mut Visibility, (With<NotesTools>, Without<ColorPalette>)>,
+    mode: Res<State<ModeState>>,
+) {
+    let is_color_mode = mode.get() == &ModeState::Color;
+
+    for mut visibility in &mut palette {
+        visibility.set_if_neq(if is_color_mode {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+
+    for mut visibility in &mut notes_tools {
+        visibility.set_if_neq(if is_color_mode {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        });
+    }
+}
+
+/// Shows the row of game actions that applies, and reports the outcome of a
+/// board check.
+pub fn render_action_rows(
+    mut rows: Query<(&ActionRow, &mut Visibility)>,
+    mut board_check_text: Query<&mut Text2d, With<BoardCheckText>>,
+    game: Res<Game>,
+    board_check: Res<BoardCheck>,
+) {
+    let board_check_message = board_check.message();
+    let visible_row = if board_check_message.is_some() {
+        ActionRow::BoardCheck
+    } else if game.is_branching() {
+        ActionRow::Branch
+    } else {
+        ActionRow::Game
+    };
+
+    for (row, mut visibility) in &mut rows {
+        visibility.set_if_neq(if *row == visible_row {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+
+    if let Some(message) = board_check_message {
+        for mut text in &mut board_check_text {
+            if text.0 != message {
+                text.0 = message.clone();
+            }
+        }
+    }
+}
+
+/// Makes the swatch of the color that is being painted with stand out.
+pub fn render_paint_colors(
+    mut swatches: Query<(&PaintSwatch, &mut Sprite)>,
+    paint_color: Res<PaintColor>,
+) {
+    for (swatch, mut sprite) in &mut swatches {
+        let color = COLOR_CELL_PAINT[swatch.0.get() as usize - 1];
+        let color = if swatch.0 == paint_color.0 {
+            color.with_alpha(1.)
+        } else {
+            color
+        };
+
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
 fn format_score(score: u32) -> String {
     if score == 1 {
         "1 pt.".to_owned()
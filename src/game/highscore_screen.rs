@@ -1,16 +1,77 @@
 use bevy::{prelude::*, sprite::Anchor};
 
-use crate::{constants::*, ui::*, utils::*, Fortune, Game, Highscores, ScreenState, Transition};
+use crate::sudoku::Difficulty;
+use crate::{constants::*, ui::*, utils::*, Fortune, Game, Highscores, ResourceBag};
+use crate::{ScreenState, Transition};
 
 #[derive(Component)]
 pub enum HighscoreButtonAction {
     Back,
     NewGame,
+    PreviousPage,
+    NextPage,
+    WatchReplay,
+    Analysis,
 }
 
 #[derive(Component)]
 pub struct StatsContainer;
 
+/// The page of the score board that is shown: either the stats of the game
+/// that was just finished, or the highscores of a difficulty.
+#[derive(Clone, Copy, Default, Eq, PartialEq, Resource)]
+pub enum HighscorePage {
+    #[default]
+    ThisGame,
+    Difficulty(Difficulty),
+    /// Highscores from before they were kept per difficulty.
+    Legacy,
+}
+
+impl HighscorePage {
+    /// Returns the page that comes `offset` pages after this one, wrapping
+    /// around at the ends.
+    fn cycle(self, highscores: &Highscores, offset: isize) -> Self {
+        use Difficulty::*;
+        let mut pages = vec![
+            Self::ThisGame,
+            Self::Difficulty(Trivial),
+            Self::Difficulty(Easy),
+            Self::Difficulty(Medium),
+            Self::Difficulty(Advanced),
+            Self::Difficulty(Expert),
+        ];
+        if !highscores.legacy.is_empty() {
+            pages.push(Self::Legacy);
+        }
+
+        let index = pages.iter().position(|page| *page == self).unwrap_or(0);
+        pages[(index as isize + offset).rem_euclid(pages.len() as isize) as usize]
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::ThisGame => "This Game",
+            Self::Difficulty(difficulty) => format_difficulty(difficulty),
+            Self::Legacy => "Earlier",
+        }
+    }
+}
+
+/// Whether the game that was just finished set a new personal best.
+#[derive(Default, Resource)]
+pub struct NewPersonalBest(pub bool);
+
+/// The columns within the score board, only one of which is shown at a time.
+#[derive(Clone, Copy, Component, Eq, PartialEq)]
+pub enum ScoreBoardPage {
+    ThisGame,
+    Records,
+}
+
+#[derive(Component)]
+pub struct HighscorePageTitle;
+
 #[derive(Component)]
 pub struct ScrollText {
     kind: ScrollTextKind,
@@ -45,12 +106,19 @@ enum StatKind {
     Time,
     Mistakes,
     Hints,
+    PersonalBest,
     HighestScore,
     BestTime,
+    /// The score of the record with the given rank on a highscores page.
+    RecordScore(usize),
+    /// The time of the record with the given rank on a highscores page.
+    RecordTime(usize),
+    /// The best time on a highscores page.
+    RecordBestTime,
 }
 
 pub fn highscore_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
-    fragment3(
+    fragment4(
         // Scroll section.
         column(
             available_size,
@@ -96,6 +164,32 @@ pub fn highscore_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
                 ),
             ),
         ),
+        // Page switcher.
+        row(
+            highscore_switcher_size,
+            gap(Val::Auto),
+            fragment3(
+                ternary_button(
+                    HighscoreButtonAction::PreviousPage,
+                    highscore_switcher_button_size,
+                    text("<", button_text),
+                ),
+                row(
+                    highscore_switcher_title_size,
+                    (),
+                    text_t(
+                        HighscorePageTitle,
+                        HighscorePage::ThisGame.title(),
+                        (button_text_size, font_bold, text_color(COLOR_MAIN_DARKER)),
+                    ),
+                ),
+                ternary_button(
+                    HighscoreButtonAction::NextPage,
+                    highscore_switcher_button_size,
+                    text(">", button_text),
+                ),
+            ),
+        ),
         // Wall section.
         row(
             highscore_screen_wall_size,
@@ -116,7 +210,7 @@ pub fn highscore_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
         column(
             available_size,
             padding(Sides::new(Val::None, Val::Auto)),
-            fragment(
+            fragment3(
                 secondary_button(
                     HighscoreButtonAction::Back,
                     (
@@ -133,6 +227,25 @@ pub fn highscore_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
                     ),
                     text("Start a New Game", button_text),
                 ),
+                row(
+                    (
+                        highscore_screen_button_size,
+                        margin(Size::all(Val::Vmin(1.5))),
+                    ),
+                    gap(Val::Auto),
+                    fragment(
+                        secondary_button(
+                            HighscoreButtonAction::WatchReplay,
+                            highscore_screen_half_button_size,
+                            text("Watch Replay", button_text),
+                        ),
+                        secondary_button(
+                            HighscoreButtonAction::Analysis,
+                            highscore_screen_half_button_size,
+                            text("Analysis", button_text),
+                        ),
+                    ),
+                ),
             ),
         ),
     )
@@ -141,8 +254,10 @@ pub fn highscore_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
 pub fn highscore_button_actions(
     mut screen_state: ResMut<NextState<ScreenState>>,
     mut transitions: MessageWriter<Transition>,
+    mut page: ResMut<HighscorePage>,
     query: Query<(&Interaction, &HighscoreButtonAction), (Changed<Interaction>, With<Button>)>,
     game: Res<Game>,
+    highscores: Res<Highscores>,
 ) {
     for (interaction, action) in &query {
         if *interaction == Interaction::Pressed {
@@ -151,6 +266,12 @@ pub fn highscore_button_actions(
                 HighscoreButtonAction::NewGame => {
                     transitions.write(Transition::StartGame(game.difficulty));
                 }
+                HighscoreButtonAction::PreviousPage => *page = page.cycle(&highscores, -1),
+                HighscoreButtonAction::NextPage => *page = page.cycle(&highscores, 1),
+                HighscoreButtonAction::WatchReplay => {
+                    transitions.write(Transition::WatchReplay);
+                }
+                HighscoreButtonAction::Analysis => screen_state.set(ScreenState::Analysis),
             }
         }
     }
@@ -159,47 +280,149 @@ pub fn highscore_button_actions(
 pub fn on_highscores_changed(
     mut stats_query: Query<(&mut Text2d, &StatTextMarker)>,
     props_tuple: PropsTuple,
+    page: Res<HighscorePage>,
+    new_personal_best: Res<NewPersonalBest>,
 ) {
     let highscores: &Res<Highscores> = &props_tuple.1;
-    if !highscores.is_changed() {
+    if !highscores.is_changed() && !page.is_changed() && !new_personal_best.is_changed() {
         return;
     }
 
+    let props = Props::from_tuple(&props_tuple);
     for (mut text, marker) in &mut stats_query {
-        text.0 = get_stat_text(&Props::from_tuple(&props_tuple), marker.kind);
+        text.0 = get_stat_text(&props, *page, new_personal_best.0, marker.kind);
     }
 }
 
-fn get_stat_text(props: &Props, kind: StatKind) -> String {
+/// Shows the column of the score board that belongs to the current page.
+pub fn render_highscore_pages(
+    mut columns: Query<(&ScoreBoardPage, &mut Visibility)>,
+    mut title: Query<&mut Text2d, With<HighscorePageTitle>>,
+    page: Res<HighscorePage>,
+) {
+    if !page.is_changed() {
+        return;
+    }
+
+    let visible_column = match *page {
+        HighscorePage::ThisGame => ScoreBoardPage::ThisGame,
+        HighscorePage::Difficulty(_) | HighscorePage::Legacy => ScoreBoardPage::Records,
+    };
+    for (column, mut visibility) in &mut columns {
+        visibility.set_if_neq(if *column == visible_column {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+
+    for mut text in &mut title {
+        text.0 = page.title().to_owned();
+    }
+}
+
+fn get_stat_text(
+    props: &Props,
+    page: HighscorePage,
+    new_personal_best: bool,
+    kind: StatKind,
+) -> String {
     let Props {
         game, highscores, ..
     } = props;
 
+    let difficulty_highscores = match page {
+        HighscorePage::ThisGame => highscores.get(game.difficulty),
+        HighscorePage::Difficulty(difficulty) => highscores.get(difficulty),
+        HighscorePage::Legacy => None,
+    };
+
     match kind {
+        StatKind::Score if game.gave_up => "Gave up".to_owned(),
         StatKind::Score => game.score.to_string(),
         StatKind::Time => format_time(game.elapsed_secs),
         StatKind::Mistakes => game.num_mistakes.to_string(),
+        StatKind::Hints if game.num_checks > 0 => {
+            format!("{} (+{} checks)", game.num_hints, game.num_checks)
+        }
         StatKind::Hints => game.num_hints.to_string(),
-        StatKind::HighestScore => highscores
-            .best_scores
-            .first()
-            .unwrap_or(&game.score)
+        StatKind::PersonalBest if new_personal_best => "New personal best!".to_owned(),
+        StatKind::PersonalBest => String::new(),
+        StatKind::HighestScore => difficulty_highscores
+            .and_then(|highscores| highscores.best_scores.first())
+            .map_or(game.score, |record| record.score)
             .to_string(),
-        StatKind::BestTime => {
-            format_time(*highscores.best_times.first().unwrap_or(&game.elapsed_secs))
+        StatKind::BestTime => format_time(
+            difficulty_highscores
+                .and_then(|highscores| highscores.best_times.first())
+                .map_or(game.elapsed_secs, |record| record.elapsed_secs),
+        ),
+        StatKind::RecordScore(rank) => {
+            let score = match page {
+                HighscorePage::Legacy => highscores.legacy.best_scores.get(rank).copied(),
+                _ => difficulty_highscores
+                    .and_then(|highscores| highscores.best_scores.get(rank))
+                    .map(|record| record.score),
+            };
+            match score {
+                Some(score) => format!("{}.  {score}", rank + 1),
+                None => format!("{}.  -", rank + 1),
+            }
+        }
+        StatKind::RecordTime(rank) => difficulty_highscores
+            .and_then(|highscores| highscores.best_scores.get(rank))
+            .map(|record| format_time(record.elapsed_secs))
+            .unwrap_or_default(),
+        StatKind::RecordBestTime => {
+            let time = match page {
+                HighscorePage::Legacy => highscores.legacy.best_times.first().copied(),
+                _ => difficulty_highscores
+                    .and_then(|highscores| highscores.best_times.first())
+                    .map(|record| record.elapsed_secs),
+            };
+            time.map(format_time).unwrap_or_else(|| "-".to_owned())
         }
     }
 }
 
 fn scores() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
-    fragment7(
-        stat_row(StatKind::Score, "Score:"),
-        stat_row(StatKind::Time, "Time:"),
-        stat_row(StatKind::Mistakes, "Mistakes:"),
-        stat_row(StatKind::Hints, "Hints:"),
-        leaf(available_size),
-        stat_row(StatKind::HighestScore, "Highest score:"),
-        stat_row(StatKind::BestTime, "Best time:"),
+    fragment(
+        column_t(
+            ScoreBoardPage::ThisGame,
+            (available_size, without_occupying_space),
+            (),
+            fragment7(
+                stat_row(StatKind::Score, "Score:"),
+                stat_row(StatKind::Time, "Time:"),
+                stat_row(StatKind::Mistakes, "Mistakes:"),
+                stat_row(StatKind::Hints, "Hints:"),
+                row(
+                    available_size,
+                    (),
+                    text_t(
+                        StatTextMarker::from(StatKind::PersonalBest),
+                        "",
+                        (button_text_size, font_bold, text_color(COLOR_POP_FOCUS)),
+                    ),
+                ),
+                stat_row(StatKind::HighestScore, "Highest score:"),
+                stat_row(StatKind::BestTime, "Best time:"),
+            ),
+        ),
+        column_t(
+            ScoreBoardPage::Records,
+            available_size,
+            (),
+            fragment3(
+                |props: &Props, spawner: &mut ChildSpawnerCommands| {
+                    for rank in 0..MAX_NUM_HIGHSCORES {
+                        spawner.spawn_with_children(props, record_row(rank));
+                    }
+                },
+                leaf(available_size),
+                stat_row(StatKind::RecordBestTime, "Best time:"),
+            ),
+        ),
     )
 }
 
@@ -207,12 +430,45 @@ fn stat_row(
     kind: StatKind,
     label: &str,
 ) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
-    let font = if matches!(kind, StatKind::HighestScore | StatKind::BestTime) {
+    let font = if matches!(
+        kind,
+        StatKind::HighestScore | StatKind::BestTime | StatKind::RecordBestTime
+    ) {
         font_bold
     } else {
         font_medium
     };
 
+    stat_columns(
+        text(label.to_owned(), stat_label_style(font)),
+        text_t(StatTextMarker::from(kind), "", stat_value_style(font)),
+    )
+}
+
+/// Returns the row with the score and time of the record with the given rank.
+fn record_row(rank: usize) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    stat_columns(
+        text_t(
+            StatTextMarker::from(StatKind::RecordScore(rank)),
+            "",
+            stat_label_style(font_medium),
+        ),
+        text_t(
+            StatTextMarker::from(StatKind::RecordTime(rank)),
+            "",
+            stat_value_style(font_medium),
+        ),
+    )
+}
+
+fn stat_columns<B1, B2>(
+    label: impl Into<BundleWithChildren<B1>>,
+    value: impl Into<BundleWithChildren<B2>>,
+) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands))
+where
+    B1: Bundle,
+    B2: Bundle,
+{
     row(
         available_size,
         (),
@@ -220,15 +476,7 @@ fn stat_row(
             row(
                 preferred_size(Val::Percent(50.), Val::Percent(100.)),
                 (),
-                text(
-                    label.to_owned(),
-                    (
-                        button_text_size,
-                        font,
-                        text_anchor(Anchor::CENTER_RIGHT),
-                        text_color(COLOR_MAIN_DARKER),
-                    ),
-                ),
+                label,
             ),
             row(
                 (
@@ -236,21 +484,34 @@ fn stat_row(
                     margin(Size::new(Val::Percent(5.), Val::None)),
                 ),
                 (),
-                text_t(
-                    StatTextMarker::from(kind),
-                    "",
-                    (
-                        button_text_size,
-                        font,
-                        text_anchor(Anchor::CENTER_LEFT),
-                        text_color(COLOR_POP_FOCUS),
-                    ),
-                ),
+                value,
             ),
         ),
     )
 }
 
+fn stat_label_style(
+    font: fn(&mut FlexTextBundle, &ResourceBag),
+) -> impl FnOnce(&mut FlexTextBundle, &ResourceBag) {
+    move |bundle: &mut FlexTextBundle, resources: &ResourceBag| {
+        button_text_size(bundle, resources);
+        font(bundle, resources);
+        text_anchor(Anchor::CENTER_RIGHT)(bundle, resources);
+        text_color(COLOR_MAIN_DARKER)(bundle, resources);
+    }
+}
+
+fn stat_value_style(
+    font: fn(&mut FlexTextBundle, &ResourceBag),
+) -> impl FnOnce(&mut FlexTextBundle, &ResourceBag) {
+    move |bundle: &mut FlexTextBundle, resources: &ResourceBag| {
+        button_text_size(bundle, resources);
+        font(bundle, resources);
+        text_anchor(Anchor::CENTER_LEFT)(bundle, resources);
+        text_color(COLOR_POP_FOCUS)(bundle, resources);
+    }
+}
+
 pub fn on_fortune(
     mut scroll_text: Query<(&mut Text2d, &mut TextLayout, &ScrollText)>,
     fortune: Res<Fortune>,
@@ -1,49 +1,81 @@
+mod analysis_screen;
 mod board;
 mod board_numbers;
 mod game_ui;
 mod highscore_screen;
 mod mode_slider;
+mod replay_screen;
 mod wheel;
 
-use crate::{pointer_query::*, sudoku::*, ui::*};
-use crate::{GameTimer, ScreenState, Settings};
+use crate::{pointer_query::*, sudoku::*, ui::*, utils::format_technique};
+use crate::{GameTimer, ScreenState, Settings, Transition};
+use analysis_screen::{analysis_button_actions, render_analysis};
 use bevy::prelude::*;
-use board::{Board, MistakeCellBorders};
+use board::{render_board_edges, Board, ConflictCellBorders, MistakeCellBorders};
 use board_numbers::*;
-use game_ui::{on_score_changed, on_time_changed, settings_icon_interaction, UiButtonAction};
-use highscore_screen::{highscore_button_actions, on_fortune, on_highscores_changed};
-use mode_slider::{render_slider_knobs, slider_interaction};
+use game_ui::{
+    on_score_changed, on_time_changed, render_action_rows, render_paint_colors, render_tool_rows,
+    settings_icon_interaction, UiButtonAction,
+};
+use highscore_screen::{
+    highscore_button_actions, on_fortune, on_highscores_changed, render_highscore_pages,
+    HighscorePage, NewPersonalBest,
+};
+use mode_slider::{render_mode_labels, render_slider_knobs, slider_interaction};
+use replay_screen::{on_replay_scrub, on_replay_timer, render_replay, replay_button_actions};
 use std::num::NonZeroU8;
 use std::time::Duration;
 use wheel::{
     on_wheel_input, on_wheel_timer, render_disabled_wheel_slices, render_wheel, WHEEL_OPEN_DELAY,
 };
 
+pub use analysis_screen::analysis_screen;
 pub use board::board;
 pub use game_ui::game_screen;
 pub use highscore_screen::highscore_screen;
 pub use mode_slider::ModeState;
+pub use replay_screen::{replay_screen, ReplayPlayer};
 pub use wheel::{ActiveSliceHandles, Wheel};
 
+/// How long a cell needs to be pressed before the press turns into a lasso.
+const LASSO_DELAY: f32 = 0.8;
+
+/// How long the outcome of checking the board remains visible.
+const BOARD_CHECK_DURATION: f32 = 4.;
+
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(Selection::default())
             .insert_resource(Highlights::default())
+            .init_resource::<PaintColor>()
+            .init_resource::<BoardCheck>()
+            .init_resource::<HighscorePage>()
+            .init_resource::<NewPersonalBest>()
             .init_resource::<ActiveSliceHandles>()
+            .init_resource::<ReplayPlayer>()
             .init_state::<ModeState>()
             .add_systems(
                 Update,
                 (
-                    on_keyboard_input,
-                    on_pointer_input,
-                    on_wheel_input,
+                    on_keyboard_input
+                        .run_if(not(in_state(ScreenState::Editor)))
+                        .run_if(not(in_state(ScreenState::Replay)))
+                        .run_if(dialog_closed),
+                    on_pointer_input
+                        .run_if(not(in_state(ScreenState::Replay)))
+                        .run_if(dialog_closed),
+                    on_wheel_input.run_if(dialog_closed),
+                    on_lasso_timer,
                     on_score_changed.run_if(in_state(ScreenState::Game)),
                     on_fortune,
                     on_highscores_changed,
+                    render_highscore_pages,
                     on_time_changed,
                     on_timer,
+                    on_board_check_timer,
+                    render_conflicts.after(calculate_highlights),
                 ),
             )
             .add_systems(
@@ -55,14 +87,32 @@ impl Plugin for GamePlugin {
                     slider_interaction.run_if(in_state(ScreenState::Game)),
                     render_numbers,
                     render_notes,
+                    render_corner_notes.after(calculate_highlights),
                     render_wheel,
                     render_disabled_wheel_slices,
                     render_slider_knobs,
+                    render_mode_labels,
+                    render_tool_rows,
+                    render_paint_colors,
+                    render_cell_colors,
+                    render_board_edges,
+                    render_action_rows,
                     settings_icon_interaction.run_if(in_state(ScreenState::Game)),
                     calculate_highlights,
                     render_cell_highlights.after(calculate_highlights),
                     render_note_highlights.after(calculate_highlights),
                 ),
+            )
+            .add_systems(
+                Update,
+                (
+                    replay_button_actions.run_if(in_state(ScreenState::Replay)),
+                    on_replay_scrub.run_if(in_state(ScreenState::Replay)),
+                    on_replay_timer.run_if(in_state(ScreenState::Replay)),
+                    render_replay,
+                    analysis_button_actions.run_if(in_state(ScreenState::Analysis)),
+                    render_analysis,
+                ),
             );
     }
 }
@@ -95,15 +145,113 @@ enum NoteAnimationKind {
     FadeOut(Duration),
 }
 
+/// One of the positions in which corner notes are rendered.
+///
+/// Corner notes fill up the corners of a cell first, followed by the edges,
+/// in the order of their numbers.
+#[derive(Component)]
+pub struct CornerNote {
+    x: u8,
+    y: u8,
+    slot: usize,
+}
+
 #[derive(Component)]
 struct Number(u8, u8);
 
+/// The layer underneath a cell's number, which shows the cell's color.
+#[derive(Component)]
+struct CellColorLayer(u8, u8);
+
+/// The color that cells are painted with in color mode.
+#[derive(Resource)]
+pub struct PaintColor(pub NonZeroU8);
+
+impl Default for PaintColor {
+    fn default() -> Self {
+        Self(NonZeroU8::MIN)
+    }
+}
+
+/// The outcome of checking the board for wrong numbers, which is shown for
+/// `BOARD_CHECK_DURATION`.
+#[derive(Default, Resource)]
+pub struct BoardCheck {
+    /// The cells with a wrong number, if the board was checked recently.
+    pub wrong_cells: Option<Vec<(u8, u8)>>,
+    pub elapsed_secs: f32,
+}
+
+impl BoardCheck {
+    /// Returns the message that reports the outcome, if any.
+    pub fn message(&self) -> Option<String> {
+        self.wrong_cells.as_ref().map(|cells| match cells.len() {
+            0 => "No wrong numbers".to_owned(),
+            1 => "1 wrong number".to_owned(),
+            n => format!("{n} wrong numbers"),
+        })
+    }
+}
+
+/// An action that needs to be confirmed before it's performed, because it
+/// can't be taken back.
+#[derive(Clone, Copy)]
+pub enum ConfirmableAction {
+    Reveal(u8, u8),
+    Restart,
+    GiveUp,
+}
+
+impl ConfirmableAction {
+    /// Returns the dialog that asks whether the action should be performed.
+    pub fn dialog(self) -> Dialog {
+        let (title, message, label, transition) = match self {
+            Self::Reveal(x, y) => (
+                "Reveal this cell?",
+                "Games with revealed cells don't\nmake it into the highscores.",
+                "Reveal",
+                Transition::RevealCell(x, y),
+            ),
+            Self::Restart => (
+                "Restart the puzzle?",
+                "All progress is lost, and restarted\ngames don't make it into the highscores.",
+                "Restart",
+                Transition::RestartGame,
+            ),
+            Self::GiveUp => (
+                "Show the solution?",
+                "This ends the game.",
+                "Show Solution",
+                Transition::GiveUp,
+            ),
+        };
+
+        Dialog {
+            title: title.to_owned(),
+            message: message.to_owned(),
+            choices: vec![
+                DialogChoice::new(label, ButtonType::Primary, vec![transition]),
+                DialogChoice::cancel("Cancel"),
+            ],
+        }
+    }
+}
+
 #[derive(Default, Resource)]
 pub struct Selection {
     pub selected_cell: Option<(u8, u8)>,
     pub selected_note: Option<NonZeroU8>,
     pub hint: Option<(u8, u8)>,
+    pub notes_check: Option<NotesCheck>,
+
+    /// Whether notes, or colors, are being set or unset while dragging.
     pub note_toggle: Option<NoteToggleMode>,
+
+    /// Other cells that are selected together with `selected_cell`.
+    pub extra_cells: Vec<(u8, u8)>,
+
+    /// Whether cells are being added to the selection by dragging.
+    pub drag_select: Option<DragSelect>,
 }
 
 impl Selection {
@@ -129,23 +277,86 @@ impl Selection {
     pub fn clear(&mut self) {
         self.selected_cell = None;
         self.selected_note = None;
+        self.extra_cells.clear();
     }
 
     /// Moves the selection to the cell with the given coordinates.
     pub fn set(&mut self, x: u8, y: u8) {
         self.selected_cell = Some((x, y));
         self.selected_note = None;
+        self.extra_cells.clear();
     }
 
     /// Moves the selection to the cell with the given coordinates, unless the
     /// selection is already there, in which case the selection is cleared.
+    ///
+    /// If multiple cells are selected, only the given cell remains selected.
     pub fn toggle(&mut self, x: u8, y: u8) {
-        if self.selected_cell == Some((x, y)) {
+        if self.selected_cell == Some((x, y)) && self.extra_cells.is_empty() {
             self.clear();
         } else {
             self.set(x, y);
         }
     }
+
+    /// Adds the cell with the given coordinates to the selection, and makes it
+    /// the selected cell while keeping the previously selected ones.
+    pub fn extend(&mut self, x: u8, y: u8) {
+        if let Some(previous) = self.selected_cell {
+            if previous == (x, y) {
+                return;
+            }
+
+            self.extra_cells.retain(|&cell| cell != (x, y));
+            self.extra_cells.push(previous);
+        }
+
+        self.selected_cell = Some((x, y));
+        self.selected_note = None;
+    }
+
+    /// Adds the cell with the given coordinates to the selection, or removes
+    /// it if it was selected already.
+    pub fn toggle_extended(&mut self, x: u8, y: u8) {
+        if self.selected_cell == Some((x, y)) {
+            self.selected_cell = self.extra_cells.pop();
+            self.selected_note = None;
+        } else if self.extra_cells.contains(&(x, y)) {
+            self.extra_cells.retain(|&cell| cell != (x, y));
+        } else {
+            self.extend(x, y);
+        }
+    }
+
+    /// Returns whether the cell with the given coordinates is selected.
+    pub fn is_selected(&self, x: u8, y: u8) -> bool {
+        self.selected_cell == Some((x, y)) || self.extra_cells.contains(&(x, y))
+    }
+
+    /// Returns whether more than a single cell is selected.
+    pub fn has_multiple_cells(&self) -> bool {
+        self.selected_cell.is_some() && !self.extra_cells.is_empty()
+    }
+
+    /// Returns all the selected cells, starting with `selected_cell`.
+    pub fn selected_cells(&self) -> Vec<(u8, u8)> {
+        self.selected_cell
+            .into_iter()
+            .chain(self.extra_cells.iter().copied())
+            .collect()
+    }
+}
+
+/// Selecting multiple cells by dragging starts either with a modifier key, or
+/// with a long press that turns into a lasso.
+#[derive(Clone, Copy, PartialEq)]
+pub enum DragSelect {
+    /// A cell is being pressed, which turns into a lasso if the press is held
+    /// for `LASSO_DELAY` without picking a number.
+    Pending { cell: (u8, u8), pressed_secs: f32 },
+
+    /// Every cell that is dragged over is added to the selection.
+    Active,
 }
 
 #[derive(Clone, Copy, Default)]
@@ -161,46 +372,72 @@ fn on_keyboard_input(
     mut selection: ResMut<Selection>,
     mut mode: ResMut<NextState<ModeState>>,
     mut notes: Query<&mut Note>,
+    mut paint_color: ResMut<PaintColor>,
+    mut board_check: ResMut<BoardCheck>,
+    mut toasts: ResMut<Toasts>,
+    mode_state: Res<State<ModeState>>,
     settings: Res<Settings>,
     keys: Res<ButtonInput<KeyCode>>,
 ) {
+    let is_shift_pressed = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+
     for key in keys.get_just_pressed() {
         use KeyCode::*;
         match key {
+            ArrowUp if is_shift_pressed => extend_selection_relative(&mut selection, 0, -1),
+            ArrowRight if is_shift_pressed => extend_selection_relative(&mut selection, 1, 0),
+            ArrowDown if is_shift_pressed => extend_selection_relative(&mut selection, 0, 1),
+            ArrowLeft if is_shift_pressed => extend_selection_relative(&mut selection, -1, 0),
             ArrowUp => move_selection_relative(&mut selection, 0, -1),
             ArrowRight => move_selection_relative(&mut selection, 1, 0),
             ArrowDown => move_selection_relative(&mut selection, 0, 1),
             ArrowLeft => move_selection_relative(&mut selection, -1, 0),
 
-            Slash => give_hint(&mut game, &mut timer, &mut selection, &mut notes),
+            Slash => give_hint(
+                &mut game,
+                &mut timer,
+                &mut selection,
+                &mut notes,
+                &mut toasts,
+            ),
+            Period => check_board(&mut game, &mut board_check),
 
+            Backspace | Delete if mode_state.get() == &ModeState::Color => {
+                for (x, y) in selection.selected_cells() {
+                    if let Some(color) = game.colors.get(x, y) {
+                        game.toggle_color(x, y, color);
+                    }
+                }
+            }
             Backspace | Delete => clear_selection(&mut game, &selection),
 
             KeyU => mode.set(ModeState::Normal),
             KeyO => mode.set(ModeState::Notes),
+            KeyP => mode.set(ModeState::CornerNotes),
+            KeyY => mode.set(ModeState::Color),
 
             key => {
-                if let Some(n) = match key {
-                    Digit1 => NonZeroU8::new(1),
-                    Digit2 => NonZeroU8::new(2),
-                    Digit3 => NonZeroU8::new(3),
-                    Digit4 => NonZeroU8::new(4),
-                    Digit5 => NonZeroU8::new(5),
-                    Digit6 => NonZeroU8::new(6),
-                    Digit7 => NonZeroU8::new(7),
-                    Digit8 => NonZeroU8::new(8),
-                    Digit9 => NonZeroU8::new(9),
-                    _ => None,
-                } {
+                if let Some(n) = get_number_for_key(*key) {
                     if keys.pressed(KeyCode::AltLeft) || keys.pressed(KeyCode::AltRight) {
-                        toggle_note(&mut game, &mut selection, n);
+                        toggle_note(&mut game, &mut selection, n, NoteLayer::Center);
+                    } else if is_shift_pressed {
+                        toggle_note(&mut game, &mut selection, n, NoteLayer::Corner);
+                    } else if mode_state.get() == &ModeState::Color {
+                        if n.get() <= NUM_CELL_COLORS {
+                            paint_color.0 = n;
+                            if selection.selected_cell.is_some() {
+                                game.toggle_colors(&selection.selected_cells(), n);
+                            }
+                        }
+                    } else if let Some(layer) = mode_state.get().note_layer() {
+                        toggle_note(&mut game, &mut selection, n, layer);
                     } else if let Some((x, y)) = selection.selected_cell {
                         fill_number(
                             &mut game,
                             &mut timer,
                             &mut selection,
                             &mut notes,
-                            settings.show_mistakes,
+                            settings.reveals_mistakes(),
                             false,
                             x,
                             y,
@@ -213,7 +450,24 @@ fn on_keyboard_input(
     }
 }
 
-fn move_selection_relative(selection: &mut Selection, dx: i8, dy: i8) {
+/// Returns the number that is entered with the given key, if any.
+pub fn get_number_for_key(key: KeyCode) -> Option<NonZeroU8> {
+    use KeyCode::*;
+    match key {
+        Digit1 => NonZeroU8::new(1),
+        Digit2 => NonZeroU8::new(2),
+        Digit3 => NonZeroU8::new(3),
+        Digit4 => NonZeroU8::new(4),
+        Digit5 => NonZeroU8::new(5),
+        Digit6 => NonZeroU8::new(6),
+        Digit7 => NonZeroU8::new(7),
+        Digit8 => NonZeroU8::new(8),
+        Digit9 => NonZeroU8::new(9),
+        _ => None,
+    }
+}
+
+pub fn move_selection_relative(selection: &mut Selection, dx: i8, dy: i8) {
     let (x, y) = selection.selected_cell.unwrap_or_default();
 
     selection.toggle(
@@ -222,14 +476,44 @@ fn move_selection_relative(selection: &mut Selection, dx: i8, dy: i8) {
     );
 }
 
+/// Moves the selected cell, while keeping the cells it moved away from
+/// selected.
+fn extend_selection_relative(selection: &mut Selection, dx: i8, dy: i8) {
+    let Some((x, y)) = selection.selected_cell else {
+        return move_selection_relative(selection, dx, dy);
+    };
+
+    selection.extend(
+        ((x as i8 + 9 + dx) % 9) as u8,
+        ((y as i8 + 9 + dy) % 9) as u8,
+    );
+}
+
+/// Returns whether a modifier key is held that makes pointer presses add cells
+/// to the selection, instead of replacing it.
+pub fn is_extending_selection(keys: &ButtonInput<KeyCode>) -> bool {
+    use KeyCode::*;
+    keys.any_pressed([
+        ShiftLeft,
+        ShiftRight,
+        ControlLeft,
+        ControlRight,
+        SuperLeft,
+        SuperRight,
+    ])
+}
+
 fn on_pointer_input(
     mut game: ResMut<Game>,
     mut selection: ResMut<Selection>,
     board: Query<(&ComputedPosition, &ScreenState), With<Board>>,
     mode: Res<State<ModeState>>,
+    paint_color: Res<PaintColor>,
     pointer_query: PointerQuery,
     screen: Res<State<ScreenState>>,
     wheel: Query<(&Wheel, &ScreenState)>,
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
 ) {
     let Some((input_kind, position)) = pointer_query.get_changed_input_with_position() else {
         return;
@@ -244,15 +528,38 @@ fn on_pointer_input(
 
     let board_x_and_y = get_board_x_and_y(board_position, position);
 
-    match mode.get() {
-        ModeState::Normal => {
+    let may_lasso = mode.get() != &ModeState::Color;
+    if select_cells(
+        &mut selection,
+        &keys,
+        may_lasso,
+        time.elapsed_secs(),
+        input_kind,
+        board_x_and_y,
+    ) {
+        return;
+    }
+
+    if mode.get() == &ModeState::Color {
+        paint_cells(
+            &mut game,
+            &mut selection,
+            paint_color.0,
+            input_kind,
+            board_x_and_y,
+        );
+        return;
+    }
+
+    match mode.get().note_layer() {
+        None => {
             if input_kind == InputKind::Press {
                 if let Some((x, y)) = board_x_and_y {
                     selection.toggle(x, y);
                 }
             }
         }
-        ModeState::Notes => {
+        Some(layer) => {
             let Some((x, y)) = board_x_and_y else {
                 return;
             };
@@ -267,8 +574,8 @@ fn on_pointer_input(
                         .and_then(|(x, y)| game.current.get(x, y))
                         .or(selection.selected_note)
                     {
-                        game.toggle_note(x, y, n);
-                        selection.note_toggle = if game.notes.has(x, y, n) {
+                        game.toggle_note(x, y, n, layer);
+                        selection.note_toggle = if game.get_notes(layer).has(x, y, n) {
                             Some(NoteToggleMode::Set)
                         } else {
                             Some(NoteToggleMode::Unset)
@@ -296,12 +603,18 @@ fn on_pointer_input(
                             if wheel.is_open && wheel.spawn_timer >= WHEEL_OPEN_DELAY {
                                 // Revert the initial toggle at the start of the long press.
                                 let (x, y) = wheel.cell;
-                                game.toggle_note(x, y, n);
+                                game.toggle_note(x, y, n, layer);
                                 selection.note_toggle = None;
                             } else {
-                                match note_toggle {
-                                    NoteToggleMode::Set => game.notes.set(x, y, n),
-                                    NoteToggleMode::Unset => game.notes.unset(x, y, n),
+                                let is_set = matches!(note_toggle, NoteToggleMode::Set);
+                                if game.get_notes(layer).has(x, y, n) != is_set {
+                                    game.make_move(MoveAction::Note {
+                                        x,
+                                        y,
+                                        n,
+                                        layer,
+                                        is_set,
+                                    });
                                 }
                             }
                         }
@@ -315,14 +628,172 @@ fn on_pointer_input(
     }
 }
 
-fn clear_selection(game: &mut Game, selection: &Selection) {
-    let Some((x, y)) = selection.selected_cell else {
+/// Handles selecting multiple cells with the pointer. Returns whether the input
+/// was used for this, in which case it shouldn't be handled otherwise.
+///
+/// Pressing a cell while holding Shift adds it to the selection, while Ctrl (or
+/// Cmd) adds or removes it. Dragging from there, or from a lasso, adds every
+/// cell along the way.
+fn select_cells(
+    selection: &mut Selection,
+    keys: &ButtonInput<KeyCode>,
+    may_lasso: bool,
+    now_secs: f32,
+    input_kind: InputKind,
+    board_x_and_y: Option<(u8, u8)>,
+) -> bool {
+    match input_kind {
+        InputKind::Press => {
+            let Some((x, y)) = board_x_and_y else {
+                return false;
+            };
+
+            if keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight) {
+                selection.extend(x, y);
+            } else if is_extending_selection(keys) {
+                selection.toggle_extended(x, y);
+            } else {
+                selection.drag_select = may_lasso.then_some(DragSelect::Pending {
+                    cell: (x, y),
+                    pressed_secs: now_secs,
+                });
+                return false;
+            }
+
+            selection.drag_select = Some(DragSelect::Active);
+            true
+        }
+        InputKind::PressedMovement => match selection.drag_select {
+            Some(DragSelect::Active) => {
+                if let Some((x, y)) = board_x_and_y {
+                    if !selection.is_selected(x, y) {
+                        selection.extend(x, y);
+                    }
+                }
+                true
+            }
+            Some(DragSelect::Pending { cell, .. }) => {
+                // Moving to another cell cancels the lasso.
+                if board_x_and_y != Some(cell) {
+                    selection.drag_select = None;
+                }
+                false
+            }
+            None => false,
+        },
+        InputKind::Release => {
+            let was_active = selection.drag_select == Some(DragSelect::Active);
+            if selection.drag_select.is_some() {
+                selection.drag_select = None;
+            }
+            was_active
+        }
+    }
+}
+
+/// Turns a long press into a lasso for selecting multiple cells, unless a
+/// number was picked from the wheel in the meantime.
+fn on_lasso_timer(
+    mut game: ResMut<Game>,
+    mut selection: ResMut<Selection>,
+    mut wheel: Query<(&mut Wheel, &ScreenState)>,
+    mode: Res<State<ModeState>>,
+    screen: Res<State<ScreenState>>,
+    time: Res<Time>,
+) {
+    let Some(DragSelect::Pending {
+        cell: (x, y),
+        pressed_secs,
+    }) = selection.drag_select
+    else {
         return;
     };
 
-    if !game.start.has(x, y) {
-        game.current = game.current.unset(x, y);
-        game.notes.clear(x, y);
+    let Some(mut wheel) = wheel
+        .iter_mut()
+        .find_map(|wheel| (wheel.1 == screen.get()).then_some(wheel.0))
+    else {
+        return;
+    };
+
+    if wheel.is_open && wheel.selected_number.is_some() {
+        selection.drag_select = None;
+        return;
+    }
+
+    let delay = if wheel.is_open && wheel.open_with_delay {
+        WHEEL_OPEN_DELAY + LASSO_DELAY
+    } else {
+        LASSO_DELAY
+    };
+    if time.elapsed_secs() - pressed_secs < delay {
+        return;
+    }
+
+    if wheel.is_open {
+        wheel.is_open = false;
+    }
+
+    if let (Some(layer), Some(_)) = (mode.get().note_layer(), selection.note_toggle) {
+        // Revert the initial toggle at the start of the long press.
+        if let Some(n) = selection
+            .selected_cell
+            .and_then(|(x, y)| game.current.get(x, y))
+            .or(selection.selected_note)
+        {
+            game.toggle_note(x, y, n, layer);
+        }
+        selection.note_toggle = None;
+    }
+
+    if !selection.is_selected(x, y) {
+        selection.extend(x, y);
+    }
+    selection.drag_select = Some(DragSelect::Active);
+}
+
+/// Paints the pressed cell, or removes its color if it already had it. Dragging
+/// from there continues to paint, or unpaint, every cell along the way.
+fn paint_cells(
+    game: &mut Game,
+    selection: &mut Selection,
+    color: NonZeroU8,
+    input_kind: InputKind,
+    board_x_and_y: Option<(u8, u8)>,
+) {
+    match input_kind {
+        InputKind::Press => {
+            let Some((x, y)) = board_x_and_y else {
+                return;
+            };
+
+            game.toggle_color(x, y, color);
+            selection.set(x, y);
+            selection.note_toggle = if game.colors.has(x, y, color) {
+                Some(NoteToggleMode::Set)
+            } else {
+                Some(NoteToggleMode::Unset)
+            };
+        }
+        InputKind::PressedMovement => {
+            let (Some(note_toggle), Some((x, y))) = (selection.note_toggle, board_x_and_y) else {
+                return;
+            };
+
+            let is_set = matches!(note_toggle, NoteToggleMode::Set);
+            game.paint_color(x, y, color, is_set);
+        }
+        InputKind::Release => {
+            selection.note_toggle = None;
+        }
+    }
+}
+
+fn clear_selection(game: &mut Game, selection: &Selection) {
+    for (x, y) in selection.selected_cells() {
+        if !game.start.has(x, y) {
+            game.make_move(MoveAction::Clear { x, y });
+        }
     }
 }
 
@@ -409,16 +880,21 @@ fn animate_mistake(
     }
 }
 
-fn toggle_note(game: &mut Game, selection: &mut Selection, n: NonZeroU8) {
+fn toggle_note(game: &mut Game, selection: &mut Selection, n: NonZeroU8, layer: NoteLayer) {
     let Some((x, y)) = selection.selected_cell else {
         return;
     };
 
-    game.toggle_note(x, y, n);
+    if selection.has_multiple_cells() {
+        game.toggle_notes(&selection.selected_cells(), n, layer);
+    } else {
+        game.toggle_note(x, y, n, layer);
+    }
 
-    if game.notes.has(x, y, n) {
+    let notes = game.get_notes(layer);
+    if notes.has(x, y, n) {
         selection.selected_note = Some(n);
-    } else if let Some(remaining_n) = game.notes.get_only_number(get_pos(x, y)) {
+    } else if let Some(remaining_n) = notes.get_only_number(get_pos(x, y)) {
         selection.selected_note = Some(remaining_n);
     }
 }
@@ -441,6 +917,10 @@ fn button_actions(
     mut screen_state: ResMut<NextState<ScreenState>>,
     mut selection: ResMut<Selection>,
     mut notes: Query<&mut Note>,
+    mut paint_color: ResMut<PaintColor>,
+    mut modal_dialog: ResMut<ModalDialog>,
+    mut board_check: ResMut<BoardCheck>,
+    mut toasts: ResMut<Toasts>,
     query: Query<(&Interaction, &UiButtonAction), Changed<Interaction>>,
 ) {
     for (interaction, action) in &query {
@@ -448,9 +928,38 @@ fn button_actions(
             match action {
                 UiButtonAction::BackToMain => screen_state.set(ScreenState::MainMenu),
                 UiButtonAction::GoToSettings => screen_state.set(ScreenState::Settings),
-                UiButtonAction::Hint => {
-                    give_hint(&mut game, &mut timer, &mut selection, &mut notes)
+                UiButtonAction::Hint => give_hint(
+                    &mut game,
+                    &mut timer,
+                    &mut selection,
+                    &mut notes,
+                    &mut toasts,
+                ),
+                UiButtonAction::FillNotes => game.fill_candidates(false),
+                UiButtonAction::FillMissingNotes => game.fill_candidates(true),
+                UiButtonAction::CheckNotes => check_notes(&mut game, &mut selection),
+                UiButtonAction::CheckBoard => check_board(&mut game, &mut board_check),
+                UiButtonAction::DismissBoardCheck => board_check.wrong_cells = None,
+                UiButtonAction::SelectColor(color) => paint_color.0 = *color,
+                UiButtonAction::ClearColor => game.clear_color(paint_color.0),
+                UiButtonAction::AddCheckpoint => game.add_checkpoint(),
+                UiButtonAction::CommitBranch => game.commit_branch(),
+                UiButtonAction::DiscardBranch => {
+                    game.discard_branch();
+                    selection.notes_check = None;
+                }
+                UiButtonAction::Reveal => {
+                    // Only offer to reveal cells that don't have the right number yet.
+                    if let Some((x, y)) = selection.selected_cell {
+                        if game.current.get(x, y) != game.solution.get(x, y) {
+                            modal_dialog.open(ConfirmableAction::Reveal(x, y).dialog());
+                        }
+                    }
                 }
+                UiButtonAction::Restart => {
+                    modal_dialog.open(ConfirmableAction::Restart.dialog());
+                }
+                UiButtonAction::GiveUp => modal_dialog.open(ConfirmableAction::GiveUp.dialog()),
             }
         }
     }
@@ -461,6 +970,7 @@ fn give_hint(
     timer: &mut GameTimer,
     selection: &mut Selection,
     notes: &mut Query<&mut Note>,
+    toasts: &mut Toasts,
 ) {
     game.num_hints += 1;
 
@@ -468,8 +978,58 @@ fn give_hint(
         if let Some(n) = game.solution.get(x, y) {
             fill_number(game, timer, selection, notes, false, true, x, y, n);
         }
-    } else if let Some(Hint { x, y }) = game.get_hint() {
+    } else if let Some(Hint { x, y, reason }) = game.get_hint() {
+        game.make_move(MoveAction::Hint { x, y });
         selection.hint = Some((x, y));
+        toasts.push(Severity::Info, explain_hint(reason));
+    }
+}
+
+fn explain_hint(reason: HintReason) -> String {
+    match reason {
+        HintReason::Mistake => "This cell has a wrong number".to_owned(),
+        HintReason::Technique(technique) => {
+            format!(
+                "Try the {} technique on this cell",
+                format_technique(technique)
+            )
+        }
+        HintReason::Guess => "No technique helps here, so try a number".to_owned(),
+    }
+}
+
+fn check_notes(game: &mut Game, selection: &mut Selection) {
+    game.make_move(MoveAction::CheckNotes);
+    game.num_hints += 1;
+
+    selection.notes_check = Some(game.check_notes());
+
+    game.is_unsaved = true; // The penalty should stick, even if the game is closed.
+}
+
+fn check_board(game: &mut Game, board_check: &mut BoardCheck) {
+    game.make_move(MoveAction::CheckBoard);
+    game.num_checks += 1;
+
+    *board_check = BoardCheck {
+        wrong_cells: Some(game.check_board()),
+        elapsed_secs: 0.,
+    };
+
+    game.is_unsaved = true; // The penalty should stick, even if the game is closed.
+}
+
+fn on_board_check_timer(mut board_check: ResMut<BoardCheck>, time: Res<Time>) {
+    if board_check.wrong_cells.is_none() {
+        return;
+    }
+
+    // Counting down shouldn't trigger change detection, or the highlights
+    // would be recalculated every frame.
+    let check = board_check.bypass_change_detection();
+    check.elapsed_secs += time.delta().as_secs_f32();
+    if check.elapsed_secs >= BOARD_CHECK_DURATION {
+        board_check.wrong_cells = None;
     }
 }
 
@@ -487,6 +1047,10 @@ fn on_timer(
 
             game_timer.elapsed_secs += time.delta().as_secs_f32();
 
+            // Moves are logged with the game's time, which shouldn't trigger
+            // change detection, or the board would be rerendered every frame.
+            game.bypass_change_detection().elapsed_secs = game_timer.elapsed_secs;
+
             // Auto-filling behind a timer to make it smoothly transition into
             // the solved animation.
             if settings.autofill_correct_notes
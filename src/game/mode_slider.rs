@@ -3,7 +3,7 @@ use std::time::Duration;
 use bevy::{prelude::*, sprite::*};
 use bevy_tweening::{Lens, Tween, TweenAnim};
 
-use crate::{constants::*, pointer_query::*, ui::*};
+use crate::{constants::*, pointer_query::*, sudoku::NoteLayer, ui::*};
 use crate::{utils::TransformExt, ResourceBag, ScreenSizing};
 
 const ACTIVE_KNOB_Z: f32 = INACTIVE_KNOB_Z + 2.;
@@ -16,11 +16,43 @@ pub enum ModeState {
     #[default]
     Normal,
     Notes,
+    CornerNotes,
+    Color,
+}
+
+impl ModeState {
+    /// Returns the layer in which notes are placed in this mode, if any.
+    pub fn note_layer(&self) -> Option<NoteLayer> {
+        match self {
+            Self::Normal | Self::Color => None,
+            Self::Notes => Some(NoteLayer::Center),
+            Self::CornerNotes => Some(NoteLayer::Corner),
+        }
+    }
+
+    /// Returns whether the mode is on the notes side of the slider.
+    fn is_notes_side(&self) -> bool {
+        self.note_layer().is_some()
+    }
+
+    /// Returns the mode that shares the same side of the slider.
+    fn alternative(&self) -> Self {
+        match self {
+            Self::Normal => Self::Color,
+            Self::Color => Self::Normal,
+            Self::Notes => Self::CornerNotes,
+            Self::CornerNotes => Self::Notes,
+        }
+    }
 }
 
 #[derive(Component)]
 pub struct ModeSlider {
     active: bool,
+
+    /// Set while the slider is pressed on the side of the current mode, in
+    /// which case releasing it switches to the alternative mode on that side.
+    switch_on_release: bool,
 }
 
 #[derive(Component)]
@@ -29,13 +61,22 @@ pub struct ModeSliderKnob;
 #[derive(Component)]
 pub struct OppositeSliderKnob;
 
+#[derive(Component)]
+pub struct NormalModeLabel;
+
+#[derive(Component)]
+pub struct NotesModeLabel;
+
 pub fn mode_slider(props: &Props, spawner: &mut ChildSpawnerCommands) {
     let resources = &props.resources;
 
     if resources.screen_sizing.is_tablet() {
         spawner
             .spawn((
-                ModeSlider { active: false },
+                ModeSlider {
+                    active: false,
+                    switch_on_release: false,
+                },
                 FlexBundle::new(
                     FlexItemStyle::preferred_size(Val::Vmin(80.), Val::Pixel(105))
                         .with_margin(Size::new(Val::None, Val::Pixel(15))),
@@ -43,7 +84,13 @@ pub fn mode_slider(props: &Props, spawner: &mut ChildSpawnerCommands) {
                 ),
             ))
             .with_children(|row| {
-                build_label(row, resources, "Normal\nmode", Anchor::CENTER_LEFT);
+                build_label(
+                    row,
+                    resources,
+                    "Normal\nmode",
+                    Anchor::CENTER_LEFT,
+                    NormalModeLabel,
+                );
 
                 row.spawn(FlexBundle::new(
                     FlexItemStyle::fixed_size(Val::Percent(66.), Val::CrossPercent(6.))
@@ -55,12 +102,21 @@ pub fn mode_slider(props: &Props, spawner: &mut ChildSpawnerCommands) {
                     build_knobs(row, resources);
                 });
 
-                build_label(row, resources, "Notes\nmode", Anchor::CENTER_RIGHT);
+                build_label(
+                    row,
+                    resources,
+                    "Notes\nmode",
+                    Anchor::CENTER_RIGHT,
+                    NotesModeLabel,
+                );
             });
     } else {
         spawner
             .spawn((
-                ModeSlider { active: false },
+                ModeSlider {
+                    active: false,
+                    switch_on_release: false,
+                },
                 FlexBundle::new(
                     FlexItemStyle::preferred_size(Val::Vmin(90.), Val::Pixel(105))
                         .with_margin(Size::new(Val::None, Val::Pixel(15))),
@@ -85,8 +141,20 @@ pub fn mode_slider(props: &Props, spawner: &mut ChildSpawnerCommands) {
                         FlexContainerStyle::row(),
                     ))
                     .with_children(|row| {
-                        build_label(row, resources, "Normal\nmode", Anchor::CENTER_LEFT);
-                        build_label(row, resources, "Notes\nmode", Anchor::CENTER_RIGHT);
+                        build_label(
+                            row,
+                            resources,
+                            "Normal\nmode",
+                            Anchor::CENTER_LEFT,
+                            NormalModeLabel,
+                        );
+                        build_label(
+                            row,
+                            resources,
+                            "Notes\nmode",
+                            Anchor::CENTER_RIGHT,
+                            NotesModeLabel,
+                        );
                     });
             });
     }
@@ -148,6 +216,7 @@ fn build_label(
     resources: &ResourceBag,
     text: &str,
     anchor: Anchor,
+    marker: impl Bundle,
 ) {
     row.spawn(FlexBundle::new(
         FlexItemStyle::available_size(),
@@ -164,7 +233,7 @@ fn build_label(
             40.
         };
 
-        label_container.spawn(bundle);
+        label_container.spawn((marker, bundle));
     });
 }
 
@@ -182,27 +251,69 @@ pub fn slider_interaction(
         return;
     };
 
+    let is_notes_side = position.x > slider_position.x + 0.5 * slider_position.width;
+    let is_current_side = is_notes_side == state.get().is_notes_side();
     if input == InputKind::Press && slider_position.contains(position) {
         mode_slider.active = true;
+        mode_slider.switch_on_release = is_current_side;
     } else if input == InputKind::Release {
+        if mode_slider.active && mode_slider.switch_on_release {
+            next_state.set(state.get().alternative());
+        }
         mode_slider.active = false;
         return;
     } else if !mode_slider.active {
         return;
     }
 
-    let mode = if position.x > slider_position.x + 0.5 * slider_position.width {
+    let mode = if is_current_side {
+        *state.get()
+    } else if is_notes_side {
         ModeState::Notes
     } else {
         ModeState::Normal
     };
+    if !is_current_side {
+        mode_slider.switch_on_release = false;
+    }
     if state.get() != &mode {
         next_state.set(mode);
     }
 }
 
+pub fn render_mode_labels(
+    mut normal_labels: Query<&mut Text2d, (With<NormalModeLabel>, Without<NotesModeLabel>)>,
+    mut notes_labels: Query<&mut Text2d, (With<NotesModeLabel>, Without<NormalModeLabel>)>,
+    mode: Res<State<ModeState>>,
+) {
+    if !mode.is_changed() {
+        return;
+    }
+
+    let normal_text = match mode.get() {
+        ModeState::Color => "Color\nmode",
+        _ => "Normal\nmode",
+    };
+    for mut label in &mut normal_labels {
+        if label.0 != normal_text {
+            label.0 = normal_text.to_owned();
+        }
+    }
+
+    let notes_text = match mode.get() {
+        ModeState::CornerNotes => "Corner\nnotes",
+        _ => "Notes\nmode",
+    };
+    for mut label in &mut notes_labels {
+        if label.0 != notes_text {
+            label.0 = notes_text.to_owned();
+        }
+    }
+}
+
 pub fn render_slider_knobs(
     mut commands: Commands,
+    mut previous_mode: Local<ModeState>,
     slider_query: Query<(&ModeSlider, &ComputedPosition)>,
     mode: Res<State<ModeState>>,
     knob_query: Query<(Entity, &ComputedPosition), With<ModeSliderKnob>>,
@@ -217,7 +328,11 @@ pub fn render_slider_knobs(
         return;
     };
 
-    if mode.is_added() || !mode.is_changed() && !slider_active {
+    // Switching between note layers doesn't move the knob.
+    let is_same_side = previous_mode.is_notes_side() == mode.get().is_notes_side();
+    *previous_mode = *mode.get();
+
+    if mode.is_added() || (!mode.is_changed() || is_same_side) && !slider_active {
         return;
     }
 
@@ -245,14 +360,14 @@ pub fn render_slider_knobs(
         (position.x - slider_x - 0.5 * knob_position.width) / slider_width
     } else {
         match mode.get() {
-            ModeState::Normal => width - knob_width,
-            ModeState::Notes => -knob_width,
+            ModeState::Normal | ModeState::Color => width - knob_width,
+            ModeState::Notes | ModeState::CornerNotes => -knob_width,
         }
     };
 
     let knob_end = match mode.get() {
-        ModeState::Normal => 0.,
-        ModeState::Notes => width,
+        ModeState::Normal | ModeState::Color => 0.,
+        ModeState::Notes | ModeState::CornerNotes => width,
     };
 
     let animator = TweenAnim::new(Tween::new(
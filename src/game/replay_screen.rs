@@ -0,0 +1,356 @@
+use bevy::prelude::*;
+
+use super::board;
+use crate::pointer_query::*;
+use crate::sudoku::{Game, Replay};
+use crate::{constants::*, ui::*, utils::*, ResourceBag};
+use crate::{ScreenState, Transition};
+
+/// The speeds at which a replay can be played back, as multiples of the time
+/// it took to play the game.
+const REPLAY_SPEEDS: [f32; 5] = [1., 2., 4., 8., 16.];
+
+/// The number of segments in the progress bar.
+const NUM_PROGRESS_SEGMENTS: usize = 40;
+
+#[derive(Component)]
+pub enum ReplayButtonAction {
+    Back,
+    ChangeSpeed,
+    ToStart,
+    StepBack,
+    PlayPause,
+    StepForward,
+    ToEnd,
+}
+
+/// Plays back a finished game on the board.
+///
+/// The board shows the game resource, so the finished game is set aside while
+/// the replay is shown, and restored afterwards.
+#[derive(Default, Resource)]
+pub struct ReplayPlayer {
+    replay: Option<Replay>,
+    timeline: Vec<f32>,
+    finished_game: Option<Game>,
+    /// The number of moves that have been played back.
+    num_moves: usize,
+    playback_secs: f32,
+    is_playing: bool,
+    speed_index: usize,
+}
+
+impl ReplayPlayer {
+    /// Starts playing back the given finished game.
+    ///
+    /// Returns the game as it was before the first move.
+    pub fn start(&mut self, finished_game: Game) -> Game {
+        let replay = Replay::new(&finished_game);
+        let game = replay.game_at(0);
+
+        *self = Self {
+            timeline: replay.timeline(),
+            replay: Some(replay),
+            finished_game: Some(finished_game),
+            is_playing: true,
+            ..default()
+        };
+
+        game
+    }
+
+    /// Stops the replay, and returns the finished game.
+    pub fn stop(&mut self) -> Game {
+        std::mem::take(self).finished_game.unwrap_or_default()
+    }
+
+    fn num_moves_total(&self) -> usize {
+        self.timeline.len()
+    }
+
+    /// Jumps to the point in the replay after the given number of moves.
+    fn seek(&mut self, num_moves: usize) {
+        self.num_moves = num_moves.min(self.num_moves_total());
+        self.playback_secs = self.time_at(self.num_moves);
+    }
+
+    /// Returns the time after the given number of moves.
+    fn time_at(&self, num_moves: usize) -> f32 {
+        num_moves
+            .checked_sub(1)
+            .and_then(|index| self.timeline.get(index))
+            .copied()
+            .unwrap_or(0.)
+    }
+
+    fn speed(&self) -> f32 {
+        REPLAY_SPEEDS[self.speed_index]
+    }
+}
+
+#[derive(Component)]
+pub struct ReplayPlayText;
+
+#[derive(Component)]
+pub struct ReplaySpeedText;
+
+#[derive(Component)]
+pub struct ReplayProgressText;
+
+/// The bar that shows the progress of the replay, and which can be dragged
+/// along to scrub through it.
+#[derive(Component)]
+pub struct ReplayScrubber;
+
+#[derive(Component)]
+pub struct ReplayProgressSegment(usize);
+
+pub fn replay_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
+    use ReplayButtonAction::*;
+
+    fragment5(
+        // Back and speed buttons.
+        replay_row(fragment(
+            secondary_button(
+                Back,
+                game_screen_top_row_button_size,
+                text("Back", button_text),
+            ),
+            secondary_button(
+                ChangeSpeed,
+                game_screen_top_row_button_size,
+                text_t(ReplaySpeedText, "1x", button_text),
+            ),
+        )),
+        // Progress.
+        row(
+            preferred_size(Val::Percent(100.), Val::Pixel(80)),
+            (),
+            text_t(
+                ReplayProgressText,
+                "",
+                (font_bold, font_size(41.7), text_color(COLOR_MAIN_DARKER)),
+            ),
+        ),
+        board(ScreenState::Replay),
+        // Scrubber.
+        row_t(
+            ReplayScrubber,
+            (
+                game_screen_top_row_size,
+                margin(Size::new(Val::None, Val::Pixel(15))),
+            ),
+            background_color(COLOR_BOARD_LINE_THIN),
+            |_props: &Props, spawner: &mut ChildSpawnerCommands| {
+                for index in 0..NUM_PROGRESS_SEGMENTS {
+                    spawner.spawn((
+                        ReplayProgressSegment(index),
+                        rect(COLOR_MAIN_DARKER, available_size),
+                    ));
+                }
+            },
+        ),
+        // Playback controls.
+        replay_row(fragment5(
+            replay_button(ToStart, text("|<", replay_control_text)),
+            replay_button(StepBack, text("<", replay_control_text)),
+            replay_button(
+                PlayPause,
+                text_t(ReplayPlayText, "Pause", replay_control_text),
+            ),
+            replay_button(StepForward, text(">", replay_control_text)),
+            replay_button(ToEnd, text(">|", replay_control_text)),
+        )),
+    )
+}
+
+fn replay_row<B: Bundle>(
+    child: impl Into<BundleWithChildren<B>>,
+) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    row(
+        (
+            game_screen_top_row_size,
+            margin(Size::new(Val::None, Val::Pixel(15))),
+        ),
+        gap(Val::Auto),
+        child,
+    )
+}
+
+fn replay_control_text(bundle: &mut FlexTextBundle, resources: &ResourceBag) {
+    button_text_color(bundle, resources);
+    button_text_font(bundle, resources);
+    game_screen_notes_button_text_size(bundle, resources);
+}
+
+fn replay_button<B: Bundle>(
+    action: ReplayButtonAction,
+    child: impl Into<BundleWithChildren<B>>,
+) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    ternary_button(action, replay_control_button_size, child)
+}
+
+pub fn replay_button_actions(
+    mut transitions: MessageWriter<Transition>,
+    mut player: ResMut<ReplayPlayer>,
+    query: Query<(&Interaction, &ReplayButtonAction), (Changed<Interaction>, With<Button>)>,
+) {
+    for (interaction, action) in &query {
+        if *interaction == Interaction::Pressed {
+            use ReplayButtonAction::*;
+            match action {
+                Back => {
+                    transitions.write(Transition::Exit);
+                }
+                ChangeSpeed => player.speed_index = (player.speed_index + 1) % REPLAY_SPEEDS.len(),
+                ToStart => {
+                    player.is_playing = false;
+                    player.seek(0);
+                }
+                StepBack => {
+                    player.is_playing = false;
+                    let num_moves = player.num_moves.saturating_sub(1);
+                    player.seek(num_moves);
+                }
+                PlayPause => {
+                    if !player.is_playing && player.num_moves == player.num_moves_total() {
+                        player.seek(0); // Start over.
+                    }
+                    player.is_playing = !player.is_playing;
+                }
+                StepForward => {
+                    player.is_playing = false;
+                    let num_moves = player.num_moves + 1;
+                    player.seek(num_moves);
+                }
+                ToEnd => {
+                    player.is_playing = false;
+                    let num_moves = player.num_moves_total();
+                    player.seek(num_moves);
+                }
+            }
+        }
+    }
+}
+
+/// Jumps to the moment in the replay that matches the position on the
+/// progress bar that is pressed.
+pub fn on_replay_scrub(
+    mut player: ResMut<ReplayPlayer>,
+    mut is_scrubbing: Local<bool>,
+    scrubber: Query<&ComputedPosition, With<ReplayScrubber>>,
+    pointer_query: PointerQuery,
+) {
+    let Some((input_kind, position)) = pointer_query.get_changed_input_with_position() else {
+        return;
+    };
+    let Ok(scrubber) = scrubber.single() else {
+        return;
+    };
+
+    match input_kind {
+        InputKind::Press => *is_scrubbing = scrubber.contains(position),
+        InputKind::PressedMovement => {}
+        InputKind::Release => *is_scrubbing = false,
+    }
+    if !*is_scrubbing {
+        return;
+    }
+
+    let fraction = ((position.x - scrubber.x) / scrubber.width).clamp(0., 1.);
+    let num_moves = (fraction * player.num_moves_total() as f32).round() as usize;
+    if num_moves != player.num_moves {
+        player.is_playing = false;
+        player.seek(num_moves);
+    }
+}
+
+pub fn on_replay_timer(mut player: ResMut<ReplayPlayer>, time: Res<Time>) {
+    if !player.is_playing {
+        return;
+    }
+
+    // Playing back shouldn't trigger change detection, unless a move is made.
+    let playback = player.bypass_change_detection();
+    playback.playback_secs += time.delta().as_secs_f32() * playback.speed();
+
+    let mut num_moves = playback.num_moves;
+    while num_moves < playback.num_moves_total()
+        && playback.timeline[num_moves] <= playback.playback_secs
+    {
+        num_moves += 1;
+    }
+
+    if num_moves != playback.num_moves {
+        player.num_moves = num_moves;
+    }
+    if num_moves == player.num_moves_total() {
+        player.is_playing = false;
+    }
+}
+
+/// Shows the game as it was at the current point in the replay.
+pub fn render_replay(
+    mut game: ResMut<Game>,
+    mut segments: Query<(&ReplayProgressSegment, &mut Visibility)>,
+    mut play_text: Query<
+        &mut Text2d,
+        (
+            With<ReplayPlayText>,
+            Without<ReplaySpeedText>,
+            Without<ReplayProgressText>,
+        ),
+    >,
+    mut speed_text: Query<&mut Text2d, (With<ReplaySpeedText>, Without<ReplayProgressText>)>,
+    mut progress_text: Query<&mut Text2d, With<ReplayProgressText>>,
+    mut shown_num_moves: Local<Option<usize>>,
+    player: Res<ReplayPlayer>,
+) {
+    if !player.is_changed() {
+        return;
+    }
+
+    let Some(replay) = &player.replay else {
+        return;
+    };
+
+    if *shown_num_moves != Some(player.num_moves) {
+        *game = replay.game_at(player.num_moves);
+        *shown_num_moves = Some(player.num_moves);
+    }
+
+    let total = player.num_moves_total();
+    let num_visible_segments = if total == 0 {
+        NUM_PROGRESS_SEGMENTS
+    } else {
+        player.num_moves * NUM_PROGRESS_SEGMENTS / total
+    };
+    for (segment, mut visibility) in &mut segments {
+        visibility.set_if_neq(if segment.0 < num_visible_segments {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+
+    for mut text in &mut play_text {
+        text.0 = if player.is_playing { "Pause" } else { "Play" }.to_owned();
+    }
+
+    for mut text in &mut speed_text {
+        text.0 = format!("{}x", player.speed());
+    }
+
+    for mut text in &mut progress_text {
+        let time = format_time(player.time_at(player.num_moves));
+        text.0 = if replay.is_jump(player.num_moves) {
+            // Only so many moves are recorded, see `Game::make_move()`.
+            format!(
+                "Skipped {} unrecorded moves  ·  {time}",
+                replay.num_unrecorded_moves
+            )
+        } else {
+            format!("Move {} of {total}  ·  {time}", player.num_moves)
+        };
+    }
+}
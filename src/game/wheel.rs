@@ -1,6 +1,9 @@
 use super::mode_slider::*;
-use super::{fill_number, get_board_x_and_y, toggle_note, Board, InputKind, Note, Selection};
-use crate::{constants::*, pointer_query::*, ui::*, utils::*};
+use super::{
+    fill_number, get_board_x_and_y, is_extending_selection, toggle_note, Board, InputKind, Note,
+    Selection,
+};
+use crate::{constants::*, editor::PuzzleEditor, pointer_query::*, ui::*, utils::*};
 use crate::{ComputedPosition, Game, GameTimer, Images, ScreenSizing, ScreenState, Settings};
 use bevy::prelude::*;
 use std::{f32::consts::PI, num::NonZeroU8};
@@ -134,6 +137,7 @@ fn get_disabled_slice_handles(images: &Images) -> [&Handle<Image>; 9] {
 pub fn on_wheel_input(
     mut wheel: Query<(&mut Wheel, &ScreenState)>,
     mut game: ResMut<Game>,
+    mut editor: ResMut<PuzzleEditor>,
     mut selection: ResMut<Selection>,
     mut notes: Query<&mut Note>,
     mut timer: ResMut<GameTimer>,
@@ -142,10 +146,11 @@ pub fn on_wheel_input(
     board: Query<(&ComputedPosition, &ScreenState), With<Board>>,
     screen: Res<State<ScreenState>>,
     settings: Res<Settings>,
+    keys: Res<ButtonInput<KeyCode>>,
 ) {
     use ScreenState::*;
     let screen = screen.get();
-    if !matches!(screen, Game | LearnNumbers | LearnNotes) {
+    if !matches!(screen, Game | LearnNumbers | LearnNotes | Editor) {
         return;
     }
 
@@ -176,7 +181,13 @@ pub fn on_wheel_input(
     match input_kind {
         InputKind::Press => {
             if let Some((x, y)) = get_board_x_and_y(board_position, position) {
-                let should_open = if settings.show_mistakes {
+                let should_open = if is_extending_selection(&keys) {
+                    false // The press selects multiple cells instead.
+                } else if mode.get() == &ModeState::Color {
+                    false // Cells are painted without picking a number.
+                } else if screen == &Editor {
+                    true // Every starting number may be changed in the editor.
+                } else if settings.reveals_mistakes() {
                     !game.current.has(x, y)
                 } else {
                     !game.start.has(x, y)
@@ -205,7 +216,9 @@ pub fn on_wheel_input(
                     return;
                 }
 
-                if let Some(selected_number) = get_newly_selected_number(&game, &settings, &wheel) {
+                if let Some(selected_number) =
+                    get_newly_selected_number(&game, &settings, &wheel, screen)
+                {
                     wheel.selected_number = selected_number;
                     wheel.slice_timer = 0.;
                 }
@@ -218,26 +231,35 @@ pub fn on_wheel_input(
                 if let Some(n) = wheel.selected_number {
                     let (x, y) = wheel.cell;
                     match mode.get() {
+                        ModeState::Normal if screen == &Editor => {
+                            editor.set(x, y, n);
+                            selection.selected_cell = Some((x, y));
+                        }
                         ModeState::Normal => {
                             fill_number(
                                 &mut game,
                                 &mut timer,
                                 &mut selection,
                                 &mut notes,
-                                settings.show_mistakes,
+                                settings.reveals_mistakes(),
                                 false,
                                 x,
                                 y,
                                 n,
                             );
                         }
-                        ModeState::Notes => {
-                            if selection.selected_cell != Some((x, y)) {
+                        ModeState::Notes | ModeState::CornerNotes => {
+                            // Notes are toggled for all the selected cells,
+                            // if the wheel was opened on one of them.
+                            if !selection.is_selected(x, y) {
                                 selection.selected_cell = Some((x, y));
+                                selection.extra_cells.clear();
                             }
 
-                            toggle_note(&mut game, &mut selection, n);
+                            let layer = mode.get().note_layer().unwrap_or_default();
+                            toggle_note(&mut game, &mut selection, n, layer);
                         }
+                        ModeState::Color => {} // The wheel doesn't open in color mode.
                     }
                 }
             }
@@ -252,7 +274,7 @@ pub fn on_wheel_timer(
 ) {
     use ScreenState::*;
     let screen = screen.get();
-    if !matches!(screen, Game | LearnNumbers | LearnNotes) {
+    if !matches!(screen, Game | LearnNumbers | LearnNotes | Editor) {
         return;
     }
 
@@ -350,12 +372,14 @@ pub fn render_wheel(
 pub fn render_disabled_wheel_slices(
     mut disabled_slices: Query<(&DisabledSlice, &mut Visibility)>,
     game: Res<Game>,
+    screen: Res<State<ScreenState>>,
     settings: Res<Settings>,
     wheel: Query<&Wheel, Changed<Wheel>>,
 ) {
     for wheel in &wheel {
         for (DisabledSlice(n), mut visibility) in &mut disabled_slices {
-            let is_disabled = !may_select_number(wheel.cell, Some(*n), &game, &settings);
+            let is_disabled =
+                !may_select_number(wheel.cell, Some(*n), &game, &settings, screen.get());
             *visibility = if is_disabled {
                 Visibility::Visible
             } else {
@@ -423,13 +447,15 @@ fn get_newly_selected_number(
     game: &Game,
     settings: &Settings,
     wheel: &Wheel,
+    screen: &ScreenState,
 ) -> Option<Option<NonZeroU8>> {
     let selected_number = get_selected_number(wheel);
     if selected_number == wheel.selected_number {
         return None;
     }
 
-    may_select_number(wheel.cell, selected_number, game, settings).then_some(selected_number)
+    may_select_number(wheel.cell, selected_number, game, settings, screen)
+        .then_some(selected_number)
 }
 
 /// Returns the number that should be selected based on the input position
@@ -462,20 +488,21 @@ fn may_select_number(
     selected_number: Option<NonZeroU8>,
     game: &Game,
     settings: &Settings,
+    screen: &ScreenState,
 ) -> bool {
     let Some(n) = selected_number else {
         return true; // It should always be allowed to deselect.
     };
 
-    if game.is_completed(n) {
-        return false; // Selecting completed numbers is always forbidden.
+    if screen != &ScreenState::Editor && game.is_completed(n) {
+        return false; // Selecting completed numbers is forbidden while playing.
     }
 
     if settings.enable_wheel_aid {
         // With wheel aid, we determine whether the number may be selected based
         // whether it may be filled in in the given cell. This still allows for
         // mistakes, but prevents *trivial* mistakes.
-        let sudoku = if settings.show_mistakes {
+        let sudoku = if settings.reveals_mistakes() {
             &game.current
         } else {
             &game.start
@@ -488,5 +515,5 @@ fn may_select_number(
 
 /// Returns whether the wheel should be opened with a delay.
 fn should_open_with_delay(x: u8, y: u8, mode: ModeState, game: &Game) -> bool {
-    mode == ModeState::Notes || game.current.has(x, y)
+    mode != ModeState::Normal || game.current.has(x, y)
 }
@@ -1,83 +1,344 @@
-use crate::{constants::*, utils::*};
-use anyhow::Context;
+use crate::persistence::{self, Migration, Persistence};
+use crate::settings::MistakeHighlighting;
+use crate::sudoku::{Difficulty, Game};
+use crate::{constants::*, ui::report_error};
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+/// The best games for every difficulty, so that games are only ever compared
+/// against games of the same difficulty.
 #[derive(Default, Deserialize, Resource, Serialize)]
 pub struct Highscores {
+    #[serde(default)]
+    pub difficulties: BTreeMap<Difficulty, DifficultyHighscores>,
+
+    /// Highscores from before they were kept per difficulty. Their difficulty
+    /// is unknown, so they're kept apart.
+    #[serde(default, skip_serializing_if = "LegacyHighscores::is_empty")]
+    pub legacy: LegacyHighscores,
+}
+
+/// The best games of a single difficulty, ranked by score and by time.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct DifficultyHighscores {
+    pub best_scores: Vec<HighscoreRecord>,
+    pub best_times: Vec<HighscoreRecord>,
+}
+
+/// A finished game that made it into the highscores.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct HighscoreRecord {
+    /// When the game was finished, in seconds since the Unix epoch.
+    pub finished_at: u64,
+    pub score: u32,
+    pub elapsed_secs: f32,
+    pub num_mistakes: u32,
+    pub num_hints: u32,
+    /// The canonical hash of the puzzle, see `Sudoku::canonical_hash()`.
+    pub puzzle_id: u64,
+    pub mode: GameMode,
+}
+
+/// How mistakes were highlighted while the game was finished, see
+/// `MistakeHighlighting`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    #[default]
+    RevealMistakes,
+    HideMistakes,
+    /// Only numbers that conflict with each other were highlighted.
+    ShowConflicts,
+}
+
+impl From<MistakeHighlighting> for GameMode {
+    fn from(mistake_highlighting: MistakeHighlighting) -> Self {
+        match mistake_highlighting {
+            MistakeHighlighting::Off => Self::HideMistakes,
+            MistakeHighlighting::Conflicts => Self::ShowConflicts,
+            MistakeHighlighting::AgainstSolution => Self::RevealMistakes,
+        }
+    }
+}
+
+/// The highscores as they were saved before records were introduced.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct LegacyHighscores {
     pub best_scores: Vec<u32>,
     pub best_times: Vec<f32>,
 }
 
-impl Highscores {
-    /// Adds a score and time to the highscores.
-    ///
-    /// Does nothing if the score doesn't reach the highscores.
-    pub fn add(&mut self, new_score: u32, new_time: f32) {
-        let score_added = self.add_score(new_score);
-        let time_added = self.add_time(new_time);
+impl LegacyHighscores {
+    pub fn is_empty(&self) -> bool {
+        self.best_scores.is_empty() && self.best_times.is_empty()
+    }
+}
 
-        if score_added || time_added {
-            self.save();
+impl HighscoreRecord {
+    /// Creates a record for the given game, which is finished right now.
+    pub fn new(game: &Game, mode: GameMode) -> Self {
+        Self {
+            finished_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            score: game.score,
+            elapsed_secs: game.elapsed_secs,
+            num_mistakes: game.num_mistakes,
+            num_hints: game.num_hints + game.num_checks,
+            puzzle_id: game.puzzle_id(),
+            mode,
         }
     }
+}
 
-    fn add_score(&mut self, new_score: u32) -> bool {
-        if let Some(index) = self.best_scores.iter().position(|score| new_score > *score) {
-            self.best_scores.insert(index, new_score);
-            self.best_scores.truncate(MAX_NUM_HIGHSCORES);
-            true
-        } else if self.best_scores.len() < MAX_NUM_HIGHSCORES {
-            self.best_scores.push(new_score);
-            true
-        } else {
-            false
+impl Highscores {
+    /// Adds a record to the highscores of the given difficulty.
+    ///
+    /// Returns `true` if the record is a new personal best, meaning it has the
+    /// highest score or the best time of its difficulty. Does nothing if the
+    /// record doesn't reach the highscores.
+    pub fn add(
+        &mut self,
+        persistence: &Persistence,
+        difficulty: Difficulty,
+        record: HighscoreRecord,
+    ) -> bool {
+        let (is_best, is_added) = self.insert(difficulty, record);
+        if is_added {
+            self.save(persistence);
         }
+
+        is_best
     }
 
-    fn add_time(&mut self, new_time: f32) -> bool {
-        if let Some(index) = self.best_times.iter().position(|time| new_time < *time) {
-            self.best_times.insert(index, new_time);
-            self.best_times.truncate(MAX_NUM_HIGHSCORES);
-            true
-        } else if self.best_times.len() < MAX_NUM_HIGHSCORES {
-            self.best_times.push(new_time);
-            true
-        } else {
-            false
+    /// Inserts the record without saving. Returns whether it's a new personal
+    /// best, and whether it was added at all.
+    pub fn insert(&mut self, difficulty: Difficulty, record: HighscoreRecord) -> (bool, bool) {
+        let highscores = self.difficulties.entry(difficulty).or_default();
+
+        let score_rank = insert_ranked(&mut highscores.best_scores, record.clone(), |a, b| {
+            a.score > b.score
+        });
+        let time_rank = insert_ranked(&mut highscores.best_times, record, |a, b| {
+            a.elapsed_secs < b.elapsed_secs
+        });
+
+        (
+            score_rank == Some(0) || time_rank == Some(0),
+            score_rank.is_some() || time_rank.is_some(),
+        )
+    }
+
+    /// Ranks the records of the other highscores together with these,
+    /// without saving. Records that are in both are only kept once.
+    pub fn merge(&mut self, other: Highscores) {
+        for (difficulty, highscores) in other.difficulties {
+            for record in highscores
+                .best_scores
+                .into_iter()
+                .chain(highscores.best_times)
+            {
+                let is_known = self.get(difficulty).is_some_and(|known| {
+                    known.best_scores.contains(&record) || known.best_times.contains(&record)
+                });
+                if !is_known {
+                    self.insert(difficulty, record);
+                }
+            }
+        }
+
+        if self.legacy.is_empty() {
+            self.legacy = other.legacy;
         }
     }
 
+    /// Returns the highscores of the given difficulty, if any.
+    pub fn get(&self, difficulty: Difficulty) -> Option<&DifficultyHighscores> {
+        self.difficulties.get(&difficulty)
+    }
+
     /// Loads highscores from disk, or returns `Self::default()` if no
     /// highscores could be loaded.
-    pub fn load() -> Self {
-        fs::read(ensure_sudoku_dir().join("highscores.json"))
-            .context("Can't read file")
-            .and_then(|json| Self::from_json(&json))
-            .map_err(|err| println!("Can't load highscores: {err}"))
-            .unwrap_or_default()
+    pub fn load(persistence: &Persistence) -> Self {
+        persistence.load_or_default(
+            Path::new("highscores.json"),
+            Self::from_json,
+            "Can't load highscores",
+        )
     }
 
     /// Saves highscores to disk.
-    ///
-    /// This is called automatically on drop.
-    fn save(&self) {
+    pub fn save(&self, persistence: &Persistence) {
         self.to_json()
-            .and_then(|json| {
-                fs::write(ensure_sudoku_dir().join("highscores.json"), json)
-                    .context("Can't write to file")
-            })
-            .unwrap_or_else(|err| println!("Can't save highscores: {err}"));
+            .map(|json| persistence.write("highscores.json", json))
+            .unwrap_or_else(|err| report_error("Can't save highscores", err));
     }
 
     /// Serializes the highscores to JSON.
-    fn to_json(&self) -> Result<Vec<u8>, anyhow::Error> {
-        serde_json::to_vec(self).map_err(anyhow::Error::from)
+    pub fn to_json(&self) -> Result<Vec<u8>, anyhow::Error> {
+        persistence::to_versioned_json(self, HIGHSCORE_MIGRATIONS)
     }
 
-    /// Parses highscores from JSON.
-    fn from_json(bytes: &[u8]) -> Result<Self, anyhow::Error> {
-        serde_json::from_slice(bytes).map_err(anyhow::Error::from)
+    /// Parses highscores from JSON, migrating highscores that were saved by
+    /// older versions of the game.
+    pub fn from_json(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        persistence::from_versioned_json(bytes, HIGHSCORE_MIGRATIONS)
+    }
+}
+
+/// The migrations for highscores, see `persistence::from_versioned_json()`.
+///
+/// Records may have been finished while showing conflicts since version 2,
+/// which older versions can't read.
+const HIGHSCORE_MIGRATIONS: &[Migration] =
+    &[migrate_unversioned_highscores, persistence::unchanged];
+
+/// Moves highscores that were saved before they were kept per difficulty
+/// apart, since their difficulty is unknown.
+///
+/// Highscores that were already kept per difficulty are left as they are.
+fn migrate_unversioned_highscores(value: Value) -> Result<Value, anyhow::Error> {
+    match serde_json::from_value::<LegacyHighscores>(value.clone()) {
+        Ok(legacy) => Ok(json!({ "legacy": legacy })),
+        Err(_) => Ok(value),
+    }
+}
+
+/// Inserts the record before the first one it's better than, and keeps at
+/// most `MAX_NUM_HIGHSCORES` records.
+///
+/// Returns the rank the record was inserted at, if it made it.
+fn insert_ranked(
+    records: &mut Vec<HighscoreRecord>,
+    record: HighscoreRecord,
+    is_better: impl Fn(&HighscoreRecord, &HighscoreRecord) -> bool,
+) -> Option<usize> {
+    let rank = records
+        .iter()
+        .position(|existing| is_better(&record, existing))
+        .unwrap_or(records.len());
+    if rank >= MAX_NUM_HIGHSCORES {
+        return None;
+    }
+
+    records.insert(rank, record);
+    records.truncate(MAX_NUM_HIGHSCORES);
+    Some(rank)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{assert_matches_fixture, read_fixture};
+    use crate::sudoku::strategies::arb_difficulty;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    fn arb_record() -> impl Strategy<Value = HighscoreRecord> {
+        (any::<u32>(), 0..1_000_000u32, any::<u64>()).prop_map(
+            |(score, elapsed_quarter_secs, puzzle_id)| HighscoreRecord {
+                finished_at: 0,
+                score,
+                elapsed_secs: elapsed_quarter_secs as f32 / 4.,
+                num_mistakes: 0,
+                num_hints: 0,
+                puzzle_id,
+                mode: GameMode::default(),
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn records_are_ranked_per_difficulty(records in vec((arb_difficulty(), arb_record()), 0..30)) {
+            let mut highscores = Highscores::default();
+            for (difficulty, record) in records.clone() {
+                let best = highscores.get(difficulty).cloned().unwrap_or_default();
+                let is_best_score = best.best_scores.first().is_none_or(|best| record.score > best.score);
+                let is_best_time = best.best_times.first().is_none_or(|best| record.elapsed_secs < best.elapsed_secs);
+
+                let (is_best, _) = highscores.insert(difficulty, record);
+                prop_assert_eq!(is_best, is_best_score || is_best_time);
+            }
+
+            for (difficulty, highscores) in &highscores.difficulties {
+                let records: Vec<_> = records.iter().filter(|(d, _)| d == difficulty).map(|(_, r)| r).collect();
+
+                let mut scores: Vec<_> = records.iter().map(|record| record.score).collect();
+                scores.sort_by(|a, b| b.cmp(a));
+                scores.truncate(MAX_NUM_HIGHSCORES);
+                let best_scores: Vec<_> = highscores.best_scores.iter().map(|record| record.score).collect();
+                prop_assert_eq!(best_scores, scores);
+
+                let mut times: Vec<_> = records.iter().map(|record| record.elapsed_secs).collect();
+                times.sort_by(f32::total_cmp);
+                times.truncate(MAX_NUM_HIGHSCORES);
+                let best_times: Vec<_> = highscores.best_times.iter().map(|record| record.elapsed_secs).collect();
+                prop_assert_eq!(best_times, times);
+            }
+        }
+
+        #[test]
+        fn highscores_survive_a_round_trip(
+            records in vec((arb_difficulty(), arb_record()), 0..30),
+            legacy_scores in vec(any::<u32>(), 0..5),
+        ) {
+            let mut highscores = Highscores::default();
+            for (difficulty, record) in records {
+                highscores.insert(difficulty, record);
+            }
+            highscores.legacy.best_scores = legacy_scores;
+
+            let restored = Highscores::from_json(&highscores.to_json().unwrap()).unwrap();
+            prop_assert_eq!(restored.difficulties, highscores.difficulties);
+            prop_assert_eq!(restored.legacy, highscores.legacy);
+        }
+
+        #[test]
+        fn legacy_highscores_are_migrated(
+            best_scores in vec(any::<u32>(), 0..5),
+            best_quarter_secs in vec(0..1_000_000u32, 0..5),
+        ) {
+            let legacy = LegacyHighscores {
+                best_scores,
+                best_times: best_quarter_secs.into_iter().map(|secs| secs as f32 / 4.).collect(),
+            };
+
+            let migrated = Highscores::from_json(&serde_json::to_vec(&legacy).unwrap()).unwrap();
+            prop_assert!(migrated.difficulties.is_empty());
+            prop_assert_eq!(migrated.legacy, legacy);
+        }
+    }
+
+    #[test]
+    fn highscores_from_every_schema_version_are_loaded() {
+        let original = Highscores::from_json(&read_fixture("highscores-v0-original.json")).unwrap();
+        assert!(original.difficulties.is_empty());
+        assert_eq!(original.legacy.best_scores, vec![1840, 1520, 990]);
+        assert_eq!(original.legacy.best_times, vec![312.5, 356.25, 401.]);
+
+        let unversioned = Highscores::from_json(&read_fixture("highscores-v0.json")).unwrap();
+        let previous = Highscores::from_json(&read_fixture("highscores-v1.json")).unwrap();
+        assert_eq!(unversioned.difficulties, previous.difficulties);
+        assert_eq!(unversioned.legacy, previous.legacy);
+        assert_eq!(
+            previous.get(Difficulty::Easy).unwrap().best_scores[0].score,
+            1840
+        );
+
+        let current = Highscores::from_json(&read_fixture("highscores-v2.json")).unwrap();
+        let easy = current.get(Difficulty::Easy).unwrap();
+        assert_eq!(easy, previous.get(Difficulty::Easy).unwrap());
+        assert_eq!(
+            current.get(Difficulty::Medium).unwrap().best_scores[0].mode,
+            GameMode::ShowConflicts
+        );
+
+        assert_matches_fixture(&current.to_json().unwrap(), "highscores-v2.json");
     }
 }
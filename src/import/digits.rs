@@ -0,0 +1,205 @@
+use super::grid::InkMask;
+use ab_glyph::{Font, FontRef};
+use std::num::NonZeroU8;
+use std::sync::LazyLock;
+
+/// Fonts from which the reference digits are rendered. Using several weights
+/// helps with recognizing digits that are printed thinner or bolder than usual.
+const TEMPLATE_FONTS: [&[u8]; 4] = [
+    include_bytes!("../../assets/Poppins/Poppins-Light.ttf"),
+    include_bytes!("../../assets/Poppins/Poppins-Regular.ttf"),
+    include_bytes!("../../assets/Poppins/Poppins-SemiBold.ttf"),
+    include_bytes!("../../assets/Poppins/Poppins-Bold.ttf"),
+];
+
+/// Width and height of the normalized pictures that digits are compared by.
+const FEATURE_SIZE: usize = 16;
+
+/// Digits are considered uncertain if they resemble their reference less than
+/// this.
+const MIN_SIMILARITY: f32 = 0.6;
+
+/// Digits are considered uncertain if they resemble their reference by less
+/// than this margin, compared to the reference of any other digit.
+const MIN_MARGIN: f32 = 0.03;
+
+static TEMPLATES: LazyLock<Vec<(NonZeroU8, Features)>> = LazyLock::new(render_templates);
+
+/// The outcome of recognizing a single cell.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RecognizedCell {
+    pub digit: Option<NonZeroU8>,
+
+    /// The digit that came closest after the recognized one.
+    pub alternative: Option<NonZeroU8>,
+
+    /// Whether the digit may have been recognized incorrectly.
+    pub is_uncertain: bool,
+}
+
+/// Normalized picture of a digit, which allows digits to be compared
+/// regardless of their size and position.
+struct Features([f32; FEATURE_SIZE * FEATURE_SIZE]);
+
+impl Features {
+    /// Scales the ink within the given dimensions to fit the normalized
+    /// picture, preserving its aspect ratio.
+    fn from_ink(width: usize, height: usize, is_ink: impl Fn(usize, usize) -> bool) -> Self {
+        const SUBSAMPLES: usize = 4;
+
+        let scale = (FEATURE_SIZE - 2) as f32 / width.max(height) as f32;
+        let offset_x = (FEATURE_SIZE as f32 - width as f32 * scale) / 2.;
+        let offset_y = (FEATURE_SIZE as f32 - height as f32 * scale) / 2.;
+
+        let mut picture = [0.; FEATURE_SIZE * FEATURE_SIZE];
+        for (i, value) in picture.iter_mut().enumerate() {
+            let (x, y) = (i % FEATURE_SIZE, i / FEATURE_SIZE);
+            for sub in 0..SUBSAMPLES * SUBSAMPLES {
+                let sub_x = (sub % SUBSAMPLES) as f32 + 0.5;
+                let sub_y = (sub / SUBSAMPLES) as f32 + 0.5;
+                let source_x = (x as f32 + sub_x / SUBSAMPLES as f32 - offset_x) / scale;
+                let source_y = (y as f32 + sub_y / SUBSAMPLES as f32 - offset_y) / scale;
+                if source_x >= 0.
+                    && source_y >= 0.
+                    && (source_x as usize) < width
+                    && (source_y as usize) < height
+                    && is_ink(source_x as usize, source_y as usize)
+                {
+                    *value += 1.;
+                }
+            }
+        }
+
+        // Blur the picture a little, so that slightly shifted strokes still
+        // overlap.
+        let mut blurred = [0.; FEATURE_SIZE * FEATURE_SIZE];
+        for (i, value) in blurred.iter_mut().enumerate() {
+            let (x, y) = (i % FEATURE_SIZE, i / FEATURE_SIZE);
+            for ny in y.saturating_sub(1)..(y + 2).min(FEATURE_SIZE) {
+                for nx in x.saturating_sub(1)..(x + 2).min(FEATURE_SIZE) {
+                    let weight = if nx == x && ny == y { 2. } else { 1. };
+                    *value += weight * picture[ny * FEATURE_SIZE + nx];
+                }
+            }
+        }
+
+        // Normalize, so that comparing two pictures gives their correlation.
+        let mean = blurred.iter().sum::<f32>() / blurred.len() as f32;
+        blurred.iter_mut().for_each(|value| *value -= mean);
+        let norm = blurred
+            .iter()
+            .map(|value| value * value)
+            .sum::<f32>()
+            .sqrt();
+        if norm > 0. {
+            blurred.iter_mut().for_each(|value| *value /= norm);
+        }
+
+        Self(blurred)
+    }
+
+    /// Returns how similar the pictures are, from -1 (opposites) to 1
+    /// (identical).
+    fn similarity(&self, other: &Self) -> f32 {
+        self.0.iter().zip(&other.0).map(|(a, b)| a * b).sum()
+    }
+}
+
+/// Recognizes the digit within the given rectangle of the mask, which is
+/// expected to cover a single cell of a straightened grid.
+pub fn recognize_cell(
+    mask: &InkMask,
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+) -> RecognizedCell {
+    // Skip the edges of the cell, where the grid lines are.
+    let margin = (x1 - x0) / 8;
+    let (x0, y0, x1, y1) = (x0 + margin, y0 + margin, x1 - margin, y1 - margin);
+    let size = x1 - x0;
+
+    let digit = mask
+        .find_components(x0, y0, x1, y1)
+        .into_iter()
+        .filter(|component| {
+            let center_x = (component.min_x + component.max_x) / 2 - x0;
+            let center_y = (component.min_y + component.max_y) / 2 - y0;
+            component.height() * 10 >= size * 3
+                && component.pixels.len() * 60 >= size * size
+                && (size / 4..size * 3 / 4).contains(&center_x)
+                && (size / 4..size * 3 / 4).contains(&center_y)
+        })
+        .max_by_key(|component| component.pixels.len());
+    let Some(digit) = digit else {
+        return RecognizedCell::default();
+    };
+
+    let features = Features::from_ink(digit.width(), digit.height(), |x, y| {
+        mask.is_ink(digit.min_x + x, digit.min_y + y)
+    });
+
+    let mut similarities = [f32::MIN; 9];
+    for (n, template) in TEMPLATES.iter() {
+        let similarity = &mut similarities[n.get() as usize - 1];
+        *similarity = similarity.max(features.similarity(template));
+    }
+
+    let mut ranking: Vec<_> = (1..=9).zip(similarities).collect();
+    ranking.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let (best, best_similarity) = ranking[0];
+    let (second, second_similarity) = ranking[1];
+
+    RecognizedCell {
+        digit: NonZeroU8::new(best),
+        alternative: NonZeroU8::new(second),
+        is_uncertain: best_similarity < MIN_SIMILARITY
+            || best_similarity - second_similarity < MIN_MARGIN,
+    }
+}
+
+fn render_templates() -> Vec<(NonZeroU8, Features)> {
+    let mut templates = Vec::new();
+    for font in TEMPLATE_FONTS {
+        let font = FontRef::try_from_slice(font).expect("Fonts should be valid");
+        for n in 1..=9 {
+            let glyph = font.glyph_id(char::from(b'0' + n)).with_scale(64.);
+            let Some(outline) = font.outline_glyph(glyph) else {
+                continue;
+            };
+
+            let bounds = outline.px_bounds();
+            let width = bounds.width() as usize;
+            let height = bounds.height() as usize;
+            let mut coverage = vec![0.; width * height];
+            outline.draw(|x, y, value| {
+                if let Some(pixel) = coverage.get_mut(y as usize * width + x as usize) {
+                    *pixel = value;
+                }
+            });
+
+            let features = Features::from_ink(width, height, |x, y| coverage[y * width + x] > 0.5);
+            templates.push((NonZeroU8::new(n).unwrap(), features));
+        }
+    }
+
+    templates.push((NonZeroU8::new(1).unwrap(), render_footed_one()));
+    templates
+}
+
+/// Renders a 1 with a horizontal stroke at its foot.
+///
+/// Our own fonts print the 1 without one, while most serif fonts, and many
+/// newspapers, do include it.
+fn render_footed_one() -> Features {
+    const WIDTH: usize = 28;
+    const HEIGHT: usize = 44;
+    const STROKE: usize = 6;
+
+    Features::from_ink(WIDTH, HEIGHT, |x, y| {
+        let is_stem = (14..14 + STROKE).contains(&x);
+        let is_foot = y >= HEIGHT - STROKE;
+        let is_flag = x < 14 && y < 14 && (12..12 + STROKE).contains(&(x + y));
+        is_stem || is_foot || is_flag
+    })
+}
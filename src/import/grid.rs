@@ -0,0 +1,192 @@
+use image::{imageops, DynamicImage, GrayImage};
+
+/// Images are scaled down to this size before processing, which keeps the
+/// pipeline fast without losing any meaningful detail.
+const MAX_IMAGE_SIZE: u32 = 1024;
+
+/// Binary image in which every pixel is either ink or background.
+pub struct InkMask {
+    pub width: usize,
+    pub height: usize,
+    ink: Vec<bool>,
+}
+
+impl InkMask {
+    /// Separates ink from background by comparing every pixel against the
+    /// average brightness of its surroundings.
+    ///
+    /// Comparing against the surroundings, rather than against a single
+    /// threshold, keeps shadows and uneven lighting from being mistaken for
+    /// ink.
+    pub fn from_image(image: &GrayImage, radius: usize) -> Self {
+        let width = image.width() as usize;
+        let height = image.height() as usize;
+
+        // Sums of all pixels above and to the left of every position, so the
+        // sum of any rectangle can be calculated in constant time.
+        let mut sums = vec![0u64; (width + 1) * (height + 1)];
+        for y in 0..height {
+            let mut row_sum = 0;
+            for x in 0..width {
+                row_sum += image.get_pixel(x as u32, y as u32).0[0] as u64;
+                sums[(y + 1) * (width + 1) + x + 1] = sums[y * (width + 1) + x + 1] + row_sum;
+            }
+        }
+
+        let mut ink = vec![false; width * height];
+        for y in 0..height {
+            let y0 = y.saturating_sub(radius);
+            let y1 = (y + radius + 1).min(height);
+            for x in 0..width {
+                let x0 = x.saturating_sub(radius);
+                let x1 = (x + radius + 1).min(width);
+
+                let sum = sums[y1 * (width + 1) + x1] + sums[y0 * (width + 1) + x0]
+                    - sums[y0 * (width + 1) + x1]
+                    - sums[y1 * (width + 1) + x0];
+                let mean = sum as f32 / ((x1 - x0) * (y1 - y0)) as f32;
+
+                let value = image.get_pixel(x as u32, y as u32).0[0] as f32;
+                ink[y * width + x] = value < 0.85 * mean - 4.;
+            }
+        }
+
+        Self { width, height, ink }
+    }
+
+    pub fn is_ink(&self, x: usize, y: usize) -> bool {
+        self.ink[y * self.width + x]
+    }
+
+    /// Returns all groups of connected ink pixels within the given rectangle.
+    pub fn find_components(&self, x0: usize, y0: usize, x1: usize, y1: usize) -> Vec<Component> {
+        let mut visited = vec![false; (x1 - x0) * (y1 - y0)];
+        let index = |x: usize, y: usize| (y - y0) * (x1 - x0) + x - x0;
+
+        let mut components = Vec::new();
+        let mut stack = Vec::new();
+        for y in y0..y1 {
+            for x in x0..x1 {
+                if visited[index(x, y)] || !self.is_ink(x, y) {
+                    continue;
+                }
+
+                let mut component = Component::new(x, y);
+                visited[index(x, y)] = true;
+                stack.push((x, y));
+                while let Some((x, y)) = stack.pop() {
+                    component.add(x, y);
+
+                    for ny in y.saturating_sub(1)..(y + 2).min(y1) {
+                        for nx in x.saturating_sub(1)..(x + 2).min(x1) {
+                            if ny >= y0
+                                && nx >= x0
+                                && !visited[index(nx, ny)]
+                                && self.is_ink(nx, ny)
+                            {
+                                visited[index(nx, ny)] = true;
+                                stack.push((nx, ny));
+                            }
+                        }
+                    }
+                }
+                components.push(component);
+            }
+        }
+
+        components
+    }
+}
+
+/// Group of connected ink pixels.
+pub struct Component {
+    pub pixels: Vec<(usize, usize)>,
+    pub min_x: usize,
+    pub min_y: usize,
+    pub max_x: usize,
+    pub max_y: usize,
+}
+
+impl Component {
+    fn new(x: usize, y: usize) -> Self {
+        Self {
+            pixels: Vec::new(),
+            min_x: x,
+            min_y: y,
+            max_x: x,
+            max_y: y,
+        }
+    }
+
+    fn add(&mut self, x: usize, y: usize) {
+        self.pixels.push((x, y));
+        self.min_x = self.min_x.min(x);
+        self.min_y = self.min_y.min(y);
+        self.max_x = self.max_x.max(x);
+        self.max_y = self.max_y.max(y);
+    }
+
+    pub fn width(&self) -> usize {
+        self.max_x - self.min_x + 1
+    }
+
+    pub fn height(&self) -> usize {
+        self.max_y - self.min_y + 1
+    }
+}
+
+/// Converts the image to grayscale, scaling it down if it's overly large.
+pub fn to_grayscale(image: DynamicImage) -> GrayImage {
+    let image = if image.width() > MAX_IMAGE_SIZE || image.height() > MAX_IMAGE_SIZE {
+        image.resize(
+            MAX_IMAGE_SIZE,
+            MAX_IMAGE_SIZE,
+            imageops::FilterType::Triangle,
+        )
+    } else {
+        image
+    };
+
+    image.to_luma8()
+}
+
+/// Finds the corners of the grid in the given image.
+///
+/// The grid is assumed to be the largest connected structure in the image,
+/// since all its lines touch one another. Corners are returned in clockwise
+/// order, starting at the top left.
+pub fn find_grid_corners(image: &GrayImage) -> Option<[(f32, f32); 4]> {
+    let radius = (image.width().max(image.height()) / 40).max(4) as usize;
+    let mask = InkMask::from_image(image, radius);
+
+    let grid = mask
+        .find_components(0, 0, mask.width, mask.height)
+        .into_iter()
+        .max_by_key(|component| component.width() * component.height())?;
+
+    // A grid that covers less than a fifth of the image is more likely to be
+    // some other shape.
+    let min_size = mask.width.min(mask.height) / 5;
+    if grid.width() < min_size || grid.height() < min_size {
+        return None;
+    }
+
+    // The corners are the pixels that are furthest out in the diagonal
+    // directions.
+    let corner = |distance: fn(isize, isize) -> isize| {
+        let (x, y) = grid
+            .pixels
+            .iter()
+            .copied()
+            .min_by_key(|&(x, y)| distance(x as isize, y as isize))
+            .unwrap_or_default();
+        (x as f32 + 0.5, y as f32 + 0.5)
+    };
+
+    Some([
+        corner(|x, y| x + y),
+        corner(|x, y| y - x),
+        corner(|x, y| -x - y),
+        corner(|x, y| x - y),
+    ])
+}
@@ -0,0 +1,152 @@
+//! Recognizes Sudokus in photos and screenshots.
+//!
+//! Importing happens in a few steps: The grid is located as the largest
+//! connected shape in the image, after which it gets straightened so every
+//! cell becomes a neat square. The digit in every cell is then compared against
+//! reference digits, and finally the result is validated by solving it.
+//!
+//! For now, the only way to start an import is by dropping an image onto the
+//! puzzle editor, which is only available on desktop. Phones would need a
+//! native photo picker, which hasn't been hooked up yet.
+
+mod digits;
+mod grid;
+mod perspective;
+
+use crate::sudoku::{get_x_and_y_from_pos, Sudoku};
+use anyhow::Context;
+use digits::{recognize_cell, RecognizedCell};
+use grid::{find_grid_corners, to_grayscale, InkMask};
+use perspective::straighten;
+
+/// Width and height in pixels of every cell, after straightening the grid.
+const CELL_SIZE: usize = 40;
+
+/// A Sudoku that was recognized in an image.
+pub struct ImportedSudoku {
+    pub sudoku: Sudoku,
+
+    /// Positions of the cells that may have been recognized incorrectly.
+    pub uncertain_cells: Vec<usize>,
+}
+
+/// Recognizes the Sudoku in the given PNG or JPEG image.
+pub fn import_sudoku(bytes: &[u8]) -> anyhow::Result<ImportedSudoku> {
+    let image = image::load_from_memory(bytes).context("Can't decode image")?;
+    let cells = recognize_cells(&to_grayscale(image))?;
+    Ok(validate(cells))
+}
+
+fn recognize_cells(image: &image::GrayImage) -> anyhow::Result<[RecognizedCell; 81]> {
+    let corners = find_grid_corners(image).context("Can't find a grid in the image")?;
+    let grid = straighten(image, corners, 9 * CELL_SIZE as u32)
+        .context("Can't straighten the grid in the image")?;
+    let mask = InkMask::from_image(&grid, CELL_SIZE / 2);
+
+    let mut cells = [RecognizedCell::default(); 81];
+    for (pos, cell) in cells.iter_mut().enumerate() {
+        let (x, y) = get_x_and_y_from_pos(pos);
+        let (x0, y0) = (x as usize * CELL_SIZE, y as usize * CELL_SIZE);
+        *cell = recognize_cell(&mask, x0, y0, x0 + CELL_SIZE, y0 + CELL_SIZE);
+    }
+    Ok(cells)
+}
+
+/// Turns the recognized cells into a Sudoku, and checks whether it has a
+/// unique solution.
+///
+/// If the Sudoku doesn't have a unique solution, we attempt to fix it by
+/// replacing one of the uncertain digits with the next best alternative.
+fn validate(cells: [RecognizedCell; 81]) -> ImportedSudoku {
+    let mut sudoku = Sudoku::new();
+    for (pos, cell) in cells.iter().enumerate() {
+        if let Some(n) = cell.digit {
+            let (x, y) = get_x_and_y_from_pos(pos);
+            sudoku = sudoku.set(x, y, n);
+        }
+    }
+
+    let mut uncertain_cells: Vec<usize> = (0..81).filter(|&pos| cells[pos].is_uncertain).collect();
+
+    if sudoku.find_unique_solution().is_none() {
+        let repaired = uncertain_cells.iter().find_map(|&pos| {
+            let (x, y) = get_x_and_y_from_pos(pos);
+            let alternative = sudoku.set(x, y, cells[pos].alternative?);
+            alternative
+                .find_unique_solution()
+                .is_some()
+                .then_some(alternative)
+        });
+        if let Some(repaired) = repaired {
+            sudoku = repaired;
+        }
+    }
+
+    // Digits that conflict with one another can't all be right.
+    for pos in 0..81 {
+        let (x, y) = get_x_and_y_from_pos(pos);
+        if let Some(n) = sudoku.get(x, y) {
+            if !sudoku.may_set(x, y, n) && !uncertain_cells.contains(&pos) {
+                uncertain_cells.push(pos);
+            }
+        }
+    }
+    uncertain_cells.sort_unstable();
+
+    ImportedSudoku {
+        sudoku,
+        uncertain_cells,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    /// Recognizes every image in the `tests/import` folder, and compares the
+    /// outcome against the text file with the same name.
+    ///
+    /// The text files contain the expected Sudoku in the format in which it is
+    /// displayed, with a zero for every empty cell.
+    #[test]
+    fn recognizes_sample_images() {
+        let folder = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/import");
+        let mut failures = Vec::new();
+        let mut num_samples = 0;
+
+        for entry in fs::read_dir(&folder).unwrap() {
+            let path = entry.unwrap().path();
+            if !matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("png" | "jpg" | "jpeg")
+            ) {
+                continue;
+            }
+
+            let expected = fs::read_to_string(path.with_extension("txt")).unwrap();
+            let actual = match import_sudoku(&fs::read(&path).unwrap()) {
+                Ok(imported) => imported.sudoku.to_string(),
+                Err(err) => err.to_string(),
+            };
+            if actual != expected.trim() {
+                failures.push(format!("{}:\n{actual}", path.display()));
+            }
+            num_samples += 1;
+        }
+
+        assert!(num_samples > 0, "No samples found in {}", folder.display());
+        assert!(
+            failures.is_empty(),
+            "Misrecognized samples:\n{}",
+            failures.join("\n")
+        );
+    }
+
+    #[test]
+    fn rejects_images_without_a_grid() {
+        let image = image::GrayImage::from_pixel(200, 200, image::Luma([255]));
+        assert!(recognize_cells(&image).is_err());
+    }
+}
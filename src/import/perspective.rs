@@ -0,0 +1,151 @@
+use image::{GrayImage, Luma};
+
+/// Projective transform, which maps the corners of any quadrilateral onto any
+/// other quadrilateral.
+pub struct Homography([f64; 9]);
+
+impl Homography {
+    /// Returns the homography that maps the given `from` points onto the `to`
+    /// points.
+    ///
+    /// Returns `None` if three or more of the points lie on a single line.
+    pub fn from_points(from: [(f32, f32); 4], to: [(f32, f32); 4]) -> Option<Self> {
+        // Every pair of points gives two equations for the eight unknown
+        // coefficients. The ninth coefficient is fixed to 1.
+        let mut equations = [[0.; 9]; 8];
+        for (i, (&(x, y), &(u, v))) in from.iter().zip(&to).enumerate() {
+            let (x, y, u, v) = (x as f64, y as f64, u as f64, v as f64);
+            equations[2 * i] = [x, y, 1., 0., 0., 0., -u * x, -u * y, u];
+            equations[2 * i + 1] = [0., 0., 0., x, y, 1., -v * x, -v * y, v];
+        }
+
+        let solution = solve_linear_system(equations)?;
+        Some(Self([
+            solution[0],
+            solution[1],
+            solution[2],
+            solution[3],
+            solution[4],
+            solution[5],
+            solution[6],
+            solution[7],
+            1.,
+        ]))
+    }
+
+    /// Maps the given point.
+    pub fn map(&self, x: f32, y: f32) -> (f32, f32) {
+        let [a, b, c, d, e, f, g, h, i] = self.0;
+        let (x, y) = (x as f64, y as f64);
+        let w = g * x + h * y + i;
+        (
+            ((a * x + b * y + c) / w) as f32,
+            ((d * x + e * y + f) / w) as f32,
+        )
+    }
+}
+
+/// Cuts out the quadrilateral with the given corners from the image, and
+/// stretches it into a square of `size` by `size` pixels.
+///
+/// Corners are expected in clockwise order, starting at the top left.
+pub fn straighten(image: &GrayImage, corners: [(f32, f32); 4], size: u32) -> Option<GrayImage> {
+    let s = size as f32;
+    let square = [(0., 0.), (s, 0.), (s, s), (0., s)];
+    let homography = Homography::from_points(square, corners)?;
+
+    Some(GrayImage::from_fn(size, size, |x, y| {
+        let (source_x, source_y) = homography.map(x as f32 + 0.5, y as f32 + 0.5);
+        Luma([sample(image, source_x - 0.5, source_y - 0.5)])
+    }))
+}
+
+/// Samples the image at the given position, interpolating between the
+/// surrounding pixels.
+fn sample(image: &GrayImage, x: f32, y: f32) -> u8 {
+    let max_x = image.width() as f32 - 1.;
+    let max_y = image.height() as f32 - 1.;
+    let x = x.clamp(0., max_x);
+    let y = y.clamp(0., max_y);
+
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let x1 = (x0 + 1.).min(max_x);
+    let y1 = (y0 + 1.).min(max_y);
+    let fx = x - x0;
+    let fy = y - y0;
+
+    let pixel = |x: f32, y: f32| image.get_pixel(x as u32, y as u32).0[0] as f32;
+    let top = pixel(x0, y0) * (1. - fx) + pixel(x1, y0) * fx;
+    let bottom = pixel(x0, y1) * (1. - fx) + pixel(x1, y1) * fx;
+    (top * (1. - fy) + bottom * fy).round() as u8
+}
+
+/// Solves a system of eight linear equations using Gaussian elimination.
+///
+/// Every equation consists of eight coefficients, followed by the value they
+/// should sum up to.
+fn solve_linear_system(mut equations: [[f64; 9]; 8]) -> Option<[f64; 8]> {
+    for column in 0..8 {
+        let pivot = (column..8).max_by(|&a, &b| {
+            equations[a][column]
+                .abs()
+                .total_cmp(&equations[b][column].abs())
+        })?;
+        if equations[pivot][column].abs() < 1e-9 {
+            return None;
+        }
+        equations.swap(column, pivot);
+
+        let pivot_equation = equations[column];
+        for (row, equation) in equations.iter_mut().enumerate() {
+            if row != column {
+                let factor = equation[column] / pivot_equation[column];
+                for (value, pivot_value) in equation.iter_mut().zip(pivot_equation).skip(column) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+
+    let mut solution = [0.; 8];
+    for (i, value) in solution.iter_mut().enumerate() {
+        *value = equations[i][8] / equations[i][i];
+    }
+    Some(solution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn homography_maps_corners_onto_corners(
+            offsets in prop::array::uniform4((-25f32..25., -25f32..25.)),
+        ) {
+            // Move the corners of a square around, without making them
+            // coincide.
+            let from = [(0., 0.), (100., 0.), (100., 100.), (0., 100.)];
+            let to = [0, 1, 2, 3].map(|i| {
+                let (x, y) = from[i];
+                (x + offsets[i].0, y + offsets[i].1)
+            });
+
+            let homography = Homography::from_points(from, to).unwrap();
+            for (&(x, y), &(u, v)) in from.iter().zip(&to) {
+                let (mapped_x, mapped_y) = homography.map(x, y);
+                prop_assert!((mapped_x - u).abs() < 0.01, "{mapped_x} != {u}");
+                prop_assert!((mapped_y - v).abs() < 0.01, "{mapped_y} != {v}");
+            }
+        }
+    }
+
+    #[test]
+    fn homography_rejects_collinear_points() {
+        let from = [(0., 0.), (1., 0.), (2., 0.), (3., 0.)];
+        let to = [(0., 0.), (1., 0.), (1., 1.), (0., 1.)];
+        assert!(Homography::from_points(from, to).is_none());
+    }
+}
@@ -2,17 +2,25 @@
 #![allow(clippy::too_many_arguments)]
 
 mod assets;
+mod backup;
 mod constants;
+mod editor;
 mod game;
 mod highscores;
+mod import;
+mod library;
 mod menus;
 mod onboarding;
+mod persistence;
 mod pointer_query;
 mod settings;
+mod statistics;
+mod storage;
 //#[cfg(feature = "steam")]
 //mod steam;
 mod resource_bag;
-mod sudoku;
+pub mod sudoku;
+mod sync;
 mod transition_events;
 mod ui;
 mod utils;
@@ -28,13 +36,21 @@ use bevy_tweening::{lens::TransformPositionLens, Tween, TweenAnim, TweeningPlugi
 use smallvec::SmallVec;
 
 use assets::*;
-use game::{game_screen, highscore_screen, ActiveSliceHandles};
+use editor::*;
+use game::{analysis_screen, game_screen, highscore_screen, replay_screen, ActiveSliceHandles};
 use highscores::Highscores;
-use menus::{menu_screen, settings_screen, SettingsToggleTimer};
+use library::Library;
+use menus::{
+    menu_screen, saved_games_screen, settings_screen, statistics_screen, SettingsToggleTimer,
+};
 use onboarding::*;
+use persistence::Persistence;
 use resource_bag::ResourceBag;
 use settings::Settings;
+use statistics::Statistics;
+use storage::FileStorage;
 use sudoku::Game;
+use sync::SyncFolder;
 use transition_events::{on_transition, Transition};
 use ui::*;
 
@@ -79,12 +95,18 @@ pub enum ScreenState {
     #[default]
     MainMenu,
     SelectDifficulty,
+    SelectTechnique,
     Game,
     Highscores,
     Settings,
+    Statistics,
     Welcome,
     LearnNumbers,
     LearnNotes,
+    Editor,
+    Replay,
+    Analysis,
+    SavedGames,
 }
 
 impl ScreenState {
@@ -95,9 +117,14 @@ impl ScreenState {
             (1, 0) => Some(Game),
             (1, 1) => Some(Highscores),
             (2, 0) => Some(Settings),
+            (0, 1) => Some(Statistics),
             (-3, 0) => Some(Welcome),
             (-2, 0) => Some(LearnNumbers),
             (-1, 0) => Some(LearnNotes),
+            (0, -1) => Some(Editor),
+            (2, 1) => Some(Replay),
+            (1, 2) => Some(Analysis),
+            (-1, 1) => Some(SavedGames),
             _ => None,
         }
     }
@@ -105,13 +132,18 @@ impl ScreenState {
     fn tile_offsets(self) -> (i8, i8) {
         use ScreenState::*;
         match self {
-            MainMenu | SelectDifficulty => (0, 0),
+            MainMenu | SelectDifficulty | SelectTechnique => (0, 0),
             Game => (1, 0),
             Highscores => (1, 1),
             Settings => (2, 0),
+            Statistics => (0, 1),
             Welcome => (-3, 0),
             LearnNumbers => (-2, 0),
             LearnNotes => (-1, 0),
+            Editor => (0, -1),
+            Replay => (2, 1),
+            Analysis => (1, 2),
+            SavedGames => (-1, 1),
         }
     }
 }
@@ -214,9 +246,31 @@ impl Plugin for AssetConfiguratorPlugin {
 }
 
 fn run(screen_sizing: ScreenSizing, zoom_factor: ZoomFactor) {
-    let settings = Settings::load();
+    let data_dir = storage::data_dir();
+    println!("Keeping data in {}", data_dir.display());
+    let persistence = Persistence::new(FileStorage::new(data_dir));
+
+    if backup::run_command_line(&persistence) {
+        persistence.flush();
+        return;
+    }
+
+    let sync_folder = sync::sync_dir().map(|dir| {
+        SyncFolder::new(
+            persistence.clone(),
+            FileStorage::new(dir),
+            sync::device_id(&persistence),
+        )
+    });
+    if let Some(sync_folder) = &sync_folder {
+        // The first sync starts in the background right after launch, so a
+        // slow folder doesn't hold up the window, see `on_sync_timer()`.
+        println!("Syncing data with {}", sync_folder.location());
+    }
+
+    let settings = Settings::load(&persistence);
     let game = if settings.onboarding_finished {
-        Game::load()
+        Game::load(&persistence)
     } else {
         Game::load_tutorial()
     };
@@ -233,7 +287,11 @@ fn run(screen_sizing: ScreenSizing, zoom_factor: ZoomFactor) {
         .insert_resource(game)
         .insert_resource(timer)
         .insert_resource(settings)
-        .insert_resource(Highscores::load())
+        .insert_resource(Highscores::load(&persistence))
+        .insert_resource(Statistics::load(&persistence))
+        .insert_resource(Library::load(&persistence))
+        .insert_resource(persistence)
+        .insert_resource(PuzzleEditor::default())
         .insert_resource(SettingsToggleTimer::default())
         .insert_resource(screen_sizing)
         .insert_resource(zoom_factor)
@@ -244,7 +302,8 @@ fn run(screen_sizing: ScreenSizing, zoom_factor: ZoomFactor) {
         .add_systems(
             Update,
             (
-                on_escape,
+                // An open dialog is dismissed with Escape instead.
+                on_escape.run_if(dialog_closed).before(keyboard_interaction),
                 #[cfg(not(target_os = "ios"))]
                 on_resize,
                 #[cfg(debug_assertions)]
@@ -252,10 +311,21 @@ fn run(screen_sizing: ScreenSizing, zoom_factor: ZoomFactor) {
                 on_screen_change,
                 on_window_close,
                 on_exit.after(on_window_close),
+                save_game_changes.after(on_transition),
+                on_sync_timer.run_if(resource_exists::<SyncFolder>),
                 onboarding_screen_button_interaction,
                 how_to_play_numbers_interaction,
                 how_to_play_notes_interaction,
                 on_transition,
+                editor_keyboard_input
+                    .run_if(in_state(ScreenState::Editor))
+                    .run_if(dialog_closed),
+                editor_button_actions.run_if(in_state(ScreenState::Editor)),
+                on_file_drop.run_if(in_state(ScreenState::Editor)),
+                on_image_imported,
+                on_editor_analyzed,
+                on_editor_changed.after(on_editor_analyzed),
+                render_editor_status,
             ),
         )
         .add_plugins(AssetConfiguratorPlugin {})
@@ -276,6 +346,10 @@ fn run(screen_sizing: ScreenSizing, zoom_factor: ZoomFactor) {
         ))
         .init_state::<ScreenState>();
 
+    if let Some(sync_folder) = sync_folder {
+        app.insert_resource(sync_folder);
+    }
+
     add_steamworks_plugin(&mut app);
 
     app.run();
@@ -327,6 +401,7 @@ fn setup(
     settings: Res<Settings>,
     game: Res<Game>,
     highscores: Res<Highscores>,
+    persistence: Res<Persistence>,
     screen_sizing: Res<ScreenSizing>,
 ) {
     commands.spawn((
@@ -344,6 +419,7 @@ fn setup(
     let props = Props {
         game: &game,
         highscores: &highscores,
+        persistence: &persistence,
         resources: ResourceBag {
             fonts: &fonts,
             images: &images,
@@ -359,12 +435,17 @@ fn setup(
     commands.spawn_with_children(&props, screen(Game, resources, game_screen()));
     commands.spawn_with_children(&props, screen(Highscores, resources, highscore_screen()));
     commands.spawn_with_children(&props, screen(Settings, resources, settings_screen()));
+    commands.spawn_with_children(&props, screen(Statistics, resources, statistics_screen()));
     commands.spawn_with_children(&props, screen(Welcome, resources, welcome_screen()));
     commands.spawn_with_children(&props, screen(LearnNotes, resources, learn_notes_screen()));
     commands.spawn_with_children(
         &props,
         screen(LearnNumbers, resources, learn_numbers_screen()),
     );
+    commands.spawn_with_children(&props, screen(Editor, resources, editor_screen()));
+    commands.spawn_with_children(&props, screen(Replay, resources, replay_screen()));
+    commands.spawn_with_children(&props, screen(Analysis, resources, analysis_screen()));
+    commands.spawn_with_children(&props, screen(SavedGames, resources, saved_games_screen()));
 
     if !settings.onboarding_finished {
         screen_state.set(Welcome);
@@ -382,14 +463,106 @@ fn setup(
 fn on_exit(
     mut game: ResMut<Game>,
     game_timer: Res<GameTimer>,
+    persistence: Res<Persistence>,
+    screen: Res<State<ScreenState>>,
     app_exit_messages: MessageReader<AppExit>,
     destroyed_windows: MessageReader<WindowDestroyed>,
 ) {
-    if !app_exit_messages.is_empty() || !destroyed_windows.is_empty() {
+    if app_exit_messages.is_empty() && destroyed_windows.is_empty() {
+        return;
+    }
+
+    // The game has already been saved before the editor or replay borrowed it.
+    if !matches!(screen.get(), ScreenState::Editor | ScreenState::Replay) {
         println!("Saving before exit");
         game.elapsed_secs = game_timer.elapsed_secs;
-        game.save();
+        game.save(&persistence);
     }
+
+    // Saving happens in the background, so whatever is still pending has to
+    // be written before the app goes away.
+    persistence.flush();
+}
+
+/// Merges the player's data with the other devices every so often, in the
+/// background.
+fn on_sync_timer(
+    mut sync_folder: ResMut<SyncFolder>,
+    mut game: ResMut<Game>,
+    mut game_timer: ResMut<GameTimer>,
+    mut highscores: ResMut<Highscores>,
+    mut statistics: ResMut<Statistics>,
+    mut library: ResMut<Library>,
+    persistence: Res<Persistence>,
+    screen: Res<State<ScreenState>>,
+    time: Res<Time>,
+) {
+    if let Some((active_slot, result)) = sync_folder.poll() {
+        match result {
+            Ok(Some(synced)) => {
+                // The data may have changed while the sync was running, so
+                // the synced data is merged into it rather than replacing it.
+                highscores.merge(synced.data.highscores);
+                highscores.save(&persistence);
+                statistics.merge(synced.data.statistics);
+                statistics.save(&persistence);
+                library.merge(synced.data.library);
+                library.save(&persistence);
+
+                // The sync left the game that was in use alone. It isn't pulled
+                // out from under the player, but otherwise it follows the other
+                // devices.
+                if game.slot == active_slot && *screen.get() != ScreenState::Game {
+                    if synced.deleted_slots.contains(&game.slot) {
+                        Game::delete_slot(&persistence, game.slot);
+                        *game = Game::default();
+                        game_timer.elapsed_secs = 0.;
+                    } else if let Some(synced_game) = synced
+                        .data
+                        .saved_games
+                        .into_iter()
+                        .chain(synced.data.archived_games)
+                        .find(|synced_game| synced_game.slot == game.slot)
+                        .filter(|synced_game| sync::takes_over(synced_game, &game))
+                    {
+                        synced_game.save(&persistence);
+                        *game = synced_game;
+                        game_timer.elapsed_secs = game.elapsed_secs;
+                    }
+                }
+            }
+            Ok(None) => {}
+            Err(err) => report_error("Can't sync data", err),
+        }
+    }
+
+    if !sync_folder.tick(time.delta_secs()) || sync_folder.is_running() {
+        return;
+    }
+
+    // The game is borrowed by the editor and the replay, so syncing waits
+    // until it's back.
+    if matches!(screen.get(), ScreenState::Editor | ScreenState::Replay) {
+        return;
+    }
+
+    game.elapsed_secs = game_timer.elapsed_secs;
+    game.save(&persistence);
+    sync_folder.start(game.slot);
+}
+
+/// Saves the game whenever a move changed it, so no progress gets lost.
+fn save_game_changes(
+    mut game: ResMut<Game>,
+    persistence: Res<Persistence>,
+    screen: Res<State<ScreenState>>,
+) {
+    // The editor and the replay borrow the game for puzzles that aren't saved.
+    if !game.is_unsaved || matches!(screen.get(), ScreenState::Editor | ScreenState::Replay) {
+        return;
+    }
+
+    game.save_if_changed(&persistence);
 }
 
 fn on_escape(input: Res<ButtonInput<KeyCode>>, mut transitions: MessageWriter<Transition>) {
@@ -0,0 +1,105 @@
+use crate::persistence::{self, Migration, Persistence};
+use crate::sudoku::{Difficulty, Sudoku};
+use crate::ui::report_error;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The migrations for the library, see `persistence::from_versioned_json()`.
+const LIBRARY_MIGRATIONS: &[Migration] = &[persistence::unchanged];
+
+/// The player's personal collection of puzzles they entered by hand.
+#[derive(Default, Deserialize, Resource, Serialize)]
+pub struct Library {
+    pub puzzles: Vec<LibraryPuzzle>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct LibraryPuzzle {
+    /// The canonical hash of the puzzle, used to recognize duplicates.
+    pub id: u64,
+    pub start: Sudoku,
+    pub difficulty: Difficulty,
+}
+
+impl Library {
+    /// Adds a puzzle with the given canonical hash to the library, see
+    /// `Sudoku::canonical_hash()`.
+    ///
+    /// Returns `false` if the puzzle, or a transformed variant of it, was
+    /// already in the library.
+    pub fn add(
+        &mut self,
+        persistence: &Persistence,
+        id: u64,
+        start: Sudoku,
+        difficulty: Difficulty,
+    ) -> bool {
+        if self.puzzles.iter().any(|puzzle| puzzle.id == id) {
+            return false;
+        }
+
+        self.puzzles.push(LibraryPuzzle {
+            id,
+            start,
+            difficulty,
+        });
+        self.save(persistence);
+        true
+    }
+
+    /// Adds the puzzles of the other library that aren't in this one yet,
+    /// without saving.
+    pub fn merge(&mut self, other: Library) {
+        for puzzle in other.puzzles {
+            if !self.puzzles.iter().any(|known| known.id == puzzle.id) {
+                self.puzzles.push(puzzle);
+            }
+        }
+    }
+
+    /// Loads the library from disk, or returns `Self::default()` if no
+    /// library could be loaded.
+    pub fn load(persistence: &Persistence) -> Self {
+        persistence.load_or_default(
+            Path::new("library.json"),
+            Self::from_json,
+            "Can't load library",
+        )
+    }
+
+    /// Saves the library to disk.
+    pub fn save(&self, persistence: &Persistence) {
+        self.to_json()
+            .map(|json| persistence.write("library.json", json))
+            .unwrap_or_else(|err| report_error("Can't save library", err));
+    }
+
+    /// Serializes the library to JSON.
+    pub fn to_json(&self) -> Result<Vec<u8>, anyhow::Error> {
+        persistence::to_versioned_json(self, LIBRARY_MIGRATIONS)
+    }
+
+    /// Parses the library from JSON.
+    pub fn from_json(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        persistence::from_versioned_json(bytes, LIBRARY_MIGRATIONS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{assert_matches_fixture, read_fixture};
+
+    #[test]
+    fn libraries_from_every_schema_version_are_loaded() {
+        let unversioned = Library::from_json(&read_fixture("library-v0.json")).unwrap();
+        let current = Library::from_json(&read_fixture("library-v1.json")).unwrap();
+        assert_eq!(unversioned.puzzles.len(), 1);
+        assert_eq!(unversioned.puzzles[0].id, current.puzzles[0].id);
+        assert_eq!(unversioned.puzzles[0].start, current.puzzles[0].start);
+        assert_eq!(current.puzzles[0].difficulty, Difficulty::Easy);
+
+        assert_matches_fixture(&current.to_json().unwrap(), "library-v1.json");
+    }
+}
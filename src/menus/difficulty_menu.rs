@@ -1,4 +1,9 @@
-use crate::{sudoku::Difficulty, ui::*, Transition};
+use super::abandon_game_dialog;
+use crate::{
+    sudoku::{Difficulty, Game},
+    ui::*,
+    Transition,
+};
 use bevy::prelude::*;
 
 #[derive(Component)]
@@ -43,6 +48,8 @@ pub fn difficulty_menu_buttons() -> impl FnOnce(&Props, &mut ChildSpawnerCommand
 // Handles screen navigation based on button actions in the difficulty screen.
 pub fn difficulty_screen_button_actions(
     mut transitions: MessageWriter<Transition>,
+    mut modal_dialog: ResMut<ModalDialog>,
+    game: Res<Game>,
     interaction_query: Query<
         (&Interaction, &DifficultyScreenButtonAction),
         (Changed<Interaction>, With<Button>),
@@ -56,7 +63,12 @@ pub fn difficulty_screen_button_actions(
                     transitions.write(Transition::Exit);
                 }
                 StartGameAtDifficulty(difficulty) => {
-                    transitions.write(Transition::StartGame(*difficulty));
+                    let start_game = Transition::StartGame(*difficulty);
+                    if game.may_continue() {
+                        modal_dialog.open(abandon_game_dialog(start_game));
+                    } else {
+                        transitions.write(start_game);
+                    }
                 }
             }
         }
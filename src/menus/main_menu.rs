@@ -1,19 +1,24 @@
-use crate::{constants::*, transition_events::*, ui::*, ScreenState};
+use crate::{constants::*, sudoku::Game, transition_events::*, ui::*, ScreenState};
 use bevy::{app::AppExit, prelude::*, sprite::Anchor};
 
 #[derive(Component)]
 #[allow(dead_code)]
 pub enum MainScreenButtonAction {
     ContinueGame,
+    CreatePuzzle,
     GoToHowToPlay,
     GoToNewGame,
+    GoToPractice,
+    GoToStatistics,
     Quit,
 }
 
 pub fn main_menu_buttons(props: &Props, spawner: &mut ChildSpawnerCommands) {
     use MainScreenButtonAction::*;
 
-    if props.game.may_continue() {
+    // Games in other save slots can be continued too, even when the last one
+    // was finished.
+    if props.game.may_continue() || Game::has_saved_games(props.persistence) {
         spawner.spawn_with_children(
             props,
             selected_button(
@@ -41,6 +46,33 @@ pub fn main_menu_buttons(props: &Props, spawner: &mut ChildSpawnerCommands) {
         );
     }
 
+    spawner.spawn_with_children(
+        props,
+        secondary_button(
+            GoToPractice,
+            (button_size_main, button_margin),
+            text("Practice", button_text),
+        ),
+    );
+
+    spawner.spawn_with_children(
+        props,
+        secondary_button(
+            GoToStatistics,
+            (button_size_main, button_margin),
+            text("Statistics", button_text),
+        ),
+    );
+
+    spawner.spawn_with_children(
+        props,
+        secondary_button(
+            CreatePuzzle,
+            (button_size_main, button_margin),
+            text("Create Puzzle", button_text),
+        ),
+    );
+
     spawner.spawn_with_children(
         props,
         secondary_button(
@@ -97,12 +129,21 @@ pub fn main_menu_button_actions(
                 ContinueGame => {
                     transitions.write(Transition::ContinueGame);
                 }
+                CreatePuzzle => {
+                    transitions.write(Transition::OpenEditor);
+                }
                 GoToHowToPlay => {
                     transitions.write(Transition::LearnNumbers);
                 }
                 GoToNewGame => {
                     screen_state.set(ScreenState::SelectDifficulty);
                 }
+                GoToPractice => {
+                    screen_state.set(ScreenState::SelectTechnique);
+                }
+                GoToStatistics => {
+                    screen_state.set(ScreenState::Statistics);
+                }
                 Quit => {
                     app_exit.write(AppExit::Success);
                 }
@@ -1,7 +1,10 @@
 mod difficulty_menu;
 mod main_menu;
+mod practice_menu;
+mod saved_games_menu;
 mod settings_menu;
 mod settings_toggle;
+mod statistics_menu;
 
 use std::f32::consts::PI;
 use std::time::Duration;
@@ -10,15 +13,21 @@ use bevy::prelude::*;
 use bevy_tweening::{Delay, EaseMethod, Lens, Tween, TweenAnim};
 use smallvec::smallvec;
 
-use crate::{ui::*, ScreenInteraction, ScreenState};
+use crate::{ui::*, ScreenInteraction, ScreenState, Transition};
 
 use difficulty_menu::*;
 use main_menu::*;
+use practice_menu::*;
+use saved_games_menu::*;
 use settings_menu::*;
 use settings_toggle::*;
+use statistics_menu::*;
 
+pub use practice_menu::PracticeGeneration;
+pub use saved_games_menu::{saved_games_screen, SavedGames};
 pub use settings_menu::settings_screen;
 pub use settings_toggle::SettingsToggleTimer;
+pub use statistics_menu::statistics_screen;
 
 #[derive(Component, Default)]
 struct ButtonSection {
@@ -30,23 +39,36 @@ pub struct MenuPlugin;
 
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
-                difficulty_screen_button_actions.run_if(in_state(ScreenState::SelectDifficulty)),
-                main_menu_button_actions.run_if(in_state(ScreenState::MainMenu)),
-                settings_screen_button_actions.run_if(in_state(ScreenState::Settings)),
-                settings_toggle_actions.run_if(in_state(ScreenState::Settings)),
-                on_setting_change,
-                render_settings_toggles.run_if(in_state(ScreenState::Settings)),
-                on_screen_change.before(LayoutSystem::ApplyLayout),
-            ),
-        );
+        app.init_resource::<PracticeGeneration>()
+            .init_resource::<SavedGames>()
+            .init_resource::<StatisticsDifficulty>()
+            .add_systems(
+                Update,
+                (
+                    difficulty_screen_button_actions
+                        .run_if(in_state(ScreenState::SelectDifficulty)),
+                    main_menu_button_actions.run_if(in_state(ScreenState::MainMenu)),
+                    practice_screen_button_actions.run_if(in_state(ScreenState::SelectTechnique)),
+                    on_practice_generated,
+                    render_practice_generation,
+                    saved_games_button_actions.run_if(in_state(ScreenState::SavedGames)),
+                    saved_games_keyboard_input.run_if(in_state(ScreenState::SavedGames)),
+                    on_saved_games_opened,
+                    render_saved_games,
+                    settings_screen_button_actions.run_if(in_state(ScreenState::Settings)),
+                    settings_toggle_actions.run_if(in_state(ScreenState::Settings)),
+                    on_setting_change,
+                    render_settings_toggles.run_if(in_state(ScreenState::Settings)),
+                    render_statistics,
+                    statistics_screen_button_actions.run_if(in_state(ScreenState::Statistics)),
+                    on_screen_change.before(LayoutSystem::ApplyLayout),
+                ),
+            );
     }
 }
 
 pub fn menu_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
-    fragment4(
+    fragment5(
         // Logo.
         dynamic_image(
             launch_screen,
@@ -68,9 +90,34 @@ pub fn menu_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
             3.,
             difficulty_menu_buttons(),
         ),
+        // Practice buttons.
+        button_section(
+            ScreenState::SelectTechnique,
+            0.5 * PI,
+            4.,
+            practice_menu_buttons(),
+        ),
     )
 }
 
+/// Asks what should happen to the game in progress before another one gets
+/// started.
+fn abandon_game_dialog(start_game: Transition) -> Dialog {
+    Dialog {
+        title: "Abandon current game?".to_owned(),
+        message: "Your game in progress can also stay\namong your saved games.".to_owned(),
+        choices: vec![
+            DialogChoice::new("Keep It", ButtonType::Primary, vec![start_game]),
+            DialogChoice::new(
+                "Abandon It",
+                ButtonType::Ternary,
+                vec![Transition::AbandonGame, start_game],
+            ),
+            DialogChoice::cancel("Cancel"),
+        ],
+    }
+}
+
 fn button_section(
     screen_state: ScreenState,
     initial_rotation: f32,
@@ -132,6 +179,7 @@ fn on_screen_change(
     let new_rotation = match screen_state.get() {
         MainMenu | Game => 0.,
         SelectDifficulty => 0.5 * PI,
+        SelectTechnique => -0.5 * PI,
         _ => return,
     };
 
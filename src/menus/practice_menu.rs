@@ -0,0 +1,175 @@
+use super::abandon_game_dialog;
+use crate::game::{ModeState, Selection};
+use crate::persistence::Persistence;
+use crate::statistics::Statistics;
+use crate::sudoku::{Game, Technique};
+use crate::{ui::*, utils::format_technique, GameTimer, ScreenState, Transition};
+use bevy::prelude::*;
+use bevy::tasks::{futures::check_ready, AsyncComputeTaskPool, Task};
+
+#[derive(Component)]
+pub enum PracticeScreenButtonAction {
+    BackToMain,
+    StartPractice(Technique),
+}
+
+/// Marks the text of the button for practicing the given technique.
+#[derive(Component)]
+pub struct TechniqueButtonText(Technique);
+
+/// The practice game that's being generated in the background, since finding
+/// a Sudoku that requires the technique may take many attempts.
+#[derive(Default, Resource)]
+pub struct PracticeGeneration(Option<(Technique, Task<anyhow::Result<Game>>)>);
+
+impl PracticeGeneration {
+    pub fn start(&mut self, technique: Technique) {
+        let task = AsyncComputeTaskPool::get()
+            .spawn(async move { Game::generate_for_technique(technique) });
+        self.0 = Some((technique, task));
+    }
+
+    /// Stops generating the practice game, if it's still being generated.
+    pub fn cancel(&mut self) {
+        // Dropping the task cancels it.
+        self.0 = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+pub fn practice_menu_buttons() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
+    use PracticeScreenButtonAction::*;
+    use Technique::*;
+
+    fragment7(
+        secondary_button(
+            BackToMain,
+            (button_size_main, button_margin_extra_height),
+            text("Back", button_text),
+        ),
+        technique_button(OnlyNumber),
+        technique_button(LoneRanger),
+        technique_button(Twins),
+        technique_button(Triplets),
+        technique_button(HiddenTwins),
+        technique_button(HiddenTriplets),
+    )
+}
+
+fn technique_button(
+    technique: Technique,
+) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    primary_button(
+        PracticeScreenButtonAction::StartPractice(technique),
+        (button_size_main, button_margin),
+        text_t(
+            TechniqueButtonText(technique),
+            format_technique(technique),
+            button_text,
+        ),
+    )
+}
+
+// Handles screen navigation based on button actions in the practice screen.
+pub fn practice_screen_button_actions(
+    mut transitions: MessageWriter<Transition>,
+    mut modal_dialog: ResMut<ModalDialog>,
+    game: Res<Game>,
+    practice_generation: Res<PracticeGeneration>,
+    interaction_query: Query<
+        (&Interaction, &PracticeScreenButtonAction),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, action) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            use PracticeScreenButtonAction::*;
+            match action {
+                BackToMain => {
+                    transitions.write(Transition::Exit);
+                }
+                // Only a single practice game is generated at a time, which
+                // is shown on its button.
+                StartPractice(_) if practice_generation.is_running() => {}
+                StartPractice(technique) => {
+                    let start_game = Transition::StartPractice(*technique);
+                    if game.may_continue() {
+                        modal_dialog.open(abandon_game_dialog(start_game));
+                    } else {
+                        transitions.write(start_game);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Shows which technique a practice game is being generated for.
+pub fn render_practice_generation(
+    mut texts: Query<(&mut Text2d, &TechniqueButtonText)>,
+    practice_generation: Res<PracticeGeneration>,
+) {
+    if !practice_generation.is_changed() {
+        return;
+    }
+
+    let generating = practice_generation
+        .0
+        .as_ref()
+        .map(|(technique, _)| *technique);
+    for (mut text, TechniqueButtonText(technique)) in &mut texts {
+        text.0 = if generating == Some(*technique) {
+            "Generating...".to_owned()
+        } else {
+            format_technique(*technique).to_owned()
+        };
+    }
+}
+
+/// Starts the practice game once it has been generated, or stays on the
+/// practice menu if no Sudoku could be found for the technique.
+///
+/// The practice game is only wanted while the practice menu is showing, so
+/// it's dropped once the player leaves it.
+pub fn on_practice_generated(
+    mut practice_generation: ResMut<PracticeGeneration>,
+    mut screen_state: ResMut<NextState<ScreenState>>,
+    mut mode_state: ResMut<NextState<ModeState>>,
+    mut game: ResMut<Game>,
+    mut game_timer: ResMut<GameTimer>,
+    mut selection: ResMut<Selection>,
+    mut statistics: ResMut<Statistics>,
+    persistence: Res<Persistence>,
+    screen: Res<State<ScreenState>>,
+) {
+    if !practice_generation.is_running() {
+        return;
+    }
+    if *screen.get() != ScreenState::SelectTechnique {
+        practice_generation.cancel();
+        return;
+    }
+
+    let Some((_, task)) = practice_generation.0.as_mut() else {
+        return;
+    };
+    let Some(result) = check_ready(task) else {
+        return;
+    };
+    practice_generation.0 = None;
+
+    match result {
+        Ok(practice_game) => {
+            *game = practice_game;
+            statistics.record_start(&persistence, &game);
+            *selection = Selection::new_for_game(&game);
+            mode_state.set(ModeState::Normal);
+            screen_state.set(ScreenState::Game);
+            game_timer.elapsed_secs = 0.;
+        }
+        Err(err) => report_error("Can't generate practice game", err),
+    }
+}
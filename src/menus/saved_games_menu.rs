@@ -0,0 +1,441 @@
+use bevy::input::{
+    keyboard::{Key, KeyboardInput},
+    ButtonState,
+};
+use bevy::prelude::*;
+
+use crate::persistence::Persistence;
+use crate::statistics::{GameOutcome, Statistics};
+use crate::sudoku::Game;
+use crate::{constants::*, ui::*, utils::*, ResourceBag, ScreenState, Transition};
+
+/// The number of saved games shown per page.
+const NUM_ROWS: usize = 6;
+
+/// Keeps names short enough to fit in a row.
+const MAX_NAME_LEN: usize = 16;
+
+#[derive(Component)]
+pub enum SavedGamesButtonAction {
+    Back,
+    PreviousPage,
+    NextPage,
+    Resume(usize),
+    Rename(usize),
+    Delete(usize),
+}
+
+/// The row with the given index on the current page.
+#[derive(Component)]
+pub struct SavedGameRow(usize);
+
+#[derive(Clone, Copy, Component)]
+pub enum SavedGameText {
+    Title,
+    /// Explains why there are no games to show.
+    Status,
+    /// Describes the game in the row with the given index.
+    Summary(usize),
+    Rename(usize),
+}
+
+/// The games listed on the saved games screen.
+#[derive(Default, Resource)]
+pub struct SavedGames {
+    /// Whether the completed games are listed, instead of the ones in
+    /// progress.
+    completed: bool,
+    page: usize,
+    /// The slots of all the listed games, the most recent first.
+    slots: Vec<u64>,
+    /// The games on the current page, which are the only ones that are read.
+    games: Vec<Game>,
+    /// The row of the game that is being renamed, and the name typed so far.
+    renaming: Option<(usize, String)>,
+}
+
+impl SavedGames {
+    fn reload(&mut self, persistence: &Persistence) {
+        self.slots = if self.completed {
+            Game::list_archived_slots(persistence)
+        } else {
+            Game::list_saved_slots(persistence)
+        };
+        self.load_page(persistence);
+    }
+
+    /// Reads the games on the current page.
+    fn load_page(&mut self, persistence: &Persistence) {
+        self.page = self.page.min(self.num_pages() - 1);
+        self.renaming = None;
+
+        self.games.clear();
+        let mut index = self.page * NUM_ROWS;
+        while self.games.len() < NUM_ROWS && index < self.slots.len() {
+            let slot = self.slots[index];
+            let game = if self.completed {
+                Game::load_archived_slot(persistence, slot)
+            } else {
+                Game::load_slot(persistence, slot)
+            };
+            match game {
+                Some(game) => {
+                    self.games.push(game);
+                    index += 1;
+                }
+                // Games that can't be restored were set aside, and the player
+                // was told.
+                None => {
+                    self.slots.remove(index);
+                }
+            }
+        }
+
+        // The page may be gone, if none of its games could be restored.
+        if self.games.is_empty() && self.page >= self.num_pages() {
+            self.load_page(persistence);
+        }
+    }
+
+    fn num_pages(&self) -> usize {
+        self.slots.len().div_ceil(NUM_ROWS).max(1)
+    }
+
+    fn get(&self, row: usize) -> Option<&Game> {
+        self.games.get(row)
+    }
+
+    /// Flips through the pages of the games in progress, followed by the pages
+    /// of the completed games.
+    fn cycle(&mut self, persistence: &Persistence, offset: isize) {
+        let page = self.page as isize + offset;
+        if page >= 0 && page < self.num_pages() as isize {
+            self.page = page as usize;
+            self.load_page(persistence);
+        } else {
+            self.completed = !self.completed;
+            self.page = if offset < 0 { usize::MAX } else { 0 };
+            self.reload(persistence);
+        }
+    }
+
+    fn finish_renaming(&mut self, persistence: &Persistence, current_game: &mut Game) {
+        let Some((row, name)) = self.renaming.take() else {
+            return;
+        };
+
+        if let Some(game) = self.games.get_mut(row) {
+            game.rename(persistence, &name);
+            if game.slot == current_game.slot {
+                current_game.name = game.name.clone();
+            }
+        }
+    }
+
+    /// Deletes the game in the given save slot, and removes it from the list.
+    ///
+    /// The next game moves up to the current page, so it has to be read.
+    pub fn delete(
+        &mut self,
+        persistence: &Persistence,
+        slot: u64,
+        current_game: &mut Game,
+        statistics: &mut Statistics,
+    ) {
+        let Some(index) = self.games.iter().position(|game| game.slot == slot) else {
+            return;
+        };
+
+        let game = self.games.remove(index);
+        self.slots.retain(|&listed_slot| listed_slot != slot);
+        game.delete(persistence);
+        if game.may_continue() {
+            statistics.record_end(persistence, &game, GameOutcome::Abandoned);
+        }
+        if game.slot == current_game.slot {
+            *current_game = Game::default();
+        }
+
+        self.load_page(persistence);
+    }
+}
+
+/// Asks whether the given game should really be deleted.
+fn delete_game_dialog(game: &Game, completed: bool) -> Dialog {
+    Dialog {
+        title: "Delete this game?".to_owned(),
+        message: format!(
+            "{}\nDeleted games can't be restored.",
+            format_summary(game, completed)
+        ),
+        choices: vec![
+            DialogChoice::new(
+                "Delete",
+                ButtonType::Primary,
+                vec![Transition::DeleteSavedGame(game.slot)],
+            ),
+            DialogChoice::cancel("Cancel"),
+        ],
+    }
+}
+
+pub fn saved_games_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
+    use SavedGamesButtonAction::*;
+
+    fragment5(
+        // Page switcher.
+        row(
+            highscore_switcher_size,
+            gap(Val::Auto),
+            fragment3(
+                ternary_button(
+                    PreviousPage,
+                    highscore_switcher_button_size,
+                    text("<", button_text),
+                ),
+                row(
+                    highscore_switcher_title_size,
+                    (),
+                    text_t(
+                        SavedGameText::Title,
+                        "In Progress",
+                        (button_text_size, font_bold, text_color(COLOR_MAIN_DARKER)),
+                    ),
+                ),
+                ternary_button(
+                    NextPage,
+                    highscore_switcher_button_size,
+                    text(">", button_text),
+                ),
+            ),
+        ),
+        // Saved games.
+        column(
+            fixed_size(Val::Percent(100.), Val::Vmin(66.)),
+            padding(Sides::vertical(Val::Vmin(2.))),
+            |props: &Props, spawner: &mut ChildSpawnerCommands| {
+                for row in 0..NUM_ROWS {
+                    spawner.spawn_with_children(props, saved_game_row(row));
+                }
+            },
+        ),
+        row(
+            fixed_size(Val::Percent(100.), Val::Vmin(6.)),
+            (),
+            text_t(SavedGameText::Status, "", detail_text_style),
+        ),
+        leaf(available_size),
+        column(
+            available_size,
+            padding(Sides::vertical(Val::Auto)),
+            secondary_button(Back, button_size_settings, text("Back", button_text)),
+        ),
+    )
+}
+
+fn saved_game_row(row: usize) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    use SavedGamesButtonAction::*;
+
+    row_t(
+        SavedGameRow(row),
+        (
+            fixed_size(Val::Vmin(86.), Val::Vmin(11.)),
+            align_self(Alignment::Centered),
+        ),
+        gap(Val::Auto),
+        fragment3(
+            ternary_button(
+                Resume(row),
+                fixed_size(Val::Vmin(54.), Val::Vmin(9.)),
+                text_t(SavedGameText::Summary(row), "", detail_text_style),
+            ),
+            ternary_button(
+                Rename(row),
+                fixed_size(Val::Vmin(15.), Val::Vmin(9.)),
+                text_t(SavedGameText::Rename(row), "Rename", detail_text_style),
+            ),
+            ternary_button(
+                Delete(row),
+                fixed_size(Val::Vmin(15.), Val::Vmin(9.)),
+                text("Delete", detail_text_style),
+            ),
+        ),
+    )
+}
+
+fn detail_text_style(bundle: &mut FlexTextBundle, resources: &ResourceBag) {
+    font_medium(bundle, resources);
+    font_size(25.)(bundle, resources);
+    text_color(COLOR_MAIN_DARKER)(bundle, resources);
+}
+
+pub fn saved_games_button_actions(
+    query: Query<(&Interaction, &SavedGamesButtonAction), (Changed<Interaction>, With<Button>)>,
+    mut transitions: MessageWriter<Transition>,
+    mut saved_games: ResMut<SavedGames>,
+    mut game: ResMut<Game>,
+    mut modal_dialog: ResMut<ModalDialog>,
+    persistence: Res<Persistence>,
+) {
+    for (interaction, action) in &query {
+        if *interaction == Interaction::Pressed {
+            use SavedGamesButtonAction::*;
+            match *action {
+                Back => {
+                    transitions.write(Transition::Exit);
+                }
+                PreviousPage => saved_games.cycle(&persistence, -1),
+                NextPage => saved_games.cycle(&persistence, 1),
+                Resume(row) => {
+                    if let Some(saved_game) = saved_games.get(row) {
+                        // Completed games can only be looked at.
+                        if !saved_games.completed {
+                            transitions.write(Transition::ResumeGame(saved_game.slot));
+                        }
+                    }
+                }
+                Rename(row) => {
+                    if saved_games
+                        .renaming
+                        .as_ref()
+                        .is_some_and(|(renaming_row, _)| *renaming_row == row)
+                    {
+                        saved_games.finish_renaming(&persistence, &mut game);
+                    } else if let Some(saved_game) = saved_games.get(row) {
+                        let name = saved_game.name.clone().unwrap_or_default();
+                        saved_games.renaming = Some((row, name));
+                    }
+                }
+                Delete(row) => {
+                    if let Some(saved_game) = saved_games.get(row) {
+                        modal_dialog.open(delete_game_dialog(saved_game, saved_games.completed));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Types the name of the game that is being renamed.
+pub fn saved_games_keyboard_input(
+    mut keyboard_input: MessageReader<KeyboardInput>,
+    mut saved_games: ResMut<SavedGames>,
+    mut game: ResMut<Game>,
+    persistence: Res<Persistence>,
+) {
+    for input in keyboard_input.read() {
+        if input.state != ButtonState::Pressed || saved_games.renaming.is_none() {
+            continue;
+        }
+
+        match &input.logical_key {
+            Key::Enter => saved_games.finish_renaming(&persistence, &mut game),
+            key => {
+                let Some((_, name)) = &mut saved_games.renaming else {
+                    continue;
+                };
+
+                match key {
+                    Key::Backspace => {
+                        name.pop();
+                    }
+                    Key::Space if name.chars().count() < MAX_NAME_LEN => name.push(' '),
+                    Key::Character(text) if name.chars().count() < MAX_NAME_LEN => {
+                        name.push_str(text)
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Lists the games in progress whenever the screen is opened.
+pub fn on_saved_games_opened(
+    mut saved_games: ResMut<SavedGames>,
+    persistence: Res<Persistence>,
+    screen_state: Res<State<ScreenState>>,
+) {
+    if screen_state.is_changed() && screen_state.get() == &ScreenState::SavedGames {
+        saved_games.completed = false;
+        saved_games.page = 0;
+        saved_games.reload(&persistence);
+    }
+}
+
+pub fn render_saved_games(
+    mut rows: Query<(&SavedGameRow, &mut Visibility)>,
+    mut texts: Query<(&mut Text2d, &SavedGameText)>,
+    saved_games: Res<SavedGames>,
+) {
+    if !saved_games.is_changed() {
+        return;
+    }
+
+    for (row, mut visibility) in &mut rows {
+        visibility.set_if_neq(if saved_games.get(row.0).is_some() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+
+    for (mut text, kind) in &mut texts {
+        text.0 = get_saved_game_text(&saved_games, *kind);
+    }
+}
+
+fn get_saved_game_text(saved_games: &SavedGames, kind: SavedGameText) -> String {
+    match kind {
+        SavedGameText::Title => {
+            let title = if saved_games.completed {
+                "Completed"
+            } else {
+                "In Progress"
+            };
+            match saved_games.num_pages() {
+                1 => title.to_owned(),
+                num_pages => format!("{title} ({}/{num_pages})", saved_games.page + 1),
+            }
+        }
+        SavedGameText::Status => match (saved_games.slots.is_empty(), saved_games.completed) {
+            (false, _) => String::new(),
+            (true, false) => "No games in progress.".to_owned(),
+            (true, true) => "No completed games yet.".to_owned(),
+        },
+        SavedGameText::Summary(row) => match &saved_games.renaming {
+            Some((renaming_row, name)) if *renaming_row == row => format!("{name}_"),
+            _ => saved_games
+                .get(row)
+                .map(|game| format_summary(game, saved_games.completed))
+                .unwrap_or_default(),
+        },
+        SavedGameText::Rename(row) => match &saved_games.renaming {
+            Some((renaming_row, _)) if *renaming_row == row => "Done".to_owned(),
+            _ => "Rename".to_owned(),
+        },
+    }
+}
+
+fn format_summary(game: &Game, completed: bool) -> String {
+    let title = match &game.name {
+        Some(name) => format!("{name} · {}", format_difficulty(game.difficulty)),
+        None => format_difficulty(game.difficulty).to_owned(),
+    };
+
+    if !completed {
+        format!(
+            "{title} · {:.0}% · {}",
+            game.progress() * 100.,
+            format_time(game.elapsed_secs)
+        )
+    } else if game.gave_up {
+        format!("{title} · gave up · {}", format_time(game.elapsed_secs))
+    } else {
+        format!(
+            "{title} · {} points · {}",
+            game.score,
+            format_time(game.elapsed_secs)
+        )
+    }
+}
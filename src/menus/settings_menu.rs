@@ -1,16 +1,22 @@
 use bevy::prelude::*;
+use std::path::Path;
 
-use crate::{ui::*, ScreenState, Settings};
+use crate::backup::{self, MergeStrategy, PlayerData, BACKUP_FILE};
+use crate::persistence::Persistence;
+use crate::settings::MistakeHighlighting;
+use crate::sudoku::Game;
+use crate::{ui::*, GameTimer, ScreenState, Settings, Transition};
 
 use super::settings_toggle::*;
 
 #[derive(Component)]
 pub enum SettingsButtonAction {
     Back,
+    ExportData,
+    ImportData,
 }
 
 pub fn settings_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
-    use SettingsButtonAction::*;
     use SettingsToggle::*;
 
     fragment3(
@@ -18,29 +24,123 @@ pub fn settings_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
         column(
             available_size,
             padding(Sides::vertical(Val::Auto)),
-            fragment4(
+            fragment6(
                 settings_toggle("Wheel swipe aid", EnableWheelAid),
                 settings_toggle("Selected cell highlight", SelectedCellHighlight),
                 settings_toggle("Reveal mistakes", ShowMistakes),
+                settings_toggle("Highlight conflicts", ShowConflicts),
+                settings_toggle("Show wrong cells on check", HighlightCheckedCells),
                 settings_toggle("Auto-fill correct notes", AutofillCorrectNotes),
             ),
         ),
         column(
             available_size,
             padding(Sides::vertical(Val::Auto)),
-            secondary_button(Back, button_size_settings, text("Back", button_text)),
+            settings_buttons,
         ),
     )
 }
 
+fn settings_buttons(props: &Props, spawner: &mut ChildSpawnerCommands) {
+    use SettingsButtonAction::*;
+
+    // Backups are exchanged through the data dir, which players can only
+    // reach on desktop.
+    if cfg!(not(any(target_os = "android", target_os = "ios"))) {
+        spawner.spawn_with_children(
+            props,
+            secondary_button(
+                ExportData,
+                (button_size_settings, button_margin),
+                text("Export Data", button_text),
+            ),
+        );
+
+        spawner.spawn_with_children(
+            props,
+            secondary_button(
+                ImportData,
+                (button_size_settings, button_margin),
+                text("Import Data", button_text),
+            ),
+        );
+    }
+
+    spawner.spawn_with_children(
+        props,
+        secondary_button(Back, button_size_settings, text("Back", button_text)),
+    );
+}
+
+/// Shows what's in the backup, and asks how to merge it with the player's
+/// data.
+fn import_data_dialog(imported: &PlayerData) -> Dialog {
+    Dialog {
+        title: "Import data?".to_owned(),
+        message: imported.summarize(),
+        choices: vec![
+            DialogChoice::new(
+                "Keep Best",
+                ButtonType::Primary,
+                vec![Transition::ImportData(MergeStrategy::KeepBest)],
+            ),
+            DialogChoice::new(
+                "Replace Mine",
+                ButtonType::Ternary,
+                vec![Transition::ImportData(MergeStrategy::Replace)],
+            ),
+            DialogChoice::new(
+                "Only Add New",
+                ButtonType::Secondary,
+                vec![Transition::ImportData(MergeStrategy::Skip)],
+            ),
+            DialogChoice::cancel("Cancel"),
+        ],
+    }
+}
+
 pub fn settings_screen_button_actions(
     query: Query<(&Interaction, &SettingsButtonAction), Changed<Interaction>>,
     mut screen_state: ResMut<NextState<ScreenState>>,
+    mut modal_dialog: ResMut<ModalDialog>,
+    mut toasts: ResMut<Toasts>,
+    mut game: ResMut<Game>,
+    game_timer: Res<GameTimer>,
+    persistence: Res<Persistence>,
 ) {
     for (interaction, action) in &query {
         if *interaction == Interaction::Pressed {
             match action {
                 SettingsButtonAction::Back => screen_state.set(ScreenState::Game),
+                SettingsButtonAction::ExportData => {
+                    // Store the current game first, so the backup has its
+                    // latest progress.
+                    game.elapsed_secs = game_timer.elapsed_secs;
+                    game.save(&persistence);
+
+                    let result = backup::export(&persistence)
+                        .and_then(|json| persistence.write_now(Path::new(BACKUP_FILE), &json));
+                    match result {
+                        Ok(()) => toasts.push(
+                            Severity::Info,
+                            format!("Exported to {BACKUP_FILE} in\n{}", persistence.location()),
+                        ),
+                        Err(err) => report_error("Can't export data", err),
+                    }
+                }
+                SettingsButtonAction::ImportData => match backup::read_backup(&persistence) {
+                    Ok(imported) => modal_dialog.open(import_data_dialog(&imported)),
+                    Err(err) if persistence.exists(Path::new(BACKUP_FILE)) => {
+                        report_error("Can't read backup", err)
+                    }
+                    Err(_) => toasts.push(
+                        Severity::Warning,
+                        format!(
+                            "Place {BACKUP_FILE} in\n{} to import it",
+                            persistence.location()
+                        ),
+                    ),
+                },
             }
         }
     }
@@ -49,6 +149,7 @@ pub fn settings_screen_button_actions(
 pub fn settings_toggle_actions(
     query: Query<(&Interaction, &SettingsToggle), Changed<Interaction>>,
     mut settings: ResMut<Settings>,
+    persistence: Res<Persistence>,
 ) {
     for (interaction, toggle) in &query {
         if *interaction == Interaction::Pressed {
@@ -62,16 +163,41 @@ pub fn settings_toggle_actions(
                 SettingsToggle::SelectedCellHighlight => {
                     settings.selected_cell_highlight = !settings.selected_cell_highlight;
                 }
+                // Mistakes are highlighted in one way at most, so enabling
+                // either of these disables the other.
                 SettingsToggle::ShowMistakes => {
-                    settings.show_mistakes = !settings.show_mistakes;
+                    settings.mistake_highlighting = toggle_mistake_highlighting(
+                        settings.mistake_highlighting,
+                        MistakeHighlighting::AgainstSolution,
+                    );
+                }
+                SettingsToggle::ShowConflicts => {
+                    settings.mistake_highlighting = toggle_mistake_highlighting(
+                        settings.mistake_highlighting,
+                        MistakeHighlighting::Conflicts,
+                    );
+                }
+                SettingsToggle::HighlightCheckedCells => {
+                    settings.highlight_checked_cells = !settings.highlight_checked_cells;
                 }
             }
 
-            settings.save();
+            settings.save(&persistence);
         }
     }
 }
 
+fn toggle_mistake_highlighting(
+    current: MistakeHighlighting,
+    toggled: MistakeHighlighting,
+) -> MistakeHighlighting {
+    if current == toggled {
+        MistakeHighlighting::Off
+    } else {
+        toggled
+    }
+}
+
 // Updates the `ToggleEnabled` component when the setting is switched.
 pub fn on_setting_change(
     mut commands: Commands,
@@ -1,5 +1,6 @@
 use bevy::{prelude::*, sprite::Anchor};
 
+use crate::settings::MistakeHighlighting;
 use crate::{ui::*, utils::*, Images, Settings};
 
 #[derive(Clone, Component, Copy)]
@@ -8,6 +9,8 @@ pub enum SettingsToggle {
     EnableWheelAid,
     SelectedCellHighlight,
     ShowMistakes,
+    ShowConflicts,
+    HighlightCheckedCells,
 }
 
 #[derive(Default, Resource)]
@@ -27,7 +30,13 @@ impl SettingsToggle {
             SettingsToggle::AutofillCorrectNotes => settings.autofill_correct_notes,
             SettingsToggle::EnableWheelAid => settings.enable_wheel_aid,
             SettingsToggle::SelectedCellHighlight => settings.selected_cell_highlight,
-            SettingsToggle::ShowMistakes => settings.show_mistakes,
+            SettingsToggle::ShowMistakes => {
+                settings.mistake_highlighting == MistakeHighlighting::AgainstSolution
+            }
+            SettingsToggle::ShowConflicts => {
+                settings.mistake_highlighting == MistakeHighlighting::Conflicts
+            }
+            SettingsToggle::HighlightCheckedCells => settings.highlight_checked_cells,
         }
     }
 }
@@ -0,0 +1,302 @@
+use bevy::{prelude::*, sprite::Anchor};
+
+use crate::persistence::Persistence;
+use crate::statistics::{DifficultyStatistics, Statistics};
+use crate::sudoku::Difficulty;
+use crate::{constants::*, ui::*, utils::*, ResourceBag, ScreenState};
+
+const DIFFICULTIES: [Difficulty; 5] = [
+    Difficulty::Trivial,
+    Difficulty::Easy,
+    Difficulty::Medium,
+    Difficulty::Advanced,
+    Difficulty::Expert,
+];
+
+#[derive(Component)]
+pub enum StatisticsButtonAction {
+    Back,
+    PreviousDifficulty,
+    NextDifficulty,
+    Export,
+}
+
+/// The difficulty for which statistics are shown.
+#[derive(Default, Resource)]
+pub struct StatisticsDifficulty(Difficulty);
+
+#[derive(Component)]
+pub struct StatisticsDifficultyTitle;
+
+#[derive(Component)]
+pub struct ExportStatusText;
+
+#[derive(Clone, Copy, Component)]
+pub enum StatisticText {
+    Played,
+    WinRate,
+    BestTime,
+    AverageTime,
+    AverageMistakes,
+    AverageHints,
+    CurrentStreak,
+    LongestStreak,
+    /// The number of mistakes made with the number at the given index,
+    /// starting with the 1.
+    MistakesOfNumber(usize),
+}
+
+pub fn statistics_screen() -> impl FnOnce(&Props, &mut ChildSpawnerCommands) {
+    use StatisticsButtonAction::*;
+
+    fragment5(
+        // Difficulty switcher.
+        row(
+            highscore_switcher_size,
+            gap(Val::Auto),
+            fragment3(
+                ternary_button(
+                    PreviousDifficulty,
+                    highscore_switcher_button_size,
+                    text("<", button_text),
+                ),
+                row(
+                    highscore_switcher_title_size,
+                    (),
+                    text_t(
+                        StatisticsDifficultyTitle,
+                        format_difficulty(Difficulty::default()),
+                        (button_text_size, font_bold, text_color(COLOR_MAIN_DARKER)),
+                    ),
+                ),
+                ternary_button(
+                    NextDifficulty,
+                    highscore_switcher_button_size,
+                    text(">", button_text),
+                ),
+            ),
+        ),
+        // Statistics.
+        column(
+            fixed_size(Val::Percent(100.), Val::Vmin(80.)),
+            (),
+            fragment3(
+                |props: &Props, spawner: &mut ChildSpawnerCommands| {
+                    use StatisticText::*;
+                    for (kind, label) in [
+                        (Played, "Games played:"),
+                        (WinRate, "Win rate:"),
+                        (BestTime, "Best time:"),
+                        (AverageTime, "Average time:"),
+                        (AverageMistakes, "Average mistakes:"),
+                        (AverageHints, "Average hints:"),
+                        (CurrentStreak, "Current streak:"),
+                        (LongestStreak, "Longest streak:"),
+                    ] {
+                        spawner.spawn_with_children(props, statistic_row(kind, label));
+                    }
+                },
+                row(
+                    available_size,
+                    (),
+                    text(
+                        "Mistakes per number:",
+                        (button_text_size, font_bold, text_color(COLOR_MAIN_DARKER)),
+                    ),
+                ),
+                row(
+                    available_size,
+                    (),
+                    |props: &Props, spawner: &mut ChildSpawnerCommands| {
+                        for index in 0..9 {
+                            spawner.spawn_with_children(props, mistakes_of_number(index));
+                        }
+                    },
+                ),
+            ),
+        ),
+        // Export.
+        column(
+            available_size,
+            padding(Sides::vertical(Val::Auto)),
+            fragment(
+                ternary_button(Export, button_size_settings, text("Export", button_text)),
+                row(
+                    fixed_size(Val::Percent(100.), Val::Vmin(6.)),
+                    (),
+                    text_t(
+                        ExportStatusText,
+                        "",
+                        (
+                            font_medium,
+                            font_size(25.),
+                            text_color(COLOR_BOARD_LINE_MEDIUM),
+                        ),
+                    ),
+                ),
+            ),
+        ),
+        leaf(available_size),
+        column(
+            available_size,
+            padding(Sides::vertical(Val::Auto)),
+            secondary_button(Back, button_size_settings, text("Back", button_text)),
+        ),
+    )
+}
+
+fn statistic_row(
+    kind: StatisticText,
+    label: &str,
+) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    row(
+        available_size,
+        (),
+        fragment(
+            row(
+                preferred_size(Val::Percent(50.), Val::Percent(100.)),
+                (),
+                text(
+                    label.to_owned(),
+                    statistic_text_style(Anchor::CENTER_RIGHT, COLOR_MAIN_DARKER),
+                ),
+            ),
+            row(
+                (
+                    preferred_size(Val::Percent(40.), Val::Percent(100.)),
+                    margin(Size::new(Val::Percent(5.), Val::None)),
+                ),
+                (),
+                text_t(
+                    kind,
+                    "",
+                    statistic_text_style(Anchor::CENTER_LEFT, COLOR_POP_FOCUS),
+                ),
+            ),
+        ),
+    )
+}
+
+/// Returns the column with a number and the mistakes that were made with it.
+fn mistakes_of_number(
+    index: usize,
+) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    column(
+        available_size,
+        (),
+        fragment(
+            row(
+                available_size,
+                (),
+                text(
+                    (index + 1).to_string(),
+                    (button_text_size, font_bold, text_color(COLOR_MAIN_DARKER)),
+                ),
+            ),
+            row(
+                available_size,
+                (),
+                text_t(
+                    StatisticText::MistakesOfNumber(index),
+                    "",
+                    (button_text_size, font_medium, text_color(COLOR_POP_FOCUS)),
+                ),
+            ),
+        ),
+    )
+}
+
+fn statistic_text_style(
+    anchor: Anchor,
+    color: Color,
+) -> impl FnOnce(&mut FlexTextBundle, &ResourceBag) {
+    move |bundle: &mut FlexTextBundle, resources: &ResourceBag| {
+        button_text_size(bundle, resources);
+        font_medium(bundle, resources);
+        text_anchor(anchor)(bundle, resources);
+        text_color(color)(bundle, resources);
+    }
+}
+
+pub fn statistics_screen_button_actions(
+    query: Query<(&Interaction, &StatisticsButtonAction), Changed<Interaction>>,
+    mut export_status: Query<&mut Text2d, With<ExportStatusText>>,
+    mut screen_state: ResMut<NextState<ScreenState>>,
+    mut difficulty: ResMut<StatisticsDifficulty>,
+    statistics: Res<Statistics>,
+    persistence: Res<Persistence>,
+) {
+    for (interaction, action) in &query {
+        if *interaction == Interaction::Pressed {
+            use StatisticsButtonAction::*;
+            match action {
+                Back => screen_state.set(ScreenState::MainMenu),
+                PreviousDifficulty => difficulty.cycle(-1),
+                NextDifficulty => difficulty.cycle(1),
+                Export => {
+                    let status = match statistics.export(&persistence) {
+                        Ok(dir) => format!("Exported to {dir}"),
+                        Err(err) => {
+                            report_error("Can't export statistics", err);
+                            "Export failed".to_owned()
+                        }
+                    };
+                    for mut text in &mut export_status {
+                        text.0 = status.clone();
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl StatisticsDifficulty {
+    fn cycle(&mut self, offset: isize) {
+        let index = DIFFICULTIES.iter().position(|d| *d == self.0).unwrap_or(0);
+        self.0 = DIFFICULTIES
+            [(index as isize + offset).rem_euclid(DIFFICULTIES.len() as isize) as usize];
+    }
+}
+
+pub fn render_statistics(
+    mut texts: Query<(&mut Text2d, &StatisticText)>,
+    mut title: Query<&mut Text2d, (With<StatisticsDifficultyTitle>, Without<StatisticText>)>,
+    statistics: Res<Statistics>,
+    difficulty: Res<StatisticsDifficulty>,
+) {
+    if !statistics.is_changed() && !difficulty.is_changed() {
+        return;
+    }
+
+    let stats = statistics.summarize(difficulty.0);
+    for (mut text, kind) in &mut texts {
+        text.0 = get_statistic_text(&stats, *kind);
+    }
+
+    for mut text in &mut title {
+        text.0 = format_difficulty(difficulty.0).to_owned();
+    }
+}
+
+fn get_statistic_text(stats: &DifficultyStatistics, kind: StatisticText) -> String {
+    let format_average = |average: Option<f32>| {
+        average.map_or_else(|| "-".to_owned(), |average| format!("{average:.1}"))
+    };
+
+    match kind {
+        StatisticText::Played => stats.num_played.to_string(),
+        StatisticText::WinRate => stats.win_rate().map_or_else(
+            || "-".to_owned(),
+            |win_rate| format!("{:.0}%", win_rate * 100.),
+        ),
+        StatisticText::BestTime => stats.best_time.map_or_else(|| "-".to_owned(), format_time),
+        StatisticText::AverageTime => stats
+            .average_time
+            .map_or_else(|| "-".to_owned(), format_time),
+        StatisticText::AverageMistakes => format_average(stats.average_mistakes),
+        StatisticText::AverageHints => format_average(stats.average_hints),
+        StatisticText::CurrentStreak => stats.current_streak.to_string(),
+        StatisticText::LongestStreak => stats.longest_streak.to_string(),
+        StatisticText::MistakesOfNumber(index) => stats.num_mistakes_by_number[index].to_string(),
+    }
+}
@@ -0,0 +1,670 @@
+//! Keeps the player's data in a storage, writing it in the background.
+//!
+//! Saving happens on every move, so files are written behind the game's back:
+//! changes are queued, and a background thread writes them once they settle.
+//! Changes to the same file are coalesced, so only the latest one is written.
+//!
+//! Files are replaced atomically, see `Storage::write()`. The previous version
+//! is kept as a backup, which is loaded instead when the file itself can't be.
+//! Files that can't be loaded at all are set aside as `*.corrupt`, rather than
+//! being overwritten.
+//!
+//! Every file records the version of its schema, so that files saved by older
+//! versions of the game can be migrated, one version at a time. Files saved by
+//! newer versions are left untouched, and never saved over.
+
+use anyhow::{anyhow, Context};
+use bevy::prelude::Resource;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::storage::Storage;
+use crate::ui::{notify, report_error, Severity};
+
+/// How long changes are collected before they're written.
+const COALESCE_DELAY: Duration = Duration::from_millis(500);
+
+/// The field that holds the schema version of a file.
+const VERSION_FIELD: &str = "version";
+
+/// Upgrades the contents of a file from one schema version to the next.
+pub type Migration = fn(Value) -> Result<Value, anyhow::Error>;
+
+/// The error for files that were saved with a schema version that is newer
+/// than this version of the game knows about.
+#[derive(Debug)]
+pub struct UnsupportedVersion(usize);
+
+impl fmt::Display for UnsupportedVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Saved with schema version {}, which is newer than this version of the game",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedVersion {}
+
+/// Keeps the player's data in a storage, with paths relative to its root.
+///
+/// The app inserts it as a resource, so systems can load and save through it.
+/// Clones share the same storage and background thread.
+#[derive(Clone, Resource)]
+pub struct Persistence {
+    writer: Arc<Writer>,
+    _shutdown: Arc<Shutdown>,
+}
+
+/// Stops the background thread once the last clone of a persistence is
+/// dropped, after it wrote whatever was still pending.
+struct Shutdown(Arc<Writer>);
+
+impl Drop for Shutdown {
+    fn drop(&mut self) {
+        self.0.lock_pending().is_stopping = true;
+        self.0.changed.notify_one();
+    }
+}
+
+#[derive(Clone)]
+enum Change {
+    Write(Vec<u8>),
+    Remove,
+}
+
+/// The changes that haven't been written yet, by path.
+///
+/// Every change gets a generation, so the writer can tell whether a file was
+/// changed again while it was being written.
+#[derive(Default)]
+struct Pending {
+    changes: BTreeMap<PathBuf, (u64, Change)>,
+    next_generation: u64,
+    /// Whether the background thread should stop, see `Shutdown`.
+    is_stopping: bool,
+}
+
+struct Writer {
+    storage: Box<dyn Storage>,
+    pending: Mutex<Pending>,
+    changed: Condvar,
+    /// Held while writing, so flushing doesn't race the background thread.
+    writing: Mutex<()>,
+    /// The files that were saved by a newer version of the game, which must
+    /// not be changed.
+    unsupported: Mutex<BTreeSet<PathBuf>>,
+}
+
+impl Writer {
+    fn lock_pending(&self) -> MutexGuard<'_, Pending> {
+        self.pending.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn is_unsupported(&self, path: &Path) -> bool {
+        self.unsupported
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .contains(path)
+    }
+
+    fn queue(&self, path: PathBuf, change: Change) {
+        if self.is_unsupported(&path) {
+            println!(
+                "Not saving {}, since it's from a newer version of the game",
+                path.display()
+            );
+            return;
+        }
+
+        let mut pending = self.lock_pending();
+        let generation = pending.next_generation;
+        pending.next_generation += 1;
+        pending.changes.insert(path, (generation, change));
+        self.changed.notify_one();
+    }
+
+    fn get_pending(&self, path: &Path) -> Option<Change> {
+        self.lock_pending()
+            .changes
+            .get(path)
+            .map(|(_, change)| change.clone())
+    }
+
+    /// Writes all pending changes to the storage.
+    ///
+    /// Changes stay pending until they're written, so reads keep seeing them
+    /// in the meantime.
+    fn write_pending(&self) {
+        let _writing = self.writing.lock().unwrap_or_else(|err| err.into_inner());
+
+        let batch: Vec<(PathBuf, u64, Change)> = self
+            .lock_pending()
+            .changes
+            .iter()
+            .map(|(path, (generation, change))| (path.clone(), *generation, change.clone()))
+            .collect();
+
+        for (path, _, change) in &batch {
+            let result = match change {
+                Change::Write(contents) => self.write_with_backup(path, contents),
+                Change::Remove => self
+                    .storage
+                    .remove(path)
+                    .and_then(|_| self.storage.remove(&backup_path(path))),
+            };
+            if let Err(err) = result {
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                report_error(&format!("Can't save {file_name}"), err);
+            }
+        }
+
+        let mut pending = self.lock_pending();
+        for (path, generation, _) in batch {
+            if pending
+                .changes
+                .get(&path)
+                .is_some_and(|(pending_generation, _)| *pending_generation == generation)
+            {
+                pending.changes.remove(&path);
+            }
+        }
+    }
+
+    /// Replaces the file, and keeps its previous version as a backup.
+    fn write_with_backup(&self, path: &Path, contents: &[u8]) -> Result<(), anyhow::Error> {
+        if let Ok(previous) = self.storage.read(path) {
+            self.storage
+                .write(&backup_path(path), &previous)
+                .context("Can't back up file")?;
+        }
+        self.storage.write(path, contents)
+    }
+
+    fn run(&self) {
+        loop {
+            let is_stopping = {
+                let mut pending = self.lock_pending();
+                while pending.changes.is_empty() && !pending.is_stopping {
+                    pending = self
+                        .changed
+                        .wait(pending)
+                        .unwrap_or_else(|err| err.into_inner());
+                }
+                pending.is_stopping
+            };
+
+            if is_stopping {
+                self.write_pending();
+                return;
+            }
+
+            // Lets a burst of changes, like a series of moves, settle first.
+            thread::sleep(COALESCE_DELAY);
+            self.write_pending();
+        }
+    }
+}
+
+impl Persistence {
+    /// Keeps the player's data in the given storage, and starts the
+    /// background thread that writes to it.
+    pub fn new(storage: impl Storage) -> Self {
+        let writer = Arc::new(Writer {
+            storage: Box::new(storage),
+            pending: Mutex::default(),
+            changed: Condvar::new(),
+            writing: Mutex::default(),
+            unsupported: Mutex::default(),
+        });
+
+        let background_writer = writer.clone();
+        thread::Builder::new()
+            .name("persistence".to_owned())
+            .spawn(move || background_writer.run())
+            .unwrap_or_else(|err| panic!("Can't start persistence thread: {err}"));
+
+        Self {
+            _shutdown: Arc::new(Shutdown(writer.clone())),
+            writer,
+        }
+    }
+
+    /// Describes where the player's data is kept.
+    pub fn location(&self) -> String {
+        self.writer.storage.location()
+    }
+
+    /// Queues the contents to be written to the given path.
+    pub fn write(&self, path: impl Into<PathBuf>, contents: Vec<u8>) {
+        self.writer.queue(path.into(), Change::Write(contents));
+    }
+
+    /// Writes the contents to the given path right away, for files the player
+    /// is waiting for, like exports.
+    pub fn write_now(&self, path: &Path, contents: &[u8]) -> Result<(), anyhow::Error> {
+        if self.writer.is_unsupported(path) {
+            return Err(anyhow!("File is from a newer version of the game"));
+        }
+
+        self.flush();
+        self.writer.storage.write(path, contents)
+    }
+
+    /// Queues the file at the given path to be removed, along with its backup.
+    pub fn remove(&self, path: impl Into<PathBuf>) {
+        self.writer.queue(path.into(), Change::Remove);
+    }
+
+    /// Writes all pending changes before returning.
+    ///
+    /// This should be called before the app exits.
+    pub fn flush(&self) {
+        self.writer.write_pending();
+    }
+
+    /// Reads the file at the given path, including changes that haven't been
+    /// written yet.
+    pub fn read(&self, path: &Path) -> Result<Vec<u8>, anyhow::Error> {
+        match self.writer.get_pending(path) {
+            Some(Change::Write(contents)) => Ok(contents),
+            Some(Change::Remove) => Err(anyhow!("File was removed")),
+            None => self.writer.storage.read(path),
+        }
+    }
+
+    /// Reads and parses the file at the given path, falling back to its
+    /// backup if the file itself can't be read or parsed.
+    ///
+    /// If neither can be parsed, the file is set aside, so that it's not
+    /// overwritten by the next save. Files from a newer version of the game
+    /// are left where they are instead, and are never written to, see
+    /// `UnsupportedVersion`.
+    pub fn load<T>(
+        &self,
+        path: &Path,
+        parse: impl Fn(&[u8]) -> Result<T, anyhow::Error>,
+    ) -> Result<T, anyhow::Error> {
+        let (err, is_corrupt) = match self.read(path) {
+            Ok(contents) => match parse(&contents) {
+                Ok(value) => return Ok(value),
+                Err(err) if err.is::<UnsupportedVersion>() => {
+                    self.leave_untouched(path);
+                    return Err(err);
+                }
+                Err(err) => (err, true),
+            },
+            Err(err) => (err, false),
+        };
+
+        // Pending changes haven't been written, so there's nothing stored yet.
+        if self.writer.get_pending(path).is_some() {
+            return Err(err);
+        }
+
+        if let Ok(value) = self
+            .writer
+            .storage
+            .read(&backup_path(path))
+            .context("Can't read backup")
+            .and_then(|contents| parse(&contents))
+        {
+            println!("Restored {} from its backup", path.display());
+            return Ok(value);
+        }
+
+        if is_corrupt {
+            self.quarantine(path);
+        }
+        Err(err)
+    }
+
+    /// Loads the file at the given path like `Persistence::load()`, or
+    /// returns the default if it can't be loaded.
+    ///
+    /// The player is told with the given message when the file is there but
+    /// can't be loaded, see `Persistence::report_load_error()`. Not having
+    /// the file yet is no problem.
+    pub fn load_or_default<T: Default>(
+        &self,
+        path: &Path,
+        parse: impl Fn(&[u8]) -> Result<T, anyhow::Error>,
+        message: &str,
+    ) -> T {
+        if !self.exists(path) && !self.writer.storage.exists(&backup_path(path)) {
+            return T::default();
+        }
+
+        self.load(path, parse).unwrap_or_else(|err| {
+            self.report_load_error(path, message, err);
+            T::default()
+        })
+    }
+
+    /// Tells the player that the file at the given path couldn't be loaded,
+    /// unless `Persistence::load()` already did so when it set the file aside
+    /// or left it untouched.
+    pub fn report_load_error(&self, path: &Path, message: &str, err: anyhow::Error) {
+        if err.is::<UnsupportedVersion>() || !self.exists(path) {
+            bevy::log::warn!("{message}: {err}");
+        } else {
+            report_error(message, err);
+        }
+    }
+
+    /// Returns whether a file exists at the given path, including changes
+    /// that haven't been written yet.
+    pub fn exists(&self, path: &Path) -> bool {
+        match self.writer.get_pending(path) {
+            Some(Change::Write(_)) => true,
+            Some(Change::Remove) => false,
+            None => self.writer.storage.exists(path),
+        }
+    }
+
+    /// Lists the JSON files in the given directory, with the time they were
+    /// last modified, including changes that haven't been written yet.
+    pub fn list(&self, dir: &Path) -> Vec<(PathBuf, SystemTime)> {
+        let mut files: BTreeMap<PathBuf, SystemTime> = self
+            .writer
+            .storage
+            .list(dir)
+            .into_iter()
+            .filter(|(path, _)| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+
+        let pending = self.writer.lock_pending();
+        for (path, (_, change)) in &pending.changes {
+            if path.parent() != Some(dir) {
+                continue;
+            }
+
+            match change {
+                Change::Write(_) => files.insert(path.clone(), SystemTime::now()),
+                Change::Remove => files.remove(path),
+            };
+        }
+
+        files.into_iter().collect()
+    }
+
+    /// Makes sure the file is never written to, so it isn't lost when an
+    /// older version of the game is played.
+    fn leave_untouched(&self, path: &Path) {
+        let is_new = self
+            .writer
+            .unsupported
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(path.to_owned());
+        if is_new {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            notify(
+                Severity::Warning,
+                format!("{file_name} is from a newer version of the game, and is left as it is"),
+            );
+        }
+    }
+
+    /// Moves the file out of the way, so it can still be recovered by hand.
+    fn quarantine(&self, path: &Path) {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        match self.writer.storage.rename(path, &corrupt_path(path)) {
+            Ok(()) => notify(
+                Severity::Warning,
+                format!("{file_name} couldn't be read, and was set aside"),
+            ),
+            Err(err) => report_error(&format!("Can't set aside {file_name}"), err),
+        }
+    }
+}
+
+/// Serializes the value to JSON, recording the schema version that follows
+/// from the given migrations.
+pub fn to_versioned_json<T: Serialize>(
+    value: &T,
+    migrations: &[Migration],
+) -> Result<Vec<u8>, anyhow::Error> {
+    let mut value = serde_json::to_value(value)?;
+    value
+        .as_object_mut()
+        .context("Only objects can be versioned")?
+        .insert(VERSION_FIELD.to_owned(), Value::from(migrations.len()));
+    serde_json::to_vec(&value).map_err(anyhow::Error::from)
+}
+
+/// Parses the value from JSON, migrating it from the schema version it was
+/// saved with.
+///
+/// Files that don't record their version were saved before versioning was
+/// introduced, and are considered version 0. The migration at index `i`
+/// upgrades a file from version `i` to version `i + 1`.
+pub fn from_versioned_json<T: DeserializeOwned>(
+    bytes: &[u8],
+    migrations: &[Migration],
+) -> Result<T, anyhow::Error> {
+    let mut value: Value = serde_json::from_slice(bytes)?;
+    let version = match value
+        .as_object_mut()
+        .and_then(|object| object.remove(VERSION_FIELD))
+    {
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| usize::try_from(version).ok())
+            .context("Invalid schema version")?,
+        None => 0,
+    };
+    if version > migrations.len() {
+        return Err(UnsupportedVersion(version).into());
+    }
+
+    for migration in &migrations[version..] {
+        value = migration(value)?;
+    }
+    serde_json::from_value(value).map_err(anyhow::Error::from)
+}
+
+/// Migrates a file whose schema didn't change when versioning was introduced.
+pub fn unchanged(value: Value) -> Result<Value, anyhow::Error> {
+    Ok(value)
+}
+
+/// Adds the fields that are missing from the object, for migrations that
+/// introduce new fields.
+pub fn insert_missing_fields(
+    mut value: Value,
+    fields: impl IntoIterator<Item = (&'static str, Value)>,
+) -> Result<Value, anyhow::Error> {
+    let object = value.as_object_mut().context("Expected an object")?;
+    for (key, default) in fields {
+        object.entry(key).or_insert(default);
+    }
+    Ok(value)
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, "bak")
+}
+
+fn corrupt_path(path: &Path) -> PathBuf {
+    with_suffix(path, "corrupt")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Reads a saved file from the `tests/saves` folder, which has one for every
+/// schema version of every file.
+#[cfg(test)]
+pub fn read_fixture(name: &str) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/saves")
+        .join(name);
+    std::fs::read(&path).unwrap_or_else(|err| panic!("Can't read {}: {err}", path.display()))
+}
+
+/// Checks that the JSON matches the fixture with the given name, so that
+/// changing a schema without adding a new version and fixture is caught.
+#[cfg(test)]
+pub fn assert_matches_fixture(json: &[u8], name: &str) {
+    let actual: Value = serde_json::from_slice(json).unwrap();
+    let expected: Value = serde_json::from_slice(&read_fixture(name)).unwrap();
+    assert_eq!(actual, expected, "{name} doesn't match what is saved now");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn stored(persistence: &Persistence, path: &Path) -> Option<Vec<u8>> {
+        persistence.writer.storage.read(path).ok()
+    }
+
+    #[test]
+    fn coalesced_writes_keep_the_last_contents_and_a_backup() {
+        let persistence = Persistence::new(MemoryStorage::default());
+        let path = Path::new("games/1.json");
+
+        persistence.write(path, b"first".to_vec());
+        persistence.flush();
+        persistence.write(path, b"second".to_vec());
+        persistence.write(path, b"third".to_vec());
+        assert_eq!(persistence.read(path).unwrap(), b"third");
+        assert_eq!(persistence.list(Path::new("games")).len(), 1);
+
+        persistence.flush();
+        assert_eq!(stored(&persistence, path).unwrap(), b"third");
+        assert_eq!(stored(&persistence, &backup_path(path)).unwrap(), b"first");
+    }
+
+    fn parse_number(bytes: &[u8]) -> Result<u32, anyhow::Error> {
+        Ok(std::str::from_utf8(bytes)?.parse()?)
+    }
+
+    #[test]
+    fn the_background_thread_stops_once_the_persistence_is_dropped() {
+        let persistence = Persistence::new(MemoryStorage::default());
+        let writer = Arc::downgrade(&persistence.writer);
+        persistence.write("settings.json", b"{}".to_vec());
+
+        drop(persistence.clone());
+        assert!(writer.upgrade().is_some());
+
+        drop(persistence);
+        for _ in 0..100 {
+            if writer.upgrade().is_none() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("The background thread is still running");
+    }
+
+    #[test]
+    fn unparsable_files_are_restored_from_their_backup() {
+        let storage = MemoryStorage::default();
+        let path = Path::new("settings.json");
+        storage.write(path, b"garbage").unwrap();
+        storage.write(&backup_path(path), b"42").unwrap();
+        let persistence = Persistence::new(storage);
+        assert_eq!(persistence.load(path, parse_number).unwrap(), 42);
+
+        persistence.remove(path);
+        assert!(!persistence.exists(path));
+        assert!(persistence.list(Path::new("")).is_empty());
+        persistence.flush();
+        assert_eq!(stored(&persistence, path), None);
+        assert_eq!(stored(&persistence, &backup_path(path)), None);
+    }
+
+    #[test]
+    fn unparsable_files_are_set_aside() {
+        let storage = MemoryStorage::default();
+        let path = Path::new("highscores.json");
+        storage.write(path, b"garbage").unwrap();
+        let persistence = Persistence::new(storage);
+        assert!(persistence.load(path, parse_number).is_err());
+        assert_eq!(stored(&persistence, path), None);
+        assert_eq!(
+            stored(&persistence, &corrupt_path(path)).unwrap(),
+            b"garbage"
+        );
+
+        // Files that are merely missing are left alone.
+        let missing_path = Path::new("statistics.json");
+        assert!(persistence.load(missing_path, parse_number).is_err());
+        assert_eq!(stored(&persistence, &corrupt_path(missing_path)), None);
+    }
+
+    #[test]
+    fn files_from_newer_versions_are_left_untouched() {
+        let storage = MemoryStorage::default();
+        let path = Path::new("settings.json");
+        let newer = br#"{"version":2,"a":1}"#;
+        storage.write(path, newer).unwrap();
+        storage.write(&backup_path(path), br#"{"a":1}"#).unwrap();
+        let persistence = Persistence::new(storage);
+
+        let parse = |bytes: &[u8]| -> Result<Value, anyhow::Error> {
+            from_versioned_json(bytes, &[unchanged])
+        };
+        let err = persistence.load(path, parse).unwrap_err();
+        assert!(err.is::<UnsupportedVersion>());
+        assert_eq!(stored(&persistence, &corrupt_path(path)), None);
+
+        persistence.write(path, br#"{"version":1}"#.to_vec());
+        persistence.remove(path);
+        persistence.flush();
+        assert!(persistence.write_now(path, b"{}").is_err());
+        assert_eq!(stored(&persistence, path).unwrap(), newer);
+    }
+
+    fn add_b(value: Value) -> Result<Value, anyhow::Error> {
+        insert_missing_fields(value, [("b", Value::from(2))])
+    }
+
+    fn rename_b_to_c(mut value: Value) -> Result<Value, anyhow::Error> {
+        let object = value.as_object_mut().context("Expected an object")?;
+        let b = object.remove("b").context("Missing b")?;
+        object.insert("c".to_owned(), b);
+        Ok(value)
+    }
+
+    #[test]
+    fn files_are_migrated_from_the_version_they_were_saved_with() {
+        type Numbers = BTreeMap<String, u32>;
+        let migrations: &[Migration] = &[add_b, rename_b_to_c];
+        let expected = |c| Numbers::from([("a".to_owned(), 1), ("c".to_owned(), c)]);
+
+        let parse = |json: &str| from_versioned_json::<Numbers>(json.as_bytes(), migrations);
+        assert_eq!(parse(r#"{"a":1}"#).unwrap(), expected(2));
+        assert_eq!(parse(r#"{"version":1,"a":1,"b":3}"#).unwrap(), expected(3));
+        assert_eq!(parse(r#"{"version":2,"a":1,"c":4}"#).unwrap(), expected(4));
+        assert!(parse(r#"{"version":3,"a":1,"c":4}"#).is_err());
+        assert!(parse(r#"{"version":-1,"a":1,"c":4}"#).is_err());
+
+        let json = to_versioned_json(&expected(5), migrations).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&json).unwrap()["version"],
+            2
+        );
+        assert_eq!(
+            from_versioned_json::<Numbers>(&json, migrations).unwrap(),
+            expected(5)
+        );
+    }
+}
@@ -1,5 +1,5 @@
-use crate::ScreenSizing;
 use crate::assets::{Fonts, Images};
+use crate::ScreenSizing;
 use bevy::prelude::*;
 
 pub type ResourceTuple<'w> = (Res<'w, Fonts>, Res<'w, Images>, Res<'w, ScreenSizing>);
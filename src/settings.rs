@@ -1,8 +1,58 @@
-use crate::utils::ensure_sudoku_dir;
+use crate::persistence::{self, Migration, Persistence};
+use crate::ui::report_error;
 use anyhow::Context;
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use serde_json::Value;
+use std::path::Path;
+
+/// The migrations for settings, see `persistence::from_versioned_json()`.
+///
+/// Settings from before versioning only lack fields that have defaults of their
+/// own.
+const SETTINGS_MIGRATIONS: &[Migration] = &[persistence::unchanged, migrate_mistake_toggles];
+
+/// Replaces the separate toggles for revealing mistakes and highlighting
+/// conflicts with a single choice.
+///
+/// Revealing mistakes wins if both were enabled, since it shows more.
+fn migrate_mistake_toggles(mut value: Value) -> Result<Value, anyhow::Error> {
+    let object = value.as_object_mut().context("Expected an object")?;
+    let show_mistakes = object
+        .remove("show_mistakes")
+        .and_then(|value| value.as_bool())
+        .unwrap_or(true);
+    let show_conflicts = object
+        .remove("show_conflicts")
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false);
+
+    let mistake_highlighting = if show_mistakes {
+        MistakeHighlighting::AgainstSolution
+    } else if show_conflicts {
+        MistakeHighlighting::Conflicts
+    } else {
+        MistakeHighlighting::Off
+    };
+    object.insert(
+        "mistake_highlighting".to_owned(),
+        serde_json::to_value(mistake_highlighting)?,
+    );
+    Ok(value)
+}
+
+/// How mistakes are highlighted on the board.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MistakeHighlighting {
+    Off,
+    /// Highlights numbers that conflict with another number in the same row,
+    /// column or block.
+    Conflicts,
+    /// Reveals every number that doesn't match the solution.
+    #[default]
+    AgainstSolution,
+}
 
 #[derive(Deserialize, Resource, Serialize)]
 pub struct Settings {
@@ -15,8 +65,11 @@ pub struct Settings {
     #[serde(default = "default_selected_cell_highlight")]
     pub selected_cell_highlight: bool,
 
-    #[serde(default = "default_show_mistakes")]
-    pub show_mistakes: bool,
+    #[serde(default)]
+    pub mistake_highlighting: MistakeHighlighting,
+
+    #[serde(default = "default_highlight_checked_cells")]
+    pub highlight_checked_cells: bool,
 
     #[serde(default)]
     pub onboarding_finished: bool,
@@ -34,7 +87,7 @@ fn default_selected_cell_highlight() -> bool {
     true
 }
 
-fn default_show_mistakes() -> bool {
+fn default_highlight_checked_cells() -> bool {
     true
 }
 
@@ -44,42 +97,95 @@ impl Default for Settings {
             autofill_correct_notes: default_autofill_correct_notes(),
             enable_wheel_aid: default_enable_wheel_aid(),
             selected_cell_highlight: default_selected_cell_highlight(),
-            show_mistakes: default_show_mistakes(),
+            mistake_highlighting: MistakeHighlighting::default(),
+            highlight_checked_cells: default_highlight_checked_cells(),
             onboarding_finished: false,
         }
     }
 }
 
 impl Settings {
+    /// Whether numbers that don't match the solution are revealed.
+    pub fn reveals_mistakes(&self) -> bool {
+        self.mistake_highlighting == MistakeHighlighting::AgainstSolution
+    }
+
+    /// Whether numbers that conflict with each other are highlighted.
+    pub fn highlights_conflicts(&self) -> bool {
+        self.mistake_highlighting == MistakeHighlighting::Conflicts
+    }
+
     /// Loads settings from disk, or returns `Self::default()` if no
     /// settings could be loaded.
-    pub fn load() -> Self {
-        fs::read(ensure_sudoku_dir().join("settings.json"))
-            .context("Can't read file")
-            .and_then(|json| Self::from_json(&json))
-            .map_err(|err| println!("Can't load settings: {err}"))
-            .unwrap_or_default()
+    pub fn load(persistence: &Persistence) -> Self {
+        persistence.load_or_default(
+            Path::new("settings.json"),
+            Self::from_json,
+            "Can't load settings",
+        )
     }
 
     /// Saves settings to disk.
-    ///
-    /// This is called automatically on drop.
-    pub fn save(&self) {
+    pub fn save(&self, persistence: &Persistence) {
         self.to_json()
-            .and_then(|json| {
-                fs::write(ensure_sudoku_dir().join("settings.json"), json)
-                    .map_err(anyhow::Error::from)
-            })
-            .unwrap_or_else(|err| println!("Can't save settings: {err}"));
+            .map(|json| persistence.write("settings.json", json))
+            .unwrap_or_else(|err| report_error("Can't save settings", err));
     }
 
     /// Serializes the settings to JSON.
-    fn to_json(&self) -> Result<Vec<u8>, anyhow::Error> {
-        serde_json::to_vec(self).map_err(anyhow::Error::from)
+    pub fn to_json(&self) -> Result<Vec<u8>, anyhow::Error> {
+        persistence::to_versioned_json(self, SETTINGS_MIGRATIONS)
     }
 
     /// Parses settings from JSON.
-    fn from_json(bytes: &[u8]) -> Result<Self, anyhow::Error> {
-        serde_json::from_slice(bytes).map_err(anyhow::Error::from)
+    pub fn from_json(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        persistence::from_versioned_json(bytes, SETTINGS_MIGRATIONS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{assert_matches_fixture, read_fixture};
+
+    #[test]
+    fn settings_from_every_schema_version_are_loaded() {
+        let original = Settings::from_json(&read_fixture("settings-v0-original.json")).unwrap();
+        assert!(!original.autofill_correct_notes);
+        assert_eq!(original.mistake_highlighting, MistakeHighlighting::Off);
+        assert_eq!(
+            original.highlight_checked_cells,
+            default_highlight_checked_cells()
+        );
+
+        for name in ["settings-v0.json", "settings-v1.json", "settings-v2.json"] {
+            let settings = Settings::from_json(&read_fixture(name)).unwrap();
+            assert!(!settings.autofill_correct_notes);
+            assert_eq!(
+                settings.mistake_highlighting,
+                MistakeHighlighting::Conflicts
+            );
+            assert!(!settings.highlight_checked_cells);
+            assert!(settings.onboarding_finished);
+        }
+
+        let current = Settings::from_json(&read_fixture("settings-v2.json")).unwrap();
+        assert_matches_fixture(&current.to_json().unwrap(), "settings-v2.json");
+    }
+
+    #[test]
+    fn revealing_mistakes_wins_over_highlighting_conflicts() {
+        let both = br#"{"show_mistakes":true,"show_conflicts":true,"version":1}"#;
+        let settings = Settings::from_json(both).unwrap();
+        assert_eq!(
+            settings.mistake_highlighting,
+            MistakeHighlighting::AgainstSolution
+        );
+
+        let defaults = Settings::from_json(br#"{"version":1}"#).unwrap();
+        assert_eq!(
+            defaults.mistake_highlighting,
+            MistakeHighlighting::AgainstSolution
+        );
     }
 }
@@ -0,0 +1,412 @@
+use crate::persistence::{self, Migration, Persistence};
+use crate::sudoku::{Difficulty, Game};
+use crate::{ui::report_error, utils::*};
+use anyhow::Context;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The migrations for statistics, see `persistence::from_versioned_json()`.
+const STATISTICS_MIGRATIONS: &[Migration] = &[persistence::unchanged];
+
+/// Lifetime statistics, kept as a log of every game that was ever started.
+#[derive(Default, Deserialize, Resource, Serialize)]
+pub struct Statistics {
+    pub games: Vec<GameRecord>,
+}
+
+/// A single game within the statistics.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct GameRecord {
+    /// When the game was started, in seconds since the Unix epoch.
+    pub started_at: u64,
+    pub difficulty: Difficulty,
+    /// The canonical hash of the puzzle, see `Sudoku::canonical_hash()`.
+    pub puzzle_id: u64,
+    pub outcome: GameOutcome,
+    pub elapsed_secs: f32,
+    pub num_mistakes: u32,
+    pub num_mistakes_by_number: [u32; 9],
+    pub num_hints: u32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameOutcome {
+    InProgress,
+    Solved,
+    GaveUp,
+    /// The game was deleted before it was finished, or replaced by another
+    /// one back when there was only a single save slot.
+    Abandoned,
+}
+
+/// The statistics of all the games of a single difficulty.
+///
+/// Games that are still in progress are left out.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct DifficultyStatistics {
+    pub num_played: u32,
+    pub num_solved: u32,
+    /// The best and average time of the solved games.
+    pub best_time: Option<f32>,
+    pub average_time: Option<f32>,
+    pub average_mistakes: Option<f32>,
+    pub average_hints: Option<f32>,
+    /// The number of games solved in a row, up to the last game played.
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    pub num_mistakes_by_number: [u32; 9],
+}
+
+impl DifficultyStatistics {
+    /// Returns the share of played games that were solved, between 0 and 1.
+    pub fn win_rate(&self) -> Option<f32> {
+        (self.num_played > 0).then(|| self.num_solved as f32 / self.num_played as f32)
+    }
+}
+
+impl GameRecord {
+    fn new(game: &Game, outcome: GameOutcome) -> Self {
+        Self {
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            difficulty: game.difficulty,
+            puzzle_id: game.puzzle_id(),
+            outcome,
+            elapsed_secs: game.elapsed_secs,
+            num_mistakes: game.num_mistakes,
+            num_mistakes_by_number: game.num_mistakes_by_number,
+            num_hints: game.num_hints + game.num_checks,
+        }
+    }
+
+    /// Updates the record with the progress made in the given game.
+    fn update(&mut self, game: &Game, outcome: GameOutcome) {
+        self.outcome = outcome;
+        self.elapsed_secs = game.elapsed_secs;
+        self.num_mistakes = game.num_mistakes;
+        self.num_mistakes_by_number = game.num_mistakes_by_number;
+        self.num_hints = game.num_hints + game.num_checks;
+    }
+}
+
+impl Statistics {
+    /// Records that the given game was started.
+    pub fn record_start(&mut self, persistence: &Persistence, game: &Game) {
+        self.start(game);
+        self.save(persistence);
+    }
+
+    /// Records that the given game ended with the given outcome.
+    pub fn record_end(&mut self, persistence: &Persistence, game: &Game, outcome: GameOutcome) {
+        self.end(game, outcome);
+        self.save(persistence);
+    }
+
+    /// Adds the games of the other statistics that aren't in these yet,
+    /// without saving.
+    ///
+    /// Of games that are in both, the one that was seen to its end is kept.
+    pub fn merge(&mut self, other: Statistics) {
+        for record in other.games {
+            match self.games.iter_mut().find(|known| {
+                known.started_at == record.started_at && known.puzzle_id == record.puzzle_id
+            }) {
+                Some(known) if known.outcome == GameOutcome::InProgress => *known = record,
+                Some(_) => {}
+                None => self.games.push(record),
+            }
+        }
+        self.games.sort_by_key(|record| record.started_at);
+    }
+
+    fn start(&mut self, game: &Game) {
+        self.games
+            .push(GameRecord::new(game, GameOutcome::InProgress));
+    }
+
+    fn end(&mut self, game: &Game, outcome: GameOutcome) {
+        let puzzle_id = game.puzzle_id();
+        match self.games.iter_mut().rev().find(|record| {
+            record.outcome == GameOutcome::InProgress && record.puzzle_id == puzzle_id
+        }) {
+            Some(record) => record.update(game, outcome),
+            // The game was started before statistics were kept.
+            None => self.games.push(GameRecord::new(game, outcome)),
+        }
+    }
+
+    /// Summarizes the games of the given difficulty.
+    pub fn summarize(&self, difficulty: Difficulty) -> DifficultyStatistics {
+        let mut stats = DifficultyStatistics::default();
+        let mut total_time = 0.;
+        let mut total_mistakes = 0;
+        let mut total_hints = 0;
+
+        let played = self.games.iter().filter(|record| {
+            record.difficulty == difficulty && record.outcome != GameOutcome::InProgress
+        });
+        for record in played {
+            stats.num_played += 1;
+            total_mistakes += record.num_mistakes;
+            total_hints += record.num_hints;
+            for (total, num_mistakes) in stats
+                .num_mistakes_by_number
+                .iter_mut()
+                .zip(record.num_mistakes_by_number)
+            {
+                *total += num_mistakes;
+            }
+
+            if record.outcome == GameOutcome::Solved {
+                stats.num_solved += 1;
+                total_time += record.elapsed_secs;
+                stats.best_time = Some(
+                    stats
+                        .best_time
+                        .map_or(record.elapsed_secs, |time| time.min(record.elapsed_secs)),
+                );
+                stats.current_streak += 1;
+                stats.longest_streak = stats.longest_streak.max(stats.current_streak);
+            } else {
+                stats.current_streak = 0;
+            }
+        }
+
+        if stats.num_played > 0 {
+            stats.average_mistakes = Some(total_mistakes as f32 / stats.num_played as f32);
+            stats.average_hints = Some(total_hints as f32 / stats.num_played as f32);
+        }
+        if stats.num_solved > 0 {
+            stats.average_time = Some(total_time / stats.num_solved as f32);
+        }
+
+        stats
+    }
+
+    /// Loads the statistics from disk, or returns `Self::default()` if no
+    /// statistics could be loaded.
+    pub fn load(persistence: &Persistence) -> Self {
+        persistence.load_or_default(
+            Path::new("statistics.json"),
+            Self::from_json,
+            "Can't load statistics",
+        )
+    }
+
+    /// Saves the statistics to disk.
+    pub fn save(&self, persistence: &Persistence) {
+        self.to_json()
+            .map(|json| persistence.write("statistics.json", json))
+            .unwrap_or_else(|err| report_error("Can't save statistics", err));
+    }
+
+    /// Exports the statistics as CSV, with a line for every game, and as
+    /// JSON, which also includes the summaries for every difficulty.
+    ///
+    /// Returns where the files were written to.
+    pub fn export(&self, persistence: &Persistence) -> Result<String, anyhow::Error> {
+        persistence
+            .write_now(Path::new("statistics-export.csv"), self.to_csv().as_bytes())
+            .context("Can't write CSV file")?;
+        persistence
+            .write_now(Path::new("statistics-export.json"), &self.to_export_json()?)
+            .context("Can't write JSON file")?;
+        Ok(persistence.location())
+    }
+
+    /// Serializes the statistics to JSON.
+    pub fn to_json(&self) -> Result<Vec<u8>, anyhow::Error> {
+        persistence::to_versioned_json(self, STATISTICS_MIGRATIONS)
+    }
+
+    /// Parses the statistics from JSON.
+    pub fn from_json(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        persistence::from_versioned_json(bytes, STATISTICS_MIGRATIONS)
+    }
+
+    /// Serializes the games to CSV.
+    fn to_csv(&self) -> String {
+        let mut csv = "started_at,difficulty,puzzle_id,outcome,elapsed_secs,num_mistakes,\
+            num_hints,mistakes_1,mistakes_2,mistakes_3,mistakes_4,mistakes_5,mistakes_6,\
+            mistakes_7,mistakes_8,mistakes_9\n"
+            .to_owned();
+        for record in &self.games {
+            let outcome = match record.outcome {
+                GameOutcome::InProgress => "in_progress",
+                GameOutcome::Solved => "solved",
+                GameOutcome::GaveUp => "gave_up",
+                GameOutcome::Abandoned => "abandoned",
+            };
+            let _ = write!(
+                csv,
+                "{},{},{:016x},{outcome},{},{},{}",
+                record.started_at,
+                format_difficulty(record.difficulty),
+                record.puzzle_id,
+                record.elapsed_secs,
+                record.num_mistakes,
+                record.num_hints,
+            );
+            for num_mistakes in record.num_mistakes_by_number {
+                let _ = write!(csv, ",{num_mistakes}");
+            }
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    /// Serializes the games, along with the summaries for every difficulty,
+    /// to human-readable JSON.
+    fn to_export_json(&self) -> Result<Vec<u8>, anyhow::Error> {
+        #[derive(Serialize)]
+        struct Export<'a> {
+            difficulties: BTreeMap<Difficulty, DifficultyStatistics>,
+            games: &'a [GameRecord],
+        }
+
+        use Difficulty::*;
+        let difficulties = [Trivial, Easy, Medium, Advanced, Expert]
+            .into_iter()
+            .map(|difficulty| (difficulty, self.summarize(difficulty)))
+            .collect();
+
+        serde_json::to_vec_pretty(&Export {
+            difficulties,
+            games: &self.games,
+        })
+        .map_err(anyhow::Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{assert_matches_fixture, read_fixture};
+    use crate::sudoku::strategies::arb_difficulty;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    fn arb_outcome() -> impl Strategy<Value = GameOutcome> {
+        prop_oneof![
+            Just(GameOutcome::Solved),
+            Just(GameOutcome::GaveUp),
+            Just(GameOutcome::Abandoned),
+        ]
+    }
+
+    fn arb_record() -> impl Strategy<Value = GameRecord> {
+        (
+            arb_difficulty(),
+            arb_outcome(),
+            0..1_000_000u32,
+            0..1000u32,
+            any::<[u8; 9]>(),
+        )
+            .prop_map(
+                |(difficulty, outcome, elapsed_quarter_secs, num_hints, mistakes)| GameRecord {
+                    started_at: 0,
+                    difficulty,
+                    puzzle_id: 0,
+                    outcome,
+                    elapsed_secs: elapsed_quarter_secs as f32 / 4.,
+                    num_mistakes: mistakes.iter().map(|&n| n as u32).sum(),
+                    num_mistakes_by_number: mistakes.map(u32::from),
+                    num_hints,
+                },
+            )
+    }
+
+    fn average(values: impl Iterator<Item = f64>) -> Option<f64> {
+        let values: Vec<_> = values.collect();
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    }
+
+    /// Compares an average against one that was calculated with more
+    /// precision.
+    fn approx_eq(actual: Option<f32>, expected: Option<f64>) -> bool {
+        match (actual, expected) {
+            (Some(actual), Some(expected)) => {
+                (actual as f64 - expected).abs() <= expected.abs().max(1.) * 1e-4
+            }
+            (actual, expected) => actual.is_none() && expected.is_none(),
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn summaries_only_count_their_difficulty(games in vec(arb_record(), 0..50), difficulty in arb_difficulty()) {
+            let statistics = Statistics { games: games.clone() };
+            let stats = statistics.summarize(difficulty);
+
+            let games: Vec<_> = games.iter().filter(|game| game.difficulty == difficulty).collect();
+            let solved: Vec<_> = games.iter().filter(|game| game.outcome == GameOutcome::Solved).collect();
+            prop_assert_eq!(stats.num_played as usize, games.len());
+            prop_assert_eq!(stats.num_solved as usize, solved.len());
+
+            let win_rate = (!games.is_empty()).then(|| solved.len() as f32 / games.len() as f32);
+            prop_assert_eq!(stats.win_rate(), win_rate);
+
+            let best_time = solved.iter().map(|game| game.elapsed_secs).min_by(f32::total_cmp);
+            prop_assert_eq!(stats.best_time, best_time);
+
+            let average_time = average(solved.iter().map(|game| game.elapsed_secs as f64));
+            let average_mistakes = average(games.iter().map(|game| game.num_mistakes as f64));
+            let average_hints = average(games.iter().map(|game| game.num_hints as f64));
+            prop_assert!(approx_eq(stats.average_time, average_time), "{:?} != {:?}", stats.average_time, average_time);
+            prop_assert!(approx_eq(stats.average_mistakes, average_mistakes), "{:?} != {:?}", stats.average_mistakes, average_mistakes);
+            prop_assert!(approx_eq(stats.average_hints, average_hints), "{:?} != {:?}", stats.average_hints, average_hints);
+
+            for (i, num_mistakes) in stats.num_mistakes_by_number.into_iter().enumerate() {
+                prop_assert_eq!(num_mistakes, games.iter().map(|game| game.num_mistakes_by_number[i]).sum::<u32>());
+            }
+        }
+
+        #[test]
+        fn streaks_count_consecutive_solves(outcomes in vec(arb_outcome(), 0..50)) {
+            let games = outcomes.iter().map(|&outcome| GameRecord {
+                started_at: 0,
+                difficulty: Difficulty::Easy,
+                puzzle_id: 0,
+                outcome,
+                elapsed_secs: 0.,
+                num_mistakes: 0,
+                num_mistakes_by_number: [0; 9],
+                num_hints: 0,
+            }).collect();
+            let stats = Statistics { games }.summarize(Difficulty::Easy);
+
+            let streaks: Vec<_> = outcomes.split(|&outcome| outcome != GameOutcome::Solved).map(|streak| streak.len() as u32).collect();
+            prop_assert_eq!(stats.current_streak, streaks.last().copied().unwrap_or_default());
+            prop_assert_eq!(stats.longest_streak, streaks.iter().copied().max().unwrap_or_default());
+        }
+
+        #[test]
+        fn statistics_survive_a_round_trip(games in vec(arb_record(), 0..30)) {
+            let statistics = Statistics { games };
+            let restored = Statistics::from_json(&statistics.to_json().unwrap()).unwrap();
+            prop_assert_eq!(&restored.games, &statistics.games);
+
+            let csv = statistics.to_csv();
+            prop_assert_eq!(csv.lines().count(), statistics.games.len() + 1);
+        }
+    }
+
+    #[test]
+    fn statistics_from_every_schema_version_are_loaded() {
+        let unversioned = Statistics::from_json(&read_fixture("statistics-v0.json")).unwrap();
+        let current = Statistics::from_json(&read_fixture("statistics-v1.json")).unwrap();
+        assert_eq!(unversioned.games, current.games);
+        assert_eq!(current.games.len(), 2);
+        assert_eq!(current.games[1].outcome, GameOutcome::Abandoned);
+
+        assert_matches_fixture(&current.to_json().unwrap(), "statistics-v1.json");
+    }
+}
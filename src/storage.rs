@@ -0,0 +1,332 @@
+//! The places where the player's data can be kept.
+//!
+//! Files are addressed by paths relative to the root of the storage, so that
+//! the same data can be kept on disk, or in memory for tests.
+
+use crate::ui::report_error;
+use anyhow::{anyhow, Context};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// The command line flag that overrides where the player's data is kept,
+/// used as either `--data-dir <dir>` or `--data-dir=<dir>`.
+const DATA_DIR_FLAG: &str = "--data-dir";
+
+/// The environment variable that overrides where the player's data is kept.
+const DATA_DIR_VAR: &str = "SUDOKU_DATA_DIR";
+
+/// A place to keep files in.
+pub trait Storage: Send + Sync + 'static {
+    /// Describes where the files are kept, for the player's information.
+    fn location(&self) -> String;
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>, anyhow::Error>;
+
+    /// Replaces the file at the given path without ever leaving it
+    /// half-written, creating its directory if necessary.
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), anyhow::Error>;
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), anyhow::Error>;
+
+    /// Removes the file at the given path, if there is one.
+    fn remove(&self, path: &Path) -> Result<(), anyhow::Error>;
+
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Lists the files in the given directory, with the time they were last
+    /// modified.
+    fn list(&self, dir: &Path) -> Vec<(PathBuf, SystemTime)>;
+}
+
+/// Keeps files in a directory on disk.
+pub struct FileStorage {
+    root: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        self.root.join(path)
+    }
+}
+
+impl Storage for FileStorage {
+    fn location(&self) -> String {
+        self.root.display().to_string()
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>, anyhow::Error> {
+        fs::read(self.resolve(path)).context("Can't read file")
+    }
+
+    /// Writes to a temporary file first, and renames it over the original
+    /// once it's complete.
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), anyhow::Error> {
+        let path = self.resolve(path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context("Can't create directory")?;
+        }
+
+        let mut temp_name = OsString::from(path.as_os_str());
+        temp_name.push(".tmp");
+        let temp_path = PathBuf::from(temp_name);
+        let mut file = File::create(&temp_path).context("Can't create file")?;
+        file.write_all(contents).context("Can't write to file")?;
+        file.sync_all().context("Can't sync file")?;
+        fs::rename(&temp_path, &path).context("Can't replace file")
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), anyhow::Error> {
+        fs::rename(self.resolve(from), self.resolve(to)).context("Can't rename file")
+    }
+
+    fn remove(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let path = self.resolve(path);
+        if path.exists() {
+            fs::remove_file(path).context("Can't remove file")?;
+        }
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.resolve(path).exists()
+    }
+
+    fn list(&self, dir: &Path) -> Vec<(PathBuf, SystemTime)> {
+        let Ok(entries) = fs::read_dir(self.resolve(dir)) else {
+            return Vec::new();
+        };
+
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+            .map(|entry| {
+                let modified = entry
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                (dir.join(entry.file_name()), modified)
+            })
+            .collect()
+    }
+}
+
+/// Keeps files in memory, so tests don't touch the disk.
+#[derive(Default)]
+pub struct MemoryStorage {
+    files: Mutex<BTreeMap<PathBuf, (Vec<u8>, SystemTime)>>,
+}
+
+impl MemoryStorage {
+    fn with_files<T>(
+        &self,
+        f: impl FnOnce(&mut BTreeMap<PathBuf, (Vec<u8>, SystemTime)>) -> T,
+    ) -> T {
+        f(&mut self.files.lock().unwrap_or_else(|err| err.into_inner()))
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn location(&self) -> String {
+        "memory".to_owned()
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>, anyhow::Error> {
+        self.with_files(|files| files.get(path).map(|(contents, _)| contents.clone()))
+            .ok_or_else(|| anyhow!("File not found"))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<(), anyhow::Error> {
+        self.with_files(|files| {
+            files.insert(path.to_owned(), (contents.to_vec(), SystemTime::now()));
+        });
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), anyhow::Error> {
+        self.with_files(|files| {
+            let file = files
+                .remove(from)
+                .ok_or_else(|| anyhow!("File not found"))?;
+            files.insert(to.to_owned(), file);
+            Ok(())
+        })
+    }
+
+    fn remove(&self, path: &Path) -> Result<(), anyhow::Error> {
+        self.with_files(|files| files.remove(path));
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.with_files(|files| files.contains_key(path))
+    }
+
+    fn list(&self, dir: &Path) -> Vec<(PathBuf, SystemTime)> {
+        self.with_files(|files| {
+            files
+                .iter()
+                .filter(|(path, _)| path.parent() == Some(dir))
+                .map(|(path, (_, modified))| (path.clone(), *modified))
+                .collect()
+        })
+    }
+}
+
+/// Returns the directory the player's data is kept in.
+///
+/// The directory can be chosen with the `--data-dir` flag or the
+/// `SUDOKU_DATA_DIR` environment variable. Otherwise, Linux follows the XDG
+/// base directories, unless the player already has data in `~/.sudoku`.
+///
+/// Falls back to the home directory if the directory can't be created.
+pub fn data_dir() -> PathBuf {
+    #[allow(deprecated)]
+    let home_dir = std::env::home_dir();
+    let dir = resolve_data_dir(
+        std::env::args_os().skip(1),
+        std::env::var_os(DATA_DIR_VAR),
+        std::env::var_os("XDG_DATA_HOME"),
+        home_dir.clone(),
+    );
+    if dir.exists() {
+        return dir;
+    }
+
+    match fs::create_dir_all(&dir) {
+        Ok(()) => dir,
+        Err(err) => {
+            let home_dir = home_dir.unwrap_or(PathBuf::from("/tmp"));
+            report_error(
+                &format!(
+                    "Can't create {}, keeping data in {}",
+                    dir.display(),
+                    home_dir.display()
+                ),
+                err,
+            );
+            home_dir
+        }
+    }
+}
+
+fn resolve_data_dir(
+    args: impl IntoIterator<Item = OsString>,
+    data_dir_var: Option<OsString>,
+    xdg_data_home: Option<OsString>,
+    home_dir: Option<PathBuf>,
+) -> PathBuf {
+    if let Some(dir) = find_flag_value(args, DATA_DIR_FLAG) {
+        return PathBuf::from(dir);
+    }
+    if let Some(dir) = data_dir_var.filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+
+    let home_dir = home_dir.unwrap_or(PathBuf::from("/tmp"));
+    if cfg!(target_os = "ios") {
+        return home_dir.join("Library/Application support");
+    }
+
+    let legacy_dir = home_dir.join(".sudoku");
+    if cfg!(target_os = "linux") && !legacy_dir.exists() {
+        // Relative paths in XDG variables are invalid, and must be ignored.
+        let data_home = xdg_data_home
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .unwrap_or_else(|| home_dir.join(".local/share"));
+        return data_home.join("sudoku");
+    }
+
+    legacy_dir
+}
+
+/// Returns the value given for the flag, as either `--flag value` or
+/// `--flag=value`.
+pub fn find_flag_value(args: impl IntoIterator<Item = OsString>, flag: &str) -> Option<OsString> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+
+        if let Some(value) = arg
+            .to_str()
+            .and_then(|arg| arg.strip_prefix(flag))
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(OsString::from(value));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn the_data_dir_can_be_chosen() {
+        let home = Some(PathBuf::from("/nonexistent/home"));
+
+        let dir = resolve_data_dir(
+            args(&["--data-dir", "/flag"]),
+            Some("/var".into()),
+            None,
+            home.clone(),
+        );
+        assert_eq!(dir, PathBuf::from("/flag"));
+
+        let dir = resolve_data_dir(args(&["--data-dir=/flag"]), None, None, home.clone());
+        assert_eq!(dir, PathBuf::from("/flag"));
+
+        let dir = resolve_data_dir(args(&[]), Some("/var".into()), None, home.clone());
+        assert_eq!(dir, PathBuf::from("/var"));
+
+        if cfg!(target_os = "linux") {
+            let dir = resolve_data_dir(args(&[]), None, Some("/xdg".into()), home.clone());
+            assert_eq!(dir, PathBuf::from("/xdg/sudoku"));
+
+            let dir = resolve_data_dir(args(&[]), None, Some("relative".into()), home.clone());
+            assert_eq!(dir, PathBuf::from("/nonexistent/home/.local/share/sudoku"));
+        }
+    }
+
+    #[test]
+    fn file_storage_replaces_files_without_leaving_temporary_ones() {
+        let root = std::env::temp_dir().join("sudoku-storage-test");
+        let _ = fs::remove_dir_all(&root);
+        let storage = FileStorage::new(root.clone());
+        let path = Path::new("games/1.json");
+
+        storage.write(path, b"first").unwrap();
+        storage.write(path, b"second").unwrap();
+        assert_eq!(storage.read(path).unwrap(), b"second");
+
+        let listed: Vec<_> = storage
+            .list(Path::new("games"))
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(listed, vec![path.to_owned()]);
+
+        storage
+            .rename(path, Path::new("games/1.json.corrupt"))
+            .unwrap();
+        assert!(!storage.exists(path));
+        storage.remove(Path::new("games/1.json.corrupt")).unwrap();
+        assert!(storage.list(Path::new("games")).is_empty());
+    }
+}
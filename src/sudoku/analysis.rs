@@ -0,0 +1,187 @@
+use super::solver::{solve, SolveStep};
+use super::{get_pos, Game, MoveAction, Replay, Technique};
+
+/// How the player got to the number in a single cell.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CellAnalysis {
+    /// Whether the number was given at the start.
+    pub is_given: bool,
+
+    /// The time spent on the cell, counted from the moment the previous cell
+    /// was filled in, in seconds.
+    pub secs_spent: f32,
+
+    /// The number of times a wrong number was placed in the cell.
+    pub num_mistakes: u32,
+
+    /// The hardest technique the solver needed before it could fill in the
+    /// cell, or `None` if it had to guess.
+    pub technique: Option<Technique>,
+}
+
+/// The time spent and the mistakes made on all the cells that needed the same
+/// technique.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TechniqueAnalysis {
+    /// The technique, or `None` for the cells the solver had to guess.
+    pub technique: Option<Technique>,
+    pub num_cells: usize,
+    pub secs_spent: f32,
+    pub num_mistakes: u32,
+}
+
+/// Lines up the moves of a finished game with the path the solver takes
+/// through the puzzle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    /// The analysis of every cell, indexed by position.
+    pub cells: Vec<CellAnalysis>,
+}
+
+impl Analysis {
+    pub fn new(replay: &Replay) -> Self {
+        let mut cells = vec![CellAnalysis::default(); 81];
+
+        let steps = solve(replay.start.clone())
+            .map(|result| result.steps)
+            .unwrap_or_default();
+        for SolveStep {
+            x, y, technique, ..
+        } in steps
+        {
+            cells[get_pos(x, y)].technique = technique;
+        }
+
+        for (pos, cell) in cells.iter_mut().enumerate() {
+            cell.is_given = replay.start.get_by_pos(pos).is_some();
+        }
+
+        let is_correct = |game: &Game, pos: usize| {
+            game.current.get_by_pos(pos) == replay.solution.get_by_pos(pos)
+        };
+
+        let mut game = replay.game_at(0);
+
+        let mut last_filled_secs = 0.;
+        for (entry, secs) in replay.moves.iter().zip(replay.timeline()) {
+            if let MoveAction::Place { x, y, n, .. } = entry.action {
+                if replay.solution.get(x, y) != Some(n) {
+                    cells[get_pos(x, y)].num_mistakes += 1;
+                }
+            }
+
+            let was_correct: Vec<bool> = (0..81).map(|pos| is_correct(&game, pos)).collect();
+            game.apply_move(&entry.action);
+
+            let filled: Vec<usize> = (0..81)
+                .filter(|&pos| !was_correct[pos] && is_correct(&game, pos))
+                .collect();
+            if !filled.is_empty() {
+                // Cells filled in by a single move share the time it took.
+                let secs_spent = (secs - last_filled_secs) / filled.len() as f32;
+                for pos in filled {
+                    cells[pos].secs_spent += secs_spent;
+                }
+                last_filled_secs = secs;
+            }
+        }
+
+        Self { cells }
+    }
+
+    /// Returns the positions of the cells the player spent the longest on,
+    /// longest first.
+    pub fn longest_stalls(&self, limit: usize) -> Vec<usize> {
+        let mut positions: Vec<usize> = (0..81)
+            .filter(|&pos| self.cells[pos].secs_spent > 0.)
+            .collect();
+        positions.sort_by(|&a, &b| {
+            self.cells[b]
+                .secs_spent
+                .total_cmp(&self.cells[a].secs_spent)
+        });
+        positions.truncate(limit);
+        positions
+    }
+
+    /// Returns the longest time spent on any cell, in seconds.
+    pub fn max_secs_spent(&self) -> f32 {
+        self.cells
+            .iter()
+            .map(|cell| cell.secs_spent)
+            .fold(0., f32::max)
+    }
+
+    /// Sums up the time and mistakes per technique, from the easiest technique
+    /// to the hardest, followed by the guesses.
+    pub fn techniques(&self) -> Vec<TechniqueAnalysis> {
+        let mut techniques: Vec<TechniqueAnalysis> = Vec::new();
+        for cell in self.cells.iter().filter(|cell| !cell.is_given) {
+            match techniques
+                .iter_mut()
+                .find(|analysis| analysis.technique == cell.technique)
+            {
+                Some(analysis) => {
+                    analysis.num_cells += 1;
+                    analysis.secs_spent += cell.secs_spent;
+                    analysis.num_mistakes += cell.num_mistakes;
+                }
+                None => techniques.push(TechniqueAnalysis {
+                    technique: cell.technique,
+                    num_cells: 1,
+                    secs_spent: cell.secs_spent,
+                    num_mistakes: cell.num_mistakes,
+                }),
+            }
+        }
+
+        techniques.sort_by_key(|analysis| (analysis.technique.is_none(), analysis.technique));
+        techniques
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sudoku::strategies::arb_game;
+    use crate::sudoku::{get_x_and_y_from_pos, Move};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn time_is_spent_on_the_cells_that_were_filled_in(game in arb_game()) {
+            let mut replay = Replay { moves: Vec::new(), ..Replay::new(&game) };
+            for pos in (0..81).filter(|&pos| game.start.get_by_pos(pos).is_none()) {
+                let (x, y) = get_x_and_y_from_pos(pos);
+                replay.moves.push(Move {
+                    elapsed_secs: 2. * pos as f32,
+                    action: MoveAction::Place {
+                        x,
+                        y,
+                        n: game.solution.get(x, y).unwrap(),
+                        is_hint: false,
+                        show_mistakes: true,
+                    },
+                });
+            }
+
+            let analysis = Analysis::new(&replay);
+            let total_secs: f32 = analysis.cells.iter().map(|cell| cell.secs_spent).sum();
+            let last_secs = replay.moves.last().map_or(0., |entry| entry.elapsed_secs);
+            prop_assert!((total_secs - last_secs).abs() < 0.01);
+
+            for (pos, cell) in analysis.cells.iter().enumerate() {
+                prop_assert_eq!(cell.is_given, game.start.get_by_pos(pos).is_some());
+                prop_assert_eq!(cell.num_mistakes, 0);
+            }
+
+            let num_open_cells = analysis.cells.iter().filter(|cell| !cell.is_given).count();
+            let num_analyzed_cells: usize = analysis
+                .techniques()
+                .iter()
+                .map(|technique| technique.num_cells)
+                .sum();
+            prop_assert_eq!(num_analyzed_cells, num_open_cells);
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use super::{CellColors, Game, Notes, Sudoku};
+use serde::{Deserialize, Serialize};
+
+/// A snapshot of the board, taken before the player tries out a guess.
+///
+/// Everything the player does afterwards happens on a branch, which may either
+/// be committed to keep the moves, or discarded to return to the snapshot.
+///
+/// Discarding a branch restores the score from the checkpoint, so points that
+/// were scored on a branch only count once it's committed. Mistakes and hints
+/// always count, even on a discarded branch, because otherwise branches could
+/// be used to try out numbers without any penalty.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Checkpoint {
+    pub current: Sudoku,
+    pub notes: Notes,
+    pub corner_notes: Notes,
+    pub mistakes: Notes,
+    pub colors: CellColors,
+    pub score: u32,
+}
+
+impl Checkpoint {
+    /// Takes a snapshot of the given game.
+    pub fn new(game: &Game) -> Self {
+        Self {
+            current: game.current.clone(),
+            notes: game.notes.clone(),
+            corner_notes: game.corner_notes.clone(),
+            mistakes: game.mistakes.clone(),
+            colors: game.colors.clone(),
+            score: game.score,
+        }
+    }
+
+    /// Reverts the given game to the snapshot.
+    pub fn restore(self, game: &mut Game) {
+        game.current = self.current;
+        game.notes = self.notes;
+        game.corner_notes = self.corner_notes;
+        game.mistakes = self.mistakes;
+        game.colors = self.colors;
+        game.score = self.score;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sudoku::strategies::{arb_colors, arb_game, arb_notes};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn restoring_only_keeps_mistakes_and_hints(
+            mut game in arb_game(),
+            other in arb_game(),
+            notes in arb_notes(),
+            colors in arb_colors(),
+        ) {
+            let checkpoint = Checkpoint::new(&game);
+
+            game.current = other.current;
+            game.notes = notes.clone();
+            game.corner_notes = notes.clone();
+            game.mistakes = notes;
+            game.colors = colors;
+            game.score = other.score;
+            game.num_mistakes = other.num_mistakes;
+            game.num_hints = other.num_hints;
+
+            checkpoint.clone().restore(&mut game);
+
+            prop_assert_eq!(Checkpoint::new(&game), checkpoint);
+            prop_assert_eq!(game.num_mistakes, other.num_mistakes);
+            prop_assert_eq!(game.num_hints, other.num_hints);
+        }
+    }
+}
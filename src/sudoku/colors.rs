@@ -0,0 +1,98 @@
+use super::math::get_pos;
+use std::num::NonZeroU8;
+
+/// The number of colors that cells may be painted with.
+pub const NUM_CELL_COLORS: u8 = 6;
+
+/// Keeps track of the colors that cells are painted with.
+///
+/// Colors are identified by a number from 1 through `NUM_CELL_COLORS`. They
+/// have no meaning to the game itself, but help players to keep track of
+/// chains while applying coloring techniques.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellColors {
+    pub(super) cells: [Option<NonZeroU8>; 81],
+}
+
+impl CellColors {
+    /// Returns the color of the cell at the given coordinates, if any.
+    pub fn get(&self, x: u8, y: u8) -> Option<NonZeroU8> {
+        self.cells[get_pos(x, y)]
+    }
+
+    /// Returns whether the cell at the given coordinates has the given color.
+    pub fn has(&self, x: u8, y: u8, color: NonZeroU8) -> bool {
+        self.get(x, y) == Some(color)
+    }
+
+    /// Returns whether any cells are painted at all.
+    pub fn has_colors(&self) -> bool {
+        self.cells.iter().any(Option::is_some)
+    }
+
+    /// Paints the cell at the given coordinates with the given color.
+    pub fn set(&mut self, x: u8, y: u8, color: NonZeroU8) {
+        self.cells[get_pos(x, y)] = Some(color);
+    }
+
+    /// Removes the color from the cell at the given coordinates, if it has the
+    /// given color.
+    pub fn unset(&mut self, x: u8, y: u8, color: NonZeroU8) {
+        let cell = &mut self.cells[get_pos(x, y)];
+        if *cell == Some(color) {
+            *cell = None;
+        }
+    }
+
+    /// Paints all the given cells with the given color, unless they all have it
+    /// already, in which case the color is removed from them instead.
+    pub fn toggle_all(&mut self, cells: &[(u8, u8)], color: NonZeroU8) {
+        if cells.iter().all(|&(x, y)| self.has(x, y, color)) {
+            for &(x, y) in cells {
+                self.unset(x, y, color);
+            }
+        } else {
+            for &(x, y) in cells {
+                self.set(x, y, color);
+            }
+        }
+    }
+
+    /// Removes the given color from all the cells.
+    pub fn clear(&mut self, color: NonZeroU8) {
+        for cell in &mut self.cells {
+            if *cell == Some(color) {
+                *cell = None;
+            }
+        }
+    }
+}
+
+impl Default for CellColors {
+    fn default() -> Self {
+        Self { cells: [None; 81] }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sudoku::strategies::arb_colors;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn clearing_only_affects_the_given_color(mut colors in arb_colors(), color in 1..=NUM_CELL_COLORS) {
+            let color = NonZeroU8::new(color).unwrap();
+            let before = colors.clone();
+            colors.clear(color);
+
+            for y in 0..9 {
+                for x in 0..9 {
+                    let expected = before.get(x, y).filter(|&c| c != color);
+                    prop_assert_eq!(colors.get(x, y), expected);
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use super::math::get_x_and_y_from_pos;
-use super::solver::{rate_difficulty, solve, Difficulty};
+use super::slots::new_slot;
+use super::solver::{rate_difficulty, solve_without_conflicts, Difficulty, Technique};
 use super::Sudoku;
 use crate::sudoku::math::get_pos;
 use crate::sudoku::solver::SolverResult;
@@ -8,10 +9,17 @@ use rand::seq::SliceRandom;
 use rand::Rng;
 use std::num::NonZeroU8;
 
+/// Generating a Sudoku that requires a specific technique may take a few
+/// attempts, especially for the harder techniques.
+const MAX_TRIES_FOR_TECHNIQUE: u32 = 500;
+
 impl super::Game {
     /// Generates a new game at the given difficulty level.
     pub fn generate(difficulty: Difficulty) -> anyhow::Result<Self> {
-        let mut result = Self::default();
+        let mut result = Self {
+            slot: new_slot(),
+            ..Self::default()
+        };
 
         let mut num_tries = 0;
         while num_tries < 10 {
@@ -20,10 +28,13 @@ impl super::Game {
             let SolverResult {
                 solution,
                 difficulty: rated_difficulty,
-            } = solve(start.clone())
+                ..
+            } = solve_without_conflicts(start.clone())
                 .context("Blimey, I could not even solve my own generated Sudoku...")?;
 
-            if rated_difficulty > result.difficulty {
+            // The first attempt is always accepted, so that we don't end up with
+            // an empty game when the lowest difficulty was requested.
+            if num_tries == 0 || rated_difficulty > result.difficulty {
                 result.solution = solution;
                 result.start = start.clone();
                 result.current = start;
@@ -43,8 +54,48 @@ impl super::Game {
             actual_difficulty = result.difficulty
         );
 
+        result.puzzle_id();
         Ok(result)
     }
+
+    /// Generates a new game for practicing the given technique.
+    ///
+    /// The generated Sudoku requires the technique at least once, and it can
+    /// be solved without any technique that is harder.
+    pub fn generate_for_technique(technique: Technique) -> anyhow::Result<Self> {
+        let mut num_tries = 0;
+        while num_tries < MAX_TRIES_FOR_TECHNIQUE {
+            num_tries += 1;
+
+            let start = generate_sudoku_for_technique(technique)?;
+            let SolverResult {
+                solution,
+                difficulty,
+                technique: rated_technique,
+                ..
+            } = solve_without_conflicts(start.clone())
+                .context("Blimey, I could not even solve my own generated Sudoku...")?;
+
+            if rated_technique == Some(technique) && difficulty != Difficulty::Expert {
+                bevy::log::info!(
+                    "Generated Sudoku for practicing {technique:?} within {num_tries} attempts"
+                );
+
+                let game = Self {
+                    start: start.clone(),
+                    solution,
+                    current: start,
+                    difficulty,
+                    slot: new_slot(),
+                    ..Default::default()
+                };
+                game.puzzle_id();
+                return Ok(game);
+            }
+        }
+
+        bail!("Could not generate a Sudoku for practicing {technique:?} in {num_tries} attempts")
+    }
 }
 
 fn generate_sudoku(difficulty: Difficulty) -> anyhow::Result<Sudoku> {
@@ -74,43 +125,11 @@ fn generate_sudoku(difficulty: Difficulty) -> anyhow::Result<Sudoku> {
     }
 
     let mut num_cells_dug = 0;
-    'dig: for i in digging_order {
+    for i in digging_order {
         let (x, y) = get_x_and_y_from_pos(i);
 
-        if min_numbers_per_line > 0 {
-            let mut num_others_in_column = 0;
-            let mut num_others_in_row = 0;
-            for j in 0..9 {
-                if j != y && start.has(x, j) {
-                    num_others_in_column += 1;
-                }
-                if j != x && start.has(j, y) {
-                    num_others_in_row += 1;
-                }
-            }
-
-            if num_others_in_column < min_numbers_per_line
-                || num_others_in_row < min_numbers_per_line
-            {
-                // We would be left with too few numbers in a single row or
-                // column, so continue before we let that happen.
-                continue 'dig;
-            }
-        }
-
-        let n = start.get(x, y);
-
-        // Determine whether the Sudoku remains unique after digging the
-        // number by trying whether the Sudoku is solvable with any other
-        // number filled in at the cell.
-        for other_n in 1..=9 {
-            let other_n = NonZeroU8::new(other_n).unwrap();
-            if Some(other_n) != n
-                && start.may_set(x, y, other_n)
-                && solve(start.set(x, y, other_n)).is_some()
-            {
-                continue 'dig; // It wouldn't remain unique otherwise.
-            }
+        if !may_dig(&start, x, y, min_numbers_per_line) {
+            continue;
         }
 
         let new_start = start.unset(x, y);
@@ -118,7 +137,7 @@ fn generate_sudoku(difficulty: Difficulty) -> anyhow::Result<Sudoku> {
         let rated_difficulty = rate_difficulty(new_start.clone())
             .context("Yikes, I could not even rate my own starting position...")?;
         if rated_difficulty > difficulty {
-            continue 'dig; // It would become too difficult otherwise.
+            continue; // It would become too difficult otherwise.
         }
 
         start = new_start;
@@ -133,6 +152,94 @@ fn generate_sudoku(difficulty: Difficulty) -> anyhow::Result<Sudoku> {
     Ok(start)
 }
 
+/// Generates a Sudoku that can be solved without any technique harder than the
+/// given one. Digging continues until the technique is required, so the result
+/// is likely, but not guaranteed, to require it.
+fn generate_sudoku_for_technique(technique: Technique) -> anyhow::Result<Sudoku> {
+    let Some(solution) = create_solution(Sudoku::new(), 0, 0) else {
+        bail!("Oh boy, I could not even create a solution...");
+    };
+
+    // We don't enforce a minimum amount of numbers per line, because it makes
+    // the harder techniques very unlikely to become required.
+    let num_cells_to_dig =
+        DiggingStrategy::generate(Difficulty::for_technique(technique))?.num_cells_to_dig;
+
+    let mut start = solution;
+    let mut num_cells_dug = 0;
+    for i in get_random_digging_order() {
+        let (x, y) = get_x_and_y_from_pos(i);
+
+        if !may_dig(&start, x, y, 0) {
+            continue;
+        }
+
+        let new_start = start.unset(x, y);
+
+        let SolverResult {
+            difficulty,
+            technique: rated_technique,
+            ..
+        } = solve_without_conflicts(new_start.clone())
+            .context("Yikes, I could not even rate my own starting position...")?;
+        if difficulty == Difficulty::Expert || rated_technique > Some(technique) {
+            continue; // It would become too difficult otherwise.
+        }
+
+        start = new_start;
+        num_cells_dug += 1;
+
+        // Once the technique is required and the board is sparse enough,
+        // we're done.
+        if rated_technique == Some(technique) && num_cells_dug >= num_cells_to_dig {
+            break;
+        }
+    }
+
+    Ok(start)
+}
+
+/// Returns whether the number in the cell at the given coordinates may be dug,
+/// without leaving fewer than `min_numbers_per_line` numbers in its row and
+/// column, and without the Sudoku losing its unique solution.
+fn may_dig(start: &Sudoku, x: u8, y: u8, min_numbers_per_line: u8) -> bool {
+    if min_numbers_per_line > 0 {
+        let mut num_others_in_column = 0;
+        let mut num_others_in_row = 0;
+        for j in 0..9 {
+            if j != y && start.has(x, j) {
+                num_others_in_column += 1;
+            }
+            if j != x && start.has(j, y) {
+                num_others_in_row += 1;
+            }
+        }
+
+        if num_others_in_column < min_numbers_per_line || num_others_in_row < min_numbers_per_line {
+            // We would be left with too few numbers in a single row or
+            // column, so continue before we let that happen.
+            return false;
+        }
+    }
+
+    let n = start.get(x, y);
+
+    // Determine whether the Sudoku remains unique after digging the number by
+    // trying whether the Sudoku is solvable with any other number filled in at
+    // the cell.
+    for other_n in 1..=9 {
+        let other_n = NonZeroU8::new(other_n).unwrap();
+        if Some(other_n) != n
+            && start.may_set(x, y, other_n)
+            && solve_without_conflicts(start.set(x, y, other_n)).is_some()
+        {
+            return false; // It wouldn't remain unique otherwise.
+        }
+    }
+
+    true
+}
+
 /// Attempt to create a solution by recursively filling the cells, starting at a
 /// random number to create unique solutions.
 ///
@@ -301,3 +408,43 @@ fn get_spiraling_digging_order() -> [usize; 81] {
 
     digging_order
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sudoku::strategies::arb_difficulty;
+    use crate::sudoku::Game;
+    use proptest::prelude::*;
+
+    proptest! {
+        // Generating is slow, especially for the higher difficulties.
+        #![proptest_config(ProptestConfig::with_cases(8))]
+
+        #[test]
+        fn generated_games_are_unique_and_solvable(difficulty in arb_difficulty()) {
+            let game = Game::generate(difficulty).unwrap();
+
+            prop_assert!(game.solution.is_solved());
+            prop_assert_eq!(game.start.find_unique_solution(), Some(game.solution.clone()));
+            prop_assert_eq!(&game.current, &game.start);
+            prop_assert!(game.difficulty <= difficulty);
+            prop_assert_eq!(rate_difficulty(game.start.clone()), Some(game.difficulty));
+        }
+
+        #[test]
+        fn practice_games_require_their_technique(
+            technique in prop_oneof![Just(Technique::OnlyNumber), Just(Technique::LoneRanger)]
+        ) {
+            let game = Game::generate_for_technique(technique).unwrap();
+
+            prop_assert!(game.solution.is_solved());
+            prop_assert_eq!(game.start.find_unique_solution(), Some(game.solution.clone()));
+            prop_assert_eq!(&game.current, &game.start);
+
+            let result = solve_without_conflicts(game.start.clone()).unwrap();
+            prop_assert_eq!(result.technique, Some(technique));
+            prop_assert_eq!(result.difficulty, game.difficulty);
+            prop_assert_ne!(result.difficulty, Difficulty::Expert);
+        }
+    }
+}
@@ -1,17 +1,34 @@
+mod analysis;
+mod checkpoint;
+mod colors;
 mod generator;
 mod math;
 mod notes;
 mod persistence;
+mod replay;
+mod slots;
 mod solver;
+mod transform;
 
+#[cfg(test)]
+pub(crate) mod strategies;
+
+use anyhow::bail;
 use bevy::prelude::Resource;
-use solver::solve;
+use serde::{Deserialize, Serialize};
+use solver::{find_solutions, solve};
 use std::fmt::{self, Write};
 use std::num::NonZeroU8;
 use std::ops::Sub;
+use std::sync::OnceLock;
 
+pub use analysis::{Analysis, TechniqueAnalysis};
+pub use checkpoint::Checkpoint;
+pub use colors::{CellColors, NUM_CELL_COLORS};
 pub use math::*;
-pub use solver::Difficulty;
+pub use replay::{Move, MoveAction, Replay};
+pub use solver::{rate_difficulty, Difficulty, Technique};
+pub use transform::Symmetry;
 
 const START_MULTIPLIERS_BY_DIFFICULTY: [i32; 5] = [20, 40, 60, 80, 100];
 const TIME_FOR_MULTIPLIER: i32 = 20;
@@ -32,26 +49,83 @@ pub struct SetNumberOptions {
     pub show_mistakes: bool,
 }
 
+/// The layers in which notes can be placed.
+///
+/// Center notes list the candidates for a cell, while corner notes mark the
+/// cells within a block where a number may go.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteLayer {
+    #[default]
+    Center,
+    Corner,
+}
+
 /// A Sudoku game with a starting board and a solution, a current state, and
 /// notes.
-#[derive(Default, Resource)]
+#[derive(Debug, Default, Resource)]
 pub struct Game {
     pub start: Sudoku,
     pub solution: Sudoku,
     pub current: Sudoku,
+    /// The center notes, which are the ones that take part in auto-filling.
     pub notes: Notes,
+    pub corner_notes: Notes,
     pub mistakes: Notes,
+    pub colors: CellColors,
+    /// Checkpoints for the branches the player is trying out, innermost last.
+    pub checkpoints: Vec<Checkpoint>,
     pub difficulty: Difficulty,
     pub score: u32,
     pub elapsed_secs: f32,
     pub num_mistakes: u32,
+    /// The mistakes broken down by the number that was wrongly filled in,
+    /// starting with the 1.
+    pub num_mistakes_by_number: [u32; 9],
     pub num_hints: u32,
+    /// How often the board was checked for wrong numbers.
+    pub num_checks: u32,
+    pub num_reveals: u32,
+    pub num_restarts: u32,
+    /// Whether the player gave up and had the solution filled in.
+    pub gave_up: bool,
+    /// Everything the player did, in order, so the game can be replayed.
+    pub moves: Vec<Move>,
+    /// The moves that were made after the move log was full, which are
+    /// counted, but not recorded.
+    pub num_unrecorded_moves: u32,
+    /// The save slot the game is stored in, or 0 for games that aren't
+    /// stored, such as the tutorial.
+    pub slot: u64,
+    /// The name the player gave the game, if any.
+    pub name: Option<String>,
+    /// The canonical hash of the starting numbers, see `Game::puzzle_id()`.
+    pub puzzle_id: OnceLock<u64>,
+    /// Whether the game changed since it was last saved, so the app can save
+    /// it, see `Game::save_if_changed()`.
+    pub is_unsaved: bool,
 }
 
 impl Game {
     /// Returns whether the current game has any notes.
     pub fn has_notes(&self) -> bool {
-        self.notes.has_notes()
+        self.notes.has_notes() || self.corner_notes.has_notes()
+    }
+
+    /// Returns the notes in the given layer.
+    pub fn get_notes(&self, layer: NoteLayer) -> &Notes {
+        match layer {
+            NoteLayer::Center => &self.notes,
+            NoteLayer::Corner => &self.corner_notes,
+        }
+    }
+
+    /// Returns the notes in the given layer for modification.
+    pub fn get_notes_mut(&mut self, layer: NoteLayer) -> &mut Notes {
+        match layer {
+            NoteLayer::Center => &mut self.notes,
+            NoteLayer::Corner => &mut self.corner_notes,
+        }
     }
 
     /// Returns whether the game is in its default (uninitialized) state.
@@ -62,6 +136,7 @@ impl Game {
     /// Returns whether all the instances of a given number have been filled in
     /// and no mistakes have been made with the given number.
     pub fn is_completed(&self, n: NonZeroU8) -> bool {
+        let mut num_instances = 0;
         for pos in 0..81 {
             if let Some(solution_n) = self.solution.get_by_pos(pos) {
                 let actual_n = self.current.get_by_pos(pos);
@@ -69,13 +144,15 @@ impl Game {
                     if actual_n != Some(n) {
                         return false;
                     }
+                    num_instances += 1;
                 } else if actual_n == Some(n) {
                     return false;
                 }
             }
         }
 
-        true
+        // A solution that isn't fully known can't tell us we're done.
+        num_instances == 9
     }
 
     /// Returns whether the game is (correctly) solved.
@@ -113,20 +190,119 @@ impl Game {
                 .expect("Cannot solve tutorial"),
             current: Sudoku::tutorial(),
             notes: Notes::default(),
+            corner_notes: Notes::default(),
             mistakes: Notes::default(),
+            colors: CellColors::default(),
+            checkpoints: Vec::new(),
             difficulty: Difficulty::Trivial,
             score: 0,
             elapsed_secs: 0.,
             num_mistakes: 0,
+            num_mistakes_by_number: [0; 9],
             num_hints: 1,
+            num_checks: 0,
+            num_reveals: 0,
+            num_restarts: 0,
+            gave_up: false,
+            moves: Vec::new(),
+            num_unrecorded_moves: 0,
+            slot: 0,
+            name: None,
+            puzzle_id: OnceLock::new(),
+            is_unsaved: false,
         }
     }
 
+    /// Creates a new game from a puzzle that was entered by hand.
+    ///
+    /// Fails if the puzzle doesn't have a unique solution.
+    pub fn from_puzzle(start: Sudoku) -> anyhow::Result<Self> {
+        let Some(solution) = start.find_unique_solution() else {
+            bail!("Puzzle doesn't have a unique solution");
+        };
+
+        let game = Self {
+            difficulty: rate_difficulty(start.clone()).unwrap_or_default(),
+            solution,
+            current: start.clone(),
+            start,
+            slot: slots::new_slot(),
+            ..Self::default()
+        };
+        game.puzzle_id();
+        Ok(game)
+    }
+
+    /// Returns the canonical hash of the puzzle, which identifies it in the
+    /// highscores and statistics, see `Sudoku::canonical_hash()`.
+    ///
+    /// Finding it takes a while, so it's kept once found. Games that are
+    /// generated or loaded find it right away, so it's ready when the game
+    /// ends.
+    pub fn puzzle_id(&self) -> u64 {
+        *self.puzzle_id.get_or_init(|| self.start.canonical_hash())
+    }
+
     /// Returns whether the game may be continued.
     pub fn may_continue(&self) -> bool {
         !self.is_default() && !self.is_solved()
     }
 
+    /// Returns whether the player restarted the game, revealed cells, or gave
+    /// up. Such games don't make it into the highscores.
+    pub fn is_assisted(&self) -> bool {
+        self.num_reveals > 0 || self.num_restarts > 0 || self.gave_up
+    }
+
+    /// Resets the board to its starting numbers, as if the game was just
+    /// started. Only the fact that it was restarted is remembered.
+    pub fn restart(&mut self) {
+        *self = Self {
+            start: self.start.clone(),
+            solution: self.solution.clone(),
+            current: self.start.clone(),
+            difficulty: self.difficulty,
+            num_restarts: self.num_restarts + 1,
+            // Restarts are part of the replay.
+            moves: std::mem::take(&mut self.moves),
+            slot: self.slot,
+            name: self.name.take(),
+            puzzle_id: std::mem::take(&mut self.puzzle_id),
+            ..Self::default()
+        };
+        self.make_move(MoveAction::Restart);
+
+        self.is_unsaved = true; // Make sure we don't loose any progress.
+    }
+
+    /// Fills in the solution for the cell at the given coordinates.
+    ///
+    /// Returns `false` if there was nothing to reveal, because the cell
+    /// already contains the correct number.
+    pub fn reveal(&mut self, x: u8, y: u8) -> bool {
+        let Some(n) = self.solution.get(x, y) else {
+            return false;
+        };
+        if self.current.get(x, y) == Some(n) {
+            return false;
+        }
+
+        self.make_move(MoveAction::Reveal { x, y });
+        self.num_reveals += 1;
+
+        self.is_unsaved = true; // Make sure we don't loose any progress.
+
+        true
+    }
+
+    /// Gives up on the game, and fills in the entire solution.
+    pub fn give_up(&mut self) {
+        self.make_move(MoveAction::GiveUp);
+        self.gave_up = true;
+
+        self.is_unsaved = true; // Make sure we don't loose any progress.
+    }
+
     /// Sets the given number `n` at the given `x` and `y` coordinates.
     ///
     /// Also increases the score or `num_mistakes`, depending on whether the
@@ -145,30 +321,27 @@ impl Game {
             return existing_n == n;
         }
 
-        let is_correct = self.solution.get(x, y) == Some(n);
-        if is_correct || !show_mistakes {
-            self.current = self.current.set(x, y, n);
-        }
-
         self.elapsed_secs = elapsed_secs;
 
+        let is_correct = self.solution.get(x, y) == Some(n);
+        self.make_move(MoveAction::Place {
+            x,
+            y,
+            n,
+            is_hint,
+            show_mistakes,
+        });
+
         if !is_correct {
             self.num_mistakes += 1;
+            self.num_mistakes_by_number[n.get() as usize - 1] += 1;
         }
 
         if is_correct && !is_hint {
             self.score += self.calculate_score(x, y, n) * self.calculate_multiplier();
         }
 
-        if show_mistakes && !is_correct {
-            self.mistakes.set(x, y, n);
-            self.notes.unset(x, y, n);
-        } else {
-            self.notes.remove_all_notes_affected_by_set(x, y, n);
-            self.mistakes.clear(x, y);
-        }
-
-        self.save(); // Make sure we don't loose any progress.
+        self.is_unsaved = true; // Make sure we don't loose any progress.
 
         is_correct
     }
@@ -178,12 +351,13 @@ impl Game {
 
         // 1 mistake drops the multiplier by 2, 2 mistakes by 6, 3 mistakes by 12, etc..
         for i in 1..=self.num_mistakes {
-            multiplier_penalty += 2 * i as i32;
+            multiplier_penalty = multiplier_penalty.saturating_add(2 * i as i32);
         }
 
-        // Similar strategy for hints, but with a less hefty penalty.
-        for i in 1..=self.num_hints {
-            multiplier_penalty += i as i32;
+        // Similar strategy for hints, but with a less hefty penalty. Checking
+        // the board counts as a hint too.
+        for i in 1..=self.num_hints.saturating_add(self.num_checks) {
+            multiplier_penalty = multiplier_penalty.saturating_add(i as i32);
         }
 
         START_MULTIPLIERS_BY_DIFFICULTY[self.difficulty as usize]
@@ -245,25 +419,28 @@ impl Game {
         for pos in 0..81 {
             if let Some(n) = self.current.get_by_pos(pos) {
                 if self.solution.get_by_pos(pos) != Some(n) {
-                    let (x, y) = get_x_and_y_from_pos(pos);
-                    return Some(Hint { x, y });
+                    return Some(Hint::new(pos, HintReason::Mistake));
                 }
             }
         }
 
+        // The hardest technique that was needed to get rid of notes so far.
+        let mut technique = Technique::OnlyNumber;
+
         let mut notes = Notes::from_sudoku(&self.current);
         'outer: while notes.has_notes() {
             // Find a place that only has a single number:
             for pos in 0..81 {
                 if notes.get_only_number(pos).is_some() {
-                    return Some(get_x_and_y_from_pos(pos).into());
+                    return Some(Hint::new(pos, HintReason::Technique(technique)));
                 }
             }
 
             // Find a lone ranger:
             for pos in 0..81 {
                 if notes.get_lone_ranger(pos).is_some() {
-                    return Some(get_x_and_y_from_pos(pos).into());
+                    let technique = technique.max(Technique::LoneRanger);
+                    return Some(Hint::new(pos, HintReason::Technique(technique)));
                 }
             }
 
@@ -272,6 +449,7 @@ impl Game {
                 if let Some(twins) = notes.find_twins(pos) {
                     if notes.remove_all_notes_affected_by_twins(twins) {
                         bevy::log::info!("Found twins: {twins:?}");
+                        technique = technique.max(Technique::Twins);
                         continue 'outer;
                     }
                 }
@@ -282,6 +460,7 @@ impl Game {
                 if let Some(triplets) = notes.find_triplets(pos) {
                     if notes.remove_all_notes_affected_by_triplets(triplets) {
                         bevy::log::info!("Found triplet: {triplets:?}");
+                        technique = technique.max(Technique::Triplets);
                         continue 'outer;
                     }
                 }
@@ -292,6 +471,7 @@ impl Game {
                 if let Some(twins) = notes.find_hidden_twins(pos) {
                     if notes.remove_all_notes_affected_by_twins(twins) {
                         bevy::log::info!("Found hidden twin: {twins:?}");
+                        technique = technique.max(Technique::HiddenTwins);
                         continue 'outer;
                     }
                 }
@@ -302,6 +482,7 @@ impl Game {
                 if let Some(triplets) = notes.find_hidden_triplets(pos) {
                     if notes.remove_all_notes_affected_by_triplets(triplets) {
                         bevy::log::info!("Found hidden triplet: {triplets:?}");
+                        technique = technique.max(Technique::HiddenTriplets);
                         continue 'outer;
                     }
                 }
@@ -310,7 +491,7 @@ impl Game {
             // Screw it, just give some position with a note:
             for pos in 0..81 {
                 if notes.has_some_number(pos) {
-                    let hint = get_x_and_y_from_pos(pos).into();
+                    let hint = Hint::new(pos, HintReason::Guess);
                     bevy::log::info!("Random hint: {hint:?}");
                     return Some(hint);
                 }
@@ -320,10 +501,184 @@ impl Game {
         None
     }
 
-    pub fn toggle_note(&mut self, x: u8, y: u8, n: NonZeroU8) {
-        self.notes.toggle(x, y, n);
+    pub fn toggle_note(&mut self, x: u8, y: u8, n: NonZeroU8, layer: NoteLayer) {
+        let is_set = !self.get_notes(layer).has(x, y, n);
+        self.make_move(MoveAction::Note {
+            x,
+            y,
+            n,
+            layer,
+            is_set,
+        });
+
+        self.is_unsaved = true; // Make sure we don't loose any progress.
+    }
+
+    /// Toggles the given number in the notes of all the given cells that are
+    /// still open. See [`Notes::toggle_all()`].
+    pub fn toggle_notes(&mut self, cells: &[(u8, u8)], n: NonZeroU8, layer: NoteLayer) {
+        let open_cells: Vec<_> = cells
+            .iter()
+            .copied()
+            .filter(|&(x, y)| !self.current.has(x, y))
+            .collect();
+        if open_cells.is_empty() {
+            return;
+        }
+
+        // See `Notes::toggle_all()`.
+        let notes = self.get_notes(layer);
+        let is_set = !open_cells.iter().all(|&(x, y)| notes.has(x, y, n));
+        self.make_move(MoveAction::Notes {
+            cells: open_cells,
+            n,
+            layer,
+            is_set,
+        });
+
+        self.is_unsaved = true; // Make sure we don't loose any progress.
+    }
+
+    /// Paints the cell at the given coordinates with the given color, or
+    /// removes its color if it was already painted with it.
+    pub fn toggle_color(&mut self, x: u8, y: u8, color: NonZeroU8) {
+        if self.colors.has(x, y, color) {
+            self.colors.unset(x, y, color);
+        } else {
+            self.colors.set(x, y, color);
+        }
+
+        self.is_unsaved = true; // Make sure we don't loose any progress.
+    }
+
+    /// Paints the cell at the given coordinates with the given color, or
+    /// removes the color from it, like when dragging across the board.
+    pub fn paint_color(&mut self, x: u8, y: u8, color: NonZeroU8, is_set: bool) {
+        if self.colors.has(x, y, color) == is_set {
+            return;
+        }
+
+        if is_set {
+            self.colors.set(x, y, color);
+        } else {
+            self.colors.unset(x, y, color);
+        }
+
+        self.is_unsaved = true; // Make sure we don't loose any progress.
+    }
+
+    /// Paints all the given cells with the given color, or removes the color if
+    /// they all had it already.
+    pub fn toggle_colors(&mut self, cells: &[(u8, u8)], color: NonZeroU8) {
+        self.colors.toggle_all(cells, color);
+
+        self.is_unsaved = true; // Make sure we don't loose any progress.
+    }
+
+    /// Removes the given color from all the cells.
+    pub fn clear_color(&mut self, color: NonZeroU8) {
+        self.colors.clear(color);
+
+        self.is_unsaved = true; // Make sure we don't loose any progress.
+    }
+
+    /// Returns whether the player is trying out moves on a branch.
+    pub fn is_branching(&self) -> bool {
+        !self.checkpoints.is_empty()
+    }
+
+    /// Marks a checkpoint, after which the player continues on a branch.
+    ///
+    /// Branches may be nested, in which case committing or discarding applies
+    /// to the innermost branch.
+    pub fn add_checkpoint(&mut self) {
+        self.make_move(MoveAction::AddCheckpoint);
+
+        self.is_unsaved = true; // Make sure we don't loose any progress.
+    }
+
+    /// Keeps the moves on the current branch, and removes its checkpoint.
+    pub fn commit_branch(&mut self) {
+        if self.is_branching() {
+            self.make_move(MoveAction::CommitBranch);
+
+            self.is_unsaved = true; // Make sure we don't loose any progress.
+        }
+    }
+
+    /// Reverts the moves on the current branch, going back to its checkpoint.
+    pub fn discard_branch(&mut self) {
+        if self.is_branching() {
+            self.make_move(MoveAction::DiscardBranch);
+
+            self.is_unsaved = true; // Make sure we don't loose any progress.
+        }
+    }
+
+    /// Fills in the notes with every number that may be filled into the open
+    /// cells without conflicts.
+    ///
+    /// If `only_missing` is `true`, the existing notes are kept and only the
+    /// missing numbers are added. Otherwise, the existing notes are replaced.
+    ///
+    /// Counts as a hint.
+    pub fn fill_candidates(&mut self, only_missing: bool) {
+        self.make_move(MoveAction::FillCandidates { only_missing });
+
+        self.num_hints += 1;
+
+        self.is_unsaved = true; // Make sure we don't loose any progress.
+    }
+
+    /// Returns the cells that are filled in with a number that differs from
+    /// the solution.
+    pub fn check_board(&self) -> Vec<(u8, u8)> {
+        (0..81)
+            .filter(|&pos| {
+                self.current.get_by_pos(pos).is_some()
+                    && self.current.get_by_pos(pos) != self.solution.get_by_pos(pos)
+            })
+            .map(get_x_and_y_from_pos)
+            .collect()
+    }
+
+    /// Checks the notes in all the open cells.
+    ///
+    /// Notes are considered wrong if they conflict with a number that is
+    /// correctly filled in, which includes the starting numbers. Cells are
+    /// considered incomplete if they have notes, but not the number from the
+    /// solution, which prevents the game from being solved through its notes.
+    pub fn check_notes(&self) -> NotesCheck {
+        let correct = Sudoku {
+            cells: std::array::from_fn(|pos| {
+                self.current
+                    .get_by_pos(pos)
+                    .filter(|&n| self.solution.get_by_pos(pos) == Some(n))
+            }),
+        };
+
+        let mut check = NotesCheck::default();
+        for pos in 0..81 {
+            let (x, y) = get_x_and_y_from_pos(pos);
+            if self.current.has(x, y) || !self.notes.has_some_number(pos) {
+                continue;
+            }
+
+            for n in 1..=9 {
+                let n = NonZeroU8::new(n).unwrap();
+                if self.notes.has(x, y, n) && !correct.may_set(x, y, n) {
+                    check.wrong_notes.set(x, y, n);
+                }
+            }
+
+            if let Some(solution_n) = self.solution.get(x, y) {
+                if !self.notes.has(x, y, solution_n) {
+                    check.incomplete_cells.push((x, y));
+                }
+            }
+        }
 
-        self.save(); // Make sure we don't loose any progress.
+        check
     }
 }
 
@@ -341,36 +696,32 @@ impl Sudoku {
 
     /// Returns the only unique solution to this Sudoku.
     ///
-    /// Returns `None` if there are multiple solutions.
+    /// Returns `None` if there are multiple solutions, or none at all.
     pub fn find_unique_solution(&self) -> Option<Self> {
-        let mut sudoku = self.clone();
-        for y in 0..9 {
-            for x in 0..9 {
-                if sudoku.has(x, y) {
-                    continue;
-                }
+        let mut solutions = find_solutions(self, 2).into_iter();
+        match (solutions.next(), solutions.next()) {
+            (Some(solution), None) => Some(solution),
+            _ => None,
+        }
+    }
 
-                let mut number: Option<NonZeroU8> = None;
-                for n in 1..=9 {
-                    let n = NonZeroU8::new(n).unwrap();
-                    if self.may_set(x, y, n) && solve(sudoku.set(x, y, n)).is_some() {
-                        if number.is_some() {
-                            return None; // Only a single number is allowed.
-                        } else {
-                            number = Some(n);
-                        }
-                    }
-                }
+    /// Returns up to `limit` distinct solutions to this Sudoku.
+    ///
+    /// Use a limit of 2 to tell whether there is no solution, a unique
+    /// solution, or multiple solutions.
+    pub fn find_solutions(&self, limit: usize) -> Vec<Self> {
+        find_solutions(self, limit)
+    }
 
-                if let Some(n) = number {
-                    // Filling in the numbers that are known speeds up
-                    // follow-up calls to `solve()`.
-                    sudoku = sudoku.set(x, y, n);
-                }
-            }
-        }
+    /// Returns the number of solutions to this Sudoku, counting no further
+    /// than `limit`.
+    pub fn count_solutions(&self, limit: usize) -> usize {
+        self.find_solutions(limit).len()
+    }
 
-        Some(sudoku)
+    /// Returns the number of cells that have a number.
+    pub fn num_filled_cells(&self) -> usize {
+        self.cells.iter().filter(|cell| cell.is_some()).count()
     }
 
     /// Returns the value of the cell at the given coordinates.
@@ -389,6 +740,44 @@ impl Sudoku {
         self.cells[get_pos(x, y)].is_some()
     }
 
+    /// Returns whether any of the numbers that are filled in conflict with one
+    /// another.
+    pub fn has_conflicts(&self) -> bool {
+        for y in 0..9 {
+            for x in 0..9 {
+                if let Some(n) = self.get(x, y) {
+                    if !self.may_set(x, y, n) {
+                        return true;
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Returns the cells that break the rules: those with a number that also
+    /// occurs elsewhere in their row, column or block, and open cells in which
+    /// no number can be filled in anymore.
+    ///
+    /// Unlike mistakes, conflicts can be found without knowing the solution.
+    pub fn find_conflicts(&self) -> Vec<(u8, u8)> {
+        let mut conflicts = Vec::new();
+        for y in 0..9 {
+            for x in 0..9 {
+                let is_conflict = match self.get(x, y) {
+                    Some(n) => !self.may_set(x, y, n),
+                    None => (1..=9).all(|n| !self.may_set(x, y, NonZeroU8::new(n).unwrap())),
+                };
+                if is_conflict {
+                    conflicts.push((x, y));
+                }
+            }
+        }
+
+        conflicts
+    }
+
     /// Returns whether the Sudoku is (correctly) solved.
     pub fn is_solved(&self) -> bool {
         for y in 0..9 {
@@ -553,6 +942,12 @@ impl Default for Sudoku {
     }
 }
 
+impl fmt::Debug for Sudoku {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\n{self}")
+    }
+}
+
 impl fmt::Display for Sudoku {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for y in 0..9 {
@@ -583,11 +978,41 @@ pub type Cell = Option<NonZeroU8>;
 pub struct Hint {
     pub x: u8,
     pub y: u8,
+    pub reason: HintReason,
+}
+
+impl Hint {
+    fn new(pos: usize, reason: HintReason) -> Self {
+        let (x, y) = get_x_and_y_from_pos(pos);
+        Self { x, y, reason }
+    }
+}
+
+/// Why a cell was picked for a hint.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HintReason {
+    /// The cell has a wrong number in it.
+    Mistake,
+    /// The number for the cell can be found with the given technique.
+    Technique(Technique),
+    /// None of the techniques helped, so the number needs to be guessed.
+    Guess,
 }
 
-impl From<(u8, u8)> for Hint {
-    fn from((x, y): (u8, u8)) -> Self {
-        Self { x, y }
+/// The outcome of checking the notes of a game.
+#[derive(Debug, Default)]
+pub struct NotesCheck {
+    /// Notes that conflict with a correctly filled in number.
+    pub wrong_notes: Notes,
+
+    /// Cells with notes that are missing the number from the solution.
+    pub incomplete_cells: Vec<(u8, u8)>,
+}
+
+impl NotesCheck {
+    /// Returns whether no problems were found.
+    pub fn is_ok(&self) -> bool {
+        !self.wrong_notes.has_notes() && self.incomplete_cells.is_empty()
     }
 }
 
@@ -595,7 +1020,108 @@ impl From<(u8, u8)> for Hint {
 ///
 /// Every cell can have 9 notes, which are represented using bit flags encoded
 /// in `u16` fields.
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Notes {
     cells: [u16; 81],
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sudoku::strategies::arb_game;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn correct_numbers_have_no_conflicts(game in arb_game()) {
+            prop_assert_eq!(game.current.find_conflicts(), vec![]);
+        }
+
+        #[test]
+        fn duplicate_numbers_are_conflicts(game in arb_game(), pos in 0..81usize, n in 1..=9u8) {
+            let (x, y) = get_x_and_y_from_pos(pos);
+            let n = NonZeroU8::new(n).unwrap();
+            prop_assume!(!game.current.has(x, y) && !game.current.may_set(x, y, n));
+
+            let conflicts = game.current.set(x, y, n).find_conflicts();
+            prop_assert!(conflicts.contains(&(x, y)));
+
+            // The number it conflicts with is flagged as well.
+            let conflicts_with_other = conflicts.iter().any(|&(other_x, other_y)| {
+                (other_x, other_y) != (x, y) && game.current.get(other_x, other_y) == Some(n)
+            });
+            prop_assert!(conflicts_with_other);
+        }
+
+        #[test]
+        fn board_check_finds_wrong_numbers(mut game in arb_game(), pos in 0..81usize, n in 1..=9u8) {
+            prop_assert_eq!(game.check_board(), vec![]);
+
+            let (x, y) = get_x_and_y_from_pos(pos);
+            let n = NonZeroU8::new(n).unwrap();
+            prop_assume!(!game.current.has(x, y) && game.solution.get(x, y) != Some(n));
+
+            game.current = game.current.set(x, y, n);
+            prop_assert_eq!(game.check_board(), vec![(x, y)]);
+        }
+
+        #[test]
+        fn hints_point_out_mistakes_first(mut game in arb_game(), pos in 0..81usize, n in 1..=9u8) {
+            let (x, y) = get_x_and_y_from_pos(pos);
+            let n = NonZeroU8::new(n).unwrap();
+            prop_assume!(!game.current.has(x, y) && game.solution.get(x, y) != Some(n));
+
+            game.current = game.current.set(x, y, n);
+            let hint = game.get_hint().unwrap();
+            prop_assert_eq!((hint.x, hint.y, hint.reason), (x, y, HintReason::Mistake));
+        }
+
+        #[test]
+        fn candidates_pass_the_notes_check(mut game in arb_game()) {
+            game.notes = Notes::from_sudoku(&game.current);
+            let check = game.check_notes();
+            prop_assert!(check.is_ok(), "{check:?}");
+        }
+
+        #[test]
+        fn notes_check_finds_problems(mut game in arb_game(), pos in 0..81usize) {
+            let (x, y) = get_x_and_y_from_pos(pos);
+            prop_assume!(!game.current.has(x, y));
+
+            game.notes = Notes::from_sudoku(&game.current);
+            let solution_n = game.solution.get(x, y).unwrap();
+            game.notes.unset(x, y, solution_n);
+            if game.notes.has_some_number(pos) {
+                prop_assert_eq!(game.check_notes().incomplete_cells, vec![(x, y)]);
+            }
+
+            // The numbers in the game are all correct, so any note that
+            // conflicts with them is wrong.
+            for n in 1..=9 {
+                let n = NonZeroU8::new(n).unwrap();
+                game.notes.set(x, y, n);
+                let is_wrong = game.check_notes().wrong_notes.has(x, y, n);
+                prop_assert_eq!(is_wrong, !game.current.may_set(x, y, n));
+            }
+        }
+
+        #[test]
+        fn painted_colors_are_saved(mut game in arb_game(), pos in 0..81usize, color in 1..=NUM_CELL_COLORS) {
+            let (x, y) = get_x_and_y_from_pos(pos);
+            let color = NonZeroU8::new(color).unwrap();
+            game.colors = CellColors::default();
+
+            game.paint_color(x, y, color, false);
+            prop_assert!(!game.is_unsaved, "Nothing changed");
+
+            game.paint_color(x, y, color, true);
+            prop_assert!(game.colors.has(x, y, color));
+            prop_assert!(game.is_unsaved);
+
+            game.is_unsaved = false;
+            game.paint_color(x, y, color, false);
+            prop_assert!(!game.colors.has(x, y, color));
+            prop_assert!(game.is_unsaved);
+        }
+    }
+}
@@ -50,6 +50,13 @@ impl Notes {
         notes
     }
 
+    /// Adds all the notes from `other` to the current notes.
+    pub fn add_all(&mut self, other: &Self) {
+        for (cell, other_cell) in self.cells.iter_mut().zip(other.cells) {
+            *cell |= other_cell;
+        }
+    }
+
     /// Clears the notes in a single cell.
     pub fn clear(&mut self, x: u8, y: u8) {
         self.cells[get_pos(x, y)] = 0;
@@ -360,7 +367,7 @@ impl Notes {
             let other_cell = other.cells[pos];
 
             if current_cell != other_cell {
-                for n in 1..9 {
+                for n in 1..=9 {
                     let bit = 1 << n;
                     if other_cell & bit > 0 && current_cell & bit == 0 {
                         let (x, y) = get_x_and_y_from_pos(pos);
@@ -644,6 +651,22 @@ impl Notes {
         }
     }
 
+    /// Toggles the given number in the notes for all the given cells at once.
+    ///
+    /// The number is removed if all the cells have it in their notes already.
+    /// Otherwise, it is added to the cells that don't have it yet.
+    pub fn toggle_all(&mut self, cells: &[(u8, u8)], n: NonZeroU8) {
+        if cells.iter().all(|&(x, y)| self.has(x, y, n)) {
+            for &(x, y) in cells {
+                self.unset(x, y, n);
+            }
+        } else {
+            for &(x, y) in cells {
+                self.set(x, y, n);
+            }
+        }
+    }
+
     /// Removes the given number from the notes for the cell at the given
     /// coordinates.
     ///
@@ -767,3 +790,124 @@ fn find_hidden_triplets(
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sudoku::strategies::{arb_notes, arb_puzzle};
+    use proptest::prelude::*;
+
+    /// Asserts that every open cell still has the number from the solution in
+    /// its notes.
+    fn assert_solution_in_notes(
+        notes: &Notes,
+        puzzle: &Sudoku,
+        solution: &Sudoku,
+    ) -> Result<(), TestCaseError> {
+        for y in 0..9 {
+            for x in 0..9 {
+                if !puzzle.has(x, y) {
+                    prop_assert!(notes.has(x, y, solution.get(x, y).unwrap()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn eliminations_keep_the_solution((solution, puzzle) in arb_puzzle()) {
+            let mut notes = Notes::from_sudoku(&puzzle);
+            assert_solution_in_notes(&notes, &puzzle, &solution)?;
+
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for pos in 0..81 {
+                    if let Some(twins) = notes.find_twins(pos) {
+                        changed |= notes.remove_all_notes_affected_by_twins(twins);
+                    }
+                    if let Some(triplets) = notes.find_triplets(pos) {
+                        changed |= notes.remove_all_notes_affected_by_triplets(triplets);
+                    }
+                    if let Some(twins) = notes.find_hidden_twins(pos) {
+                        changed |= notes.remove_all_notes_affected_by_twins(twins);
+                    }
+                    if let Some(triplets) = notes.find_hidden_triplets(pos) {
+                        changed |= notes.remove_all_notes_affected_by_triplets(triplets);
+                    }
+                    assert_solution_in_notes(&notes, &puzzle, &solution)?;
+                }
+            }
+        }
+
+        #[test]
+        fn singles_match_the_solution((solution, puzzle) in arb_puzzle()) {
+            let notes = Notes::from_sudoku(&puzzle);
+            for pos in 0..81 {
+                if let Some(n) = notes.get_only_number(pos) {
+                    prop_assert_eq!(Some(n), solution.get_by_pos(pos));
+                }
+                if let Some(n) = notes.get_lone_ranger(pos) {
+                    prop_assert_eq!(Some(n), solution.get_by_pos(pos));
+                }
+            }
+        }
+
+        #[test]
+        fn added_notes_are_combined(mut notes in arb_notes(), other in arb_notes()) {
+            let before = notes.clone();
+            notes.add_all(&other);
+            for y in 0..9 {
+                for x in 0..9 {
+                    for n in 1..=9 {
+                        let n = NonZeroU8::new(n).unwrap();
+                        prop_assert_eq!(
+                            notes.has(x, y, n),
+                            before.has(x, y, n) || other.has(x, y, n)
+                        );
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn notes_are_toggled_for_all_cells_together(
+            mut notes in arb_notes(),
+            cells in prop::collection::vec((0..9u8, 0..9u8), 1..10),
+            n in 1..=9u8,
+        ) {
+            let n = NonZeroU8::new(n).unwrap();
+            let had_all = cells.iter().all(|&(x, y)| notes.has(x, y, n));
+            notes.toggle_all(&cells, n);
+            for &(x, y) in &cells {
+                prop_assert_eq!(notes.has(x, y, n), !had_all);
+            }
+
+            // Toggling again leaves the cells in agreement with each other.
+            notes.toggle_all(&cells, n);
+            for &(x, y) in &cells {
+                prop_assert_eq!(notes.has(x, y, n), had_all);
+            }
+        }
+
+        #[test]
+        fn cleared_notes_are_reported(before in arb_notes(), after in arb_notes()) {
+            let mut expected = Vec::new();
+            for y in 0..9 {
+                for x in 0..9 {
+                    for n in 1..=9 {
+                        let n = NonZeroU8::new(n).unwrap();
+                        if before.has(x, y, n) && !after.has(x, y, n) {
+                            expected.push((x, y, n));
+                        }
+                    }
+                }
+            }
+
+            let mut cleared = after.get_cleared_since(&before);
+            cleared.sort_by_key(|&(x, y, n)| (y, x, n));
+            prop_assert_eq!(cleared, expected);
+        }
+    }
+}
@@ -1,79 +1,105 @@
-use super::{Cell, Difficulty, Game, Notes, Sudoku};
-use crate::utils::ensure_sudoku_dir;
-use anyhow::{anyhow, Context};
+use super::{Cell, CellColors, Checkpoint, Difficulty, Game, Move, Notes, Sudoku, NUM_CELL_COLORS};
+use crate::persistence::{
+    from_versioned_json, insert_missing_fields, to_versioned_json, Migration,
+};
+use anyhow::anyhow;
 use serde::de::{self, SeqAccess, Visitor};
 use serde::ser::SerializeSeq;
 use serde::{Deserialize, Serialize};
-use std::{fmt, fs};
+use serde_json::Value;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// The migrations for saved games, see `from_versioned_json()`.
+const GAME_MIGRATIONS: &[Migration] = &[migrate_unversioned_game, migrate_unrecorded_moves];
 
 impl Game {
-    /// Loads an existing game from disk, or returns `Self::default()` if no
-    /// game could be loaded.
-    pub fn load() -> Self {
-        fs::read(ensure_sudoku_dir().join("game.json"))
-            .context("Can't read file")
-            .and_then(|json| Self::from_json(&json))
-            .map_err(|err| println!("Can't restore Sudoku game: {err}"))
-            .unwrap_or_default()
+    /// Serializes the game to JSON, omitting its solution.
+    pub fn to_json(&self) -> Result<Vec<u8>, anyhow::Error> {
+        to_versioned_json(&SerializedGame::from(self), GAME_MIGRATIONS)
     }
 
-    /// Saves the game to disk.
+    /// Parses the game from JSON, verifying there is only a single solution.
     ///
-    /// This is called automatically on drop.
-    pub fn save(&self) {
-        let game_path = ensure_sudoku_dir().join("game.json");
-
-        if self.start == Sudoku::tutorial() {
-            // Do nothing.
-        } else if self.current == Sudoku::default() || self.is_solved() {
-            if game_path.exists() {
-                fs::remove_file(game_path)
-                    .unwrap_or_else(|err| println!("Can't clean up Sudoku game: {err}"));
+    /// This must never panic, no matter how the input was mangled.
+    pub fn from_json(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        from_versioned_json(bytes, GAME_MIGRATIONS).and_then(|serialized_game| {
+            let SerializedGame {
+                start,
+                current,
+                notes,
+                corner_notes,
+                mistakes,
+                colors,
+                checkpoints,
+                difficulty,
+                score,
+                elapsed_secs,
+                num_mistakes,
+                num_mistakes_by_number,
+                num_hints,
+                num_checks,
+                num_reveals,
+                num_restarts,
+                gave_up,
+                moves,
+                num_unrecorded_moves,
+                slot,
+                name,
+            } = serialized_game;
+            let matches_start = |sudoku: &Sudoku| {
+                (0..81).all(|pos| {
+                    start.get_by_pos(pos).is_none()
+                        || sudoku.get_by_pos(pos) == start.get_by_pos(pos)
+                })
+            };
+            if !matches_start(&current) {
+                return Err(anyhow!("Saved game didn't match its starting numbers"));
+            }
+            if !checkpoints
+                .iter()
+                .all(|checkpoint| matches_start(&checkpoint.current))
+            {
+                return Err(anyhow!(
+                    "Saved checkpoint didn't match its starting numbers"
+                ));
+            }
+            if !moves.iter().all(|entry| entry.action.is_valid()) {
+                return Err(anyhow!("Saved move log contained moves outside the board"));
             }
-        } else {
-            self.to_json()
-                .and_then(|json| fs::write(game_path, json).context("Can't write to file"))
-                .unwrap_or_else(|err| println!("Can't save Sudoku game: {err}"));
-        }
-    }
-
-    /// Serializes the game to JSON, omitting its solution.
-    fn to_json(&self) -> Result<Vec<u8>, anyhow::Error> {
-        serde_json::to_vec(&SerializedGame::from(self)).map_err(anyhow::Error::from)
-    }
 
-    /// Parses the game from JSON, verifying there is only a single solution.
-    fn from_json(bytes: &[u8]) -> Result<Self, anyhow::Error> {
-        serde_json::from_slice(bytes)
-            .map_err(anyhow::Error::from)
-            .and_then(|serialized_game| {
-                let SerializedGame {
-                    start,
-                    current,
-                    notes,
-                    mistakes,
-                    difficulty,
-                    score,
-                    elapsed_secs,
-                    num_mistakes,
-                    num_hints,
-                } = serialized_game;
-                match start.find_unique_solution() {
-                    Some(solution) => Ok(Game {
-                        start,
-                        current,
-                        solution,
-                        notes,
-                        mistakes,
-                        difficulty,
-                        score,
-                        elapsed_secs,
-                        num_mistakes,
-                        num_hints,
-                    }),
-                    None => Err(anyhow!("Saved game didn't have a unique solution")),
-                }
-            })
+            let Some(solution) = start.find_unique_solution() else {
+                return Err(anyhow!("Saved game didn't have a unique solution"));
+            };
+            let game = Game {
+                start,
+                current,
+                solution,
+                notes,
+                corner_notes,
+                mistakes,
+                colors,
+                checkpoints,
+                difficulty,
+                score,
+                elapsed_secs,
+                num_mistakes,
+                num_mistakes_by_number,
+                num_hints,
+                num_checks,
+                num_reveals,
+                num_restarts,
+                gave_up,
+                moves,
+                num_unrecorded_moves,
+                slot,
+                name,
+                puzzle_id: OnceLock::new(),
+                is_unsaved: false,
+            };
+            game.puzzle_id();
+            Ok(game)
+        })
     }
 }
 
@@ -83,12 +109,55 @@ pub struct SerializedGame {
     pub start: Sudoku,
     pub current: Sudoku,
     pub notes: Notes,
+    pub corner_notes: Notes,
     pub mistakes: Notes,
+    pub colors: CellColors,
+    pub checkpoints: Vec<Checkpoint>,
     pub difficulty: Difficulty,
     pub score: u32,
     pub elapsed_secs: f32,
     pub num_mistakes: u32,
+    pub num_mistakes_by_number: [u32; 9],
     pub num_hints: u32,
+    pub num_checks: u32,
+    pub num_reveals: u32,
+    pub num_restarts: u32,
+    pub gave_up: bool,
+    pub moves: Vec<Move>,
+    pub num_unrecorded_moves: u32,
+    pub slot: u64,
+    pub name: Option<String>,
+}
+
+/// Fills in the fields that were added before games recorded their schema
+/// version.
+///
+/// Games from before then may lack any of them, because they were added one
+/// at a time: corner notes, colors, checkpoints, the restart, reveal and give
+/// up actions, board checks, mistakes per number, the move log and save slots.
+fn migrate_unversioned_game(value: Value) -> Result<Value, anyhow::Error> {
+    insert_missing_fields(
+        value,
+        [
+            ("corner_notes", serde_json::to_value(Notes::default())?),
+            ("colors", serde_json::to_value(CellColors::default())?),
+            ("checkpoints", Value::Array(Vec::new())),
+            ("num_mistakes_by_number", serde_json::to_value([0u32; 9])?),
+            ("num_checks", Value::from(0)),
+            ("num_reveals", Value::from(0)),
+            ("num_restarts", Value::from(0)),
+            ("gave_up", Value::from(false)),
+            ("moves", Value::Array(Vec::new())),
+            ("slot", Value::from(0)),
+            ("name", Value::Null),
+        ],
+    )
+}
+
+/// Adds the count of the moves that didn't fit into the move log, which
+/// wasn't kept before.
+fn migrate_unrecorded_moves(value: Value) -> Result<Value, anyhow::Error> {
+    insert_missing_fields(value, [("num_unrecorded_moves", Value::from(0))])
 }
 
 impl From<&Game> for SerializedGame {
@@ -97,12 +166,24 @@ impl From<&Game> for SerializedGame {
             start: game.start.clone(),
             current: game.current.clone(),
             notes: game.notes.clone(),
+            corner_notes: game.corner_notes.clone(),
             mistakes: game.mistakes.clone(),
+            colors: game.colors.clone(),
+            checkpoints: game.checkpoints.clone(),
             difficulty: game.difficulty,
             score: game.score,
             elapsed_secs: game.elapsed_secs,
             num_mistakes: game.num_mistakes,
+            num_mistakes_by_number: game.num_mistakes_by_number,
             num_hints: game.num_hints,
+            num_checks: game.num_checks,
+            num_reveals: game.num_reveals,
+            num_restarts: game.num_restarts,
+            gave_up: game.gave_up,
+            moves: game.moves.clone(),
+            num_unrecorded_moves: game.num_unrecorded_moves,
+            slot: game.slot,
+            name: game.name.clone(),
         }
     }
 }
@@ -132,27 +213,24 @@ impl<'de> Visitor<'de> for SudokuVisitor {
     where
         A: SeqAccess<'de>,
     {
-        let vec: Vec<Cell> = (0..81)
-            .map(|_| {
-                let cell: Cell = seq.next_element()?.unwrap_or_default();
-                if let Some(n) = cell {
-                    if n.get() > 9 {
-                        return Err(de::Error::invalid_value(
-                            de::Unexpected::Unsigned(n.get().into()),
-                            &self,
-                        ));
-                    }
+        let mut cells = [None; 81];
+        for (i, cell) in cells.iter_mut().enumerate() {
+            let value: Cell = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+            if let Some(n) = value {
+                if n.get() > 9 {
+                    return Err(de::Error::invalid_value(
+                        de::Unexpected::Unsigned(n.get().into()),
+                        &self,
+                    ));
                 }
-                Ok(cell)
-            })
-            .collect::<Result<_, _>>()?;
-        if vec.len() != 81 {
-            return Err(de::Error::invalid_length(81, &self));
+            }
+            *cell = value;
         }
+        reject_trailing_elements(&mut seq, &self)?;
 
-        Ok(Sudoku {
-            cells: vec.try_into().unwrap_or([None; 81]),
-        })
+        Ok(Sudoku { cells })
     }
 }
 
@@ -169,6 +247,65 @@ impl Serialize for Sudoku {
     }
 }
 
+impl<'de> Deserialize<'de> for CellColors {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(CellColorsVisitor)
+    }
+}
+
+struct CellColorsVisitor;
+
+impl<'de> Visitor<'de> for CellColorsVisitor {
+    type Value = CellColors;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "an array with 81 optional integers from 1 through {NUM_CELL_COLORS}"
+        )
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut cells = [None; 81];
+        for (i, cell) in cells.iter_mut().enumerate() {
+            let value: Cell = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+            if let Some(color) = value {
+                if color.get() > NUM_CELL_COLORS {
+                    return Err(de::Error::invalid_value(
+                        de::Unexpected::Unsigned(color.get().into()),
+                        &self,
+                    ));
+                }
+            }
+            *cell = value;
+        }
+        reject_trailing_elements(&mut seq, &self)?;
+
+        Ok(CellColors { cells })
+    }
+}
+
+impl Serialize for CellColors {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(81))?;
+        for cell in self.cells {
+            seq.serialize_element(&cell)?;
+        }
+        seq.end()
+    }
+}
+
 impl<'de> Deserialize<'de> for Notes {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -180,24 +317,40 @@ impl<'de> Deserialize<'de> for Notes {
 
 struct NotesVisitor;
 
+/// The bits that may be set in the notes for a single cell, one for each of the
+/// numbers 1 through 9.
+const VALID_NOTES_MASK: u16 = 0b11_1111_1110;
+
 impl<'de> Visitor<'de> for NotesVisitor {
     type Value = Notes;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "an array with 81 integers")
+        write!(
+            formatter,
+            "an array with 81 bit sets of the numbers 1 through 9"
+        )
     }
 
     fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
     where
         A: SeqAccess<'de>,
     {
-        let vec: Vec<u16> = (0..81)
-            .map(|_| Ok(seq.next_element()?.unwrap_or_default()))
-            .collect::<Result<_, _>>()?;
+        let mut cells = [0; 81];
+        for (i, cell) in cells.iter_mut().enumerate() {
+            let value: u16 = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+            if value & !VALID_NOTES_MASK != 0 {
+                return Err(de::Error::invalid_value(
+                    de::Unexpected::Unsigned(value.into()),
+                    &self,
+                ));
+            }
+            *cell = value;
+        }
+        reject_trailing_elements(&mut seq, &self)?;
 
-        Ok(Notes {
-            cells: vec.try_into().unwrap_or([0; 81]),
-        })
+        Ok(Notes { cells })
     }
 }
 
@@ -213,3 +366,188 @@ impl Serialize for Notes {
         seq.end()
     }
 }
+
+/// Makes sure a sequence of cells doesn't contain more than 81 elements.
+fn reject_trailing_elements<'de, A, V>(seq: &mut A, visitor: &V) -> Result<(), A::Error>
+where
+    A: SeqAccess<'de>,
+    V: Visitor<'de>,
+{
+    match seq.next_element::<de::IgnoredAny>()? {
+        Some(_) => Err(de::Error::invalid_length(82, visitor)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{assert_matches_fixture, read_fixture};
+    use crate::sudoku::strategies::arb_game;
+    use proptest::prelude::*;
+    use serde_json::Value;
+
+    /// Serializes the game and lets the caller mangle it, before parsing it
+    /// again.
+    fn mangle(game: &Game, f: impl FnOnce(&mut serde_json::Map<String, Value>)) -> Vec<u8> {
+        let mut value: Value = serde_json::from_slice(&game.to_json().unwrap()).unwrap();
+        f(value.as_object_mut().unwrap());
+        serde_json::to_vec(&value).unwrap()
+    }
+
+    fn arb_field() -> impl Strategy<Value = &'static str> {
+        prop_oneof![
+            Just("start"),
+            Just("current"),
+            Just("notes"),
+            Just("corner_notes"),
+            Just("mistakes"),
+            Just("colors"),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn games_survive_a_round_trip(game in arb_game()) {
+            let restored = Game::from_json(&game.to_json().unwrap()).unwrap();
+
+            prop_assert_eq!(&restored.start, &game.start);
+            prop_assert_eq!(&restored.solution, &game.solution);
+            prop_assert_eq!(&restored.current, &game.current);
+            prop_assert_eq!(&restored.notes, &game.notes);
+            prop_assert_eq!(&restored.corner_notes, &game.corner_notes);
+            prop_assert_eq!(&restored.mistakes, &game.mistakes);
+            prop_assert_eq!(&restored.colors, &game.colors);
+            prop_assert_eq!(&restored.checkpoints, &game.checkpoints);
+            prop_assert_eq!(restored.difficulty, game.difficulty);
+            prop_assert_eq!(restored.score, game.score);
+            prop_assert_eq!(restored.elapsed_secs, game.elapsed_secs);
+            prop_assert_eq!(restored.num_mistakes, game.num_mistakes);
+            prop_assert_eq!(restored.num_mistakes_by_number, game.num_mistakes_by_number);
+            prop_assert_eq!(restored.num_hints, game.num_hints);
+            prop_assert_eq!(restored.num_checks, game.num_checks);
+            prop_assert_eq!(restored.num_reveals, game.num_reveals);
+            prop_assert_eq!(restored.num_restarts, game.num_restarts);
+            prop_assert_eq!(restored.gave_up, game.gave_up);
+            prop_assert_eq!(&restored.moves, &game.moves);
+            prop_assert_eq!(restored.slot, game.slot);
+            prop_assert_eq!(&restored.name, &game.name);
+        }
+
+        #[test]
+        fn truncated_arrays_are_rejected(game in arb_game(), field in arb_field(), len in 0..81usize) {
+            let json = mangle(&game, |map| {
+                map.get_mut(field).unwrap().as_array_mut().unwrap().truncate(len);
+            });
+            prop_assert!(Game::from_json(&json).is_err());
+        }
+
+        #[test]
+        fn extended_arrays_are_rejected(game in arb_game(), field in arb_field()) {
+            let json = mangle(&game, |map| {
+                map.get_mut(field).unwrap().as_array_mut().unwrap().push(Value::from(0));
+            });
+            prop_assert!(Game::from_json(&json).is_err());
+        }
+
+        #[test]
+        fn invalid_numbers_are_rejected(game in arb_game(), pos in 0..81usize, n in 10..=255u8) {
+            let json = mangle(&game, |map| {
+                map.get_mut("current").unwrap()[pos] = Value::from(n);
+            });
+            prop_assert!(Game::from_json(&json).is_err());
+        }
+
+        #[test]
+        fn invalid_colors_are_rejected(game in arb_game(), pos in 0..81usize, color in NUM_CELL_COLORS + 1..=255u8) {
+            let json = mangle(&game, |map| {
+                map.get_mut("colors").unwrap()[pos] = Value::from(color);
+            });
+            prop_assert!(Game::from_json(&json).is_err());
+        }
+
+        #[test]
+        fn checkpoints_must_match_the_starting_numbers(game in arb_game(), pos in 0..81usize) {
+            prop_assume!(!game.checkpoints.is_empty());
+            let Some(n) = game.start.get_by_pos(pos) else {
+                return Ok(());
+            };
+
+            let json = mangle(&game, |map| {
+                map.get_mut("checkpoints").unwrap()[0]["current"][pos] = Value::from(n.get() % 9 + 1);
+            });
+            prop_assert!(Game::from_json(&json).is_err());
+        }
+
+        #[test]
+        fn invalid_notes_are_rejected(game in arb_game(), pos in 0..81usize, bit in prop_oneof![Just(0), 10..16u16]) {
+            let json = mangle(&game, |map| {
+                map.get_mut("notes").unwrap()[pos] = Value::from(1u16 << bit);
+            });
+            prop_assert!(Game::from_json(&json).is_err());
+        }
+
+        #[test]
+        fn games_without_newer_fields_are_accepted(game in arb_game()) {
+            let json = mangle(&game, |map| {
+                map.remove("version");
+                map.remove("corner_notes");
+                map.remove("colors");
+                map.remove("checkpoints");
+                map.remove("num_mistakes_by_number");
+                map.remove("num_checks");
+                map.remove("num_reveals");
+                map.remove("num_restarts");
+                map.remove("gave_up");
+                map.remove("moves");
+                map.remove("slot");
+                map.remove("name");
+            });
+            let restored = Game::from_json(&json).unwrap();
+            prop_assert_eq!(&restored.corner_notes, &Notes::default());
+            prop_assert_eq!(&restored.colors, &CellColors::default());
+            prop_assert!(restored.checkpoints.is_empty());
+            prop_assert_eq!(restored.num_checks, 0);
+            prop_assert!(restored.moves.is_empty());
+            prop_assert_eq!(restored.slot, 0);
+            prop_assert_eq!(&restored.name, &None);
+            prop_assert!(!restored.is_assisted());
+        }
+
+        #[test]
+        fn parsing_never_panics(bytes in proptest::collection::vec(any::<u8>(), 0..1024)) {
+            let _ = Game::from_json(&bytes);
+        }
+    }
+
+    #[test]
+    fn games_from_every_schema_version_are_loaded() {
+        let original = Game::from_json(&read_fixture("game-v0-original.json")).unwrap();
+        let unversioned = Game::from_json(&read_fixture("game-v0.json")).unwrap();
+        let previous = Game::from_json(&read_fixture("game-v1.json")).unwrap();
+        let current = Game::from_json(&read_fixture("game-v2.json")).unwrap();
+
+        for game in [&original, &unversioned, &previous] {
+            assert_eq!(game.start, current.start);
+            assert_eq!(game.current, current.current);
+            assert_eq!(game.notes, current.notes);
+            assert_eq!(game.elapsed_secs, current.elapsed_secs);
+        }
+
+        assert_eq!(original.corner_notes, Notes::default());
+        assert!(original.moves.is_empty());
+        assert_eq!(original.slot, 0);
+        assert_eq!(original.name, None);
+
+        assert_eq!(unversioned.corner_notes, current.corner_notes);
+        assert_eq!(unversioned.moves, current.moves);
+        assert_eq!(unversioned.slot, current.slot);
+        assert_eq!(unversioned.name, current.name);
+
+        assert_eq!(previous.moves, current.moves);
+        assert_eq!(previous.num_unrecorded_moves, 0);
+        assert_eq!(current.num_unrecorded_moves, 3);
+
+        assert_matches_fixture(&current.to_json().unwrap(), "game-v2.json");
+    }
+}
@@ -0,0 +1,512 @@
+use super::{Checkpoint, Difficulty, Game, NoteLayer, Notes, Sudoku};
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroU8;
+
+/// The most moves that are kept in the move log of a game.
+///
+/// Only pathologically long games get there. Later moves are only counted, so
+/// the log doesn't keep growing the save file, and their replays jump from the
+/// last recorded move to the board the game ended with.
+const MAX_NUM_MOVES: usize = 10_000;
+
+/// A single entry in the move log of a game.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Move {
+    /// The game timer at the time of the move, in seconds.
+    pub elapsed_secs: f32,
+    #[serde(flatten)]
+    pub action: MoveAction,
+}
+
+/// Everything the player can do that affects the board, or that gets counted.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum MoveAction {
+    /// A number was filled in. Whether it was a mistake follows from the
+    /// solution.
+    Place {
+        x: u8,
+        y: u8,
+        n: NonZeroU8,
+        is_hint: bool,
+        show_mistakes: bool,
+    },
+    /// The number and the notes in a cell were cleared.
+    Clear {
+        x: u8,
+        y: u8,
+    },
+    /// A note was added to, or removed from, a cell.
+    Note {
+        x: u8,
+        y: u8,
+        n: NonZeroU8,
+        layer: NoteLayer,
+        is_set: bool,
+    },
+    /// A note was added to, or removed from, several cells at once.
+    Notes {
+        cells: Vec<(u8, u8)>,
+        n: NonZeroU8,
+        layer: NoteLayer,
+        is_set: bool,
+    },
+    FillCandidates {
+        only_missing: bool,
+    },
+    /// A hint pointed out the cell at the given coordinates.
+    Hint {
+        x: u8,
+        y: u8,
+    },
+    CheckNotes,
+    CheckBoard,
+    Reveal {
+        x: u8,
+        y: u8,
+    },
+    AddCheckpoint,
+    CommitBranch,
+    DiscardBranch,
+    Restart,
+    GiveUp,
+}
+
+impl MoveAction {
+    /// Returns whether all the cells and numbers of the move are within the
+    /// board, so that it may safely be applied.
+    pub fn is_valid(&self) -> bool {
+        let is_valid_cell = |x: u8, y: u8| x < 9 && y < 9;
+        let is_valid_number = |n: NonZeroU8| n.get() <= 9;
+
+        match self {
+            Self::Place { x, y, n, .. } | Self::Note { x, y, n, .. } => {
+                is_valid_cell(*x, *y) && is_valid_number(*n)
+            }
+            Self::Notes { cells, n, .. } => {
+                cells.iter().all(|&(x, y)| is_valid_cell(x, y)) && is_valid_number(*n)
+            }
+            Self::Clear { x, y } | Self::Hint { x, y } | Self::Reveal { x, y } => {
+                is_valid_cell(*x, *y)
+            }
+            _ => true,
+        }
+    }
+}
+
+impl MoveAction {
+    /// Returns whether the given move undoes this one, assuming this one
+    /// changed the board and the given one is made right after it.
+    fn is_undone_by(&self, other: &MoveAction) -> bool {
+        match (self, other) {
+            (
+                &Self::Note {
+                    x,
+                    y,
+                    n,
+                    layer,
+                    is_set,
+                },
+                &Self::Note {
+                    x: other_x,
+                    y: other_y,
+                    n: other_n,
+                    layer: other_layer,
+                    is_set: other_is_set,
+                },
+            ) => {
+                (x, y, n, layer) == (other_x, other_y, other_n, other_layer)
+                    && is_set != other_is_set
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Game {
+    /// Makes the given move: it's recorded in the move log, timestamped with
+    /// the current `elapsed_secs`, and applied to the board.
+    ///
+    /// Notes that are toggled back and forth leave no trace in the log, and
+    /// moves beyond `MAX_NUM_MOVES` are only counted.
+    ///
+    /// Keeping score is left to the caller, while the game is marked to be
+    /// saved, see `Game::is_unsaved`.
+    pub fn make_move(&mut self, action: MoveAction) {
+        let is_noop = match action {
+            MoveAction::Note {
+                x,
+                y,
+                n,
+                layer,
+                is_set,
+            } => self.get_notes(layer).has(x, y, n) == is_set,
+            _ => false,
+        };
+
+        self.apply_move(&action);
+
+        if is_noop {
+            return;
+        }
+        self.is_unsaved = true;
+        if self
+            .moves
+            .last()
+            .is_some_and(|last| last.action.is_undone_by(&action))
+        {
+            self.moves.pop();
+        } else if self.moves.len() < MAX_NUM_MOVES {
+            self.moves.push(Move {
+                elapsed_secs: self.elapsed_secs,
+                action,
+            });
+        } else {
+            self.num_unrecorded_moves += 1;
+        }
+    }
+
+    /// Applies the effects of the given move to the board, without recording
+    /// it or keeping score.
+    pub(super) fn apply_move(&mut self, action: &MoveAction) {
+        match action {
+            &MoveAction::Place {
+                x,
+                y,
+                n,
+                show_mistakes,
+                ..
+            } => {
+                if self.start.has(x, y) {
+                    return;
+                }
+
+                let is_correct = self.solution.get(x, y) == Some(n);
+                if is_correct || !show_mistakes {
+                    self.current = self.current.set(x, y, n);
+                }
+
+                if show_mistakes && !is_correct {
+                    self.mistakes.set(x, y, n);
+                    self.notes.unset(x, y, n);
+                } else {
+                    self.notes.remove_all_notes_affected_by_set(x, y, n);
+                    self.mistakes.clear(x, y);
+                }
+            }
+            &MoveAction::Clear { x, y } => {
+                if !self.start.has(x, y) {
+                    self.current = self.current.unset(x, y);
+                    self.notes.clear(x, y);
+                }
+            }
+            &MoveAction::Note {
+                x,
+                y,
+                n,
+                layer,
+                is_set,
+            } => {
+                let notes = self.get_notes_mut(layer);
+                if is_set {
+                    notes.set(x, y, n);
+                } else {
+                    notes.unset(x, y, n);
+                }
+            }
+            MoveAction::Notes {
+                cells,
+                n,
+                layer,
+                is_set,
+            } => {
+                let notes = self.get_notes_mut(*layer);
+                for &(x, y) in cells {
+                    if *is_set {
+                        notes.set(x, y, *n);
+                    } else {
+                        notes.unset(x, y, *n);
+                    }
+                }
+            }
+            &MoveAction::FillCandidates { only_missing } => {
+                let candidates = Notes::from_sudoku(&self.current);
+                if only_missing {
+                    self.notes.add_all(&candidates);
+                } else {
+                    self.notes = candidates;
+                }
+            }
+            &MoveAction::Reveal { x, y } => {
+                if let Some(n) = self.solution.get(x, y) {
+                    self.current = self.current.set(x, y, n);
+                    self.notes.remove_all_notes_affected_by_set(x, y, n);
+                    self.corner_notes.clear(x, y);
+                    self.mistakes.clear(x, y);
+                }
+            }
+            MoveAction::AddCheckpoint => self.checkpoints.push(Checkpoint::new(self)),
+            MoveAction::CommitBranch => {
+                self.checkpoints.pop();
+            }
+            MoveAction::DiscardBranch => {
+                if let Some(checkpoint) = self.checkpoints.pop() {
+                    checkpoint.restore(self);
+                }
+            }
+            MoveAction::Restart => {
+                self.current = self.start.clone();
+                self.notes = Notes::default();
+                self.corner_notes = Notes::default();
+                self.mistakes = Notes::default();
+                self.colors = Default::default();
+                self.checkpoints.clear();
+            }
+            MoveAction::GiveUp => {
+                self.current = self.solution.clone();
+                self.notes = Notes::default();
+                self.corner_notes = Notes::default();
+                self.mistakes = Notes::default();
+                self.checkpoints.clear();
+            }
+            MoveAction::Hint { .. } | MoveAction::CheckNotes | MoveAction::CheckBoard => {}
+        }
+    }
+}
+
+/// A finished game that can be played back move by move.
+///
+/// Replays aren't stored by themselves: the move log is archived along with
+/// the game, which a replay can always be created from again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub start: Sudoku,
+    pub solution: Sudoku,
+    pub difficulty: Difficulty,
+    pub moves: Vec<Move>,
+    /// The board the game ended with, and the time it ended at, if the moves
+    /// that led up to it weren't all recorded. It's shown as a final step
+    /// after the recorded moves.
+    pub end: Option<(Checkpoint, f32)>,
+    /// The number of moves that were skipped over by the final step.
+    pub num_unrecorded_moves: u32,
+}
+
+impl Replay {
+    /// Creates a replay of the given game.
+    pub fn new(game: &Game) -> Self {
+        Self {
+            start: game.start.clone(),
+            solution: game.solution.clone(),
+            difficulty: game.difficulty,
+            moves: game.moves.clone(),
+            end: (game.num_unrecorded_moves > 0)
+                .then(|| (Checkpoint::new(game), game.elapsed_secs)),
+            num_unrecorded_moves: game.num_unrecorded_moves,
+        }
+    }
+
+    /// Returns the number of steps in the replay, which are the recorded
+    /// moves, and the jump to the end if not all of them were recorded.
+    pub fn num_steps(&self) -> usize {
+        self.moves.len() + usize::from(self.end.is_some())
+    }
+
+    /// Returns whether the given step jumps over the unrecorded moves.
+    pub fn is_jump(&self, num_steps: usize) -> bool {
+        self.end.is_some() && num_steps == self.num_steps()
+    }
+
+    /// Returns the game as it was after the given number of steps.
+    pub fn game_at(&self, num_steps: usize) -> Game {
+        let mut game = Game {
+            start: self.start.clone(),
+            solution: self.solution.clone(),
+            current: self.start.clone(),
+            difficulty: self.difficulty,
+            ..Game::default()
+        };
+
+        match &self.end {
+            Some((end, _)) if self.is_jump(num_steps) => end.clone().restore(&mut game),
+            _ => {
+                for entry in self.moves.iter().take(num_steps) {
+                    game.apply_move(&entry.action);
+                }
+            }
+        }
+        game.elapsed_secs = self
+            .timeline()
+            .get(num_steps.wrapping_sub(1))
+            .copied()
+            .unwrap_or(0.);
+
+        game
+    }
+
+    /// Returns the time at which every step was made, counted from the start
+    /// of the replay.
+    ///
+    /// The game timer starts over when a game is restarted, so the times in
+    /// the move log don't always go up. The timeline skips over such jumps,
+    /// so it always does.
+    pub fn timeline(&self) -> Vec<f32> {
+        let mut time = 0.;
+        let mut previous_secs = 0.;
+        self.moves
+            .iter()
+            .map(|entry| entry.elapsed_secs)
+            .chain(self.end.as_ref().map(|(_, elapsed_secs)| *elapsed_secs))
+            .map(|elapsed_secs| {
+                time += (elapsed_secs - previous_secs).max(0.);
+                previous_secs = elapsed_secs;
+                time
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sudoku::strategies::{arb_game, arb_moves};
+    use crate::sudoku::{get_x_and_y_from_pos, SetNumberOptions};
+    use proptest::prelude::*;
+
+    /// Lets the player make a move through the regular game methods, which
+    /// don't save anything for the tutorial.
+    fn play(game: &mut Game, action: MoveAction) {
+        match action {
+            MoveAction::Place {
+                x,
+                y,
+                n,
+                is_hint,
+                show_mistakes,
+            } => {
+                let elapsed_secs = game.elapsed_secs;
+                game.set(
+                    x,
+                    y,
+                    n,
+                    SetNumberOptions {
+                        elapsed_secs,
+                        is_hint,
+                        show_mistakes,
+                    },
+                );
+            }
+            MoveAction::Note { x, y, n, layer, .. } => game.toggle_note(x, y, n, layer),
+            MoveAction::Notes {
+                cells, n, layer, ..
+            } => game.toggle_notes(&cells, n, layer),
+            MoveAction::FillCandidates { only_missing } => game.fill_candidates(only_missing),
+            MoveAction::Reveal { x, y } => {
+                game.reveal(x, y);
+            }
+            MoveAction::AddCheckpoint => game.add_checkpoint(),
+            MoveAction::CommitBranch => game.commit_branch(),
+            MoveAction::DiscardBranch => game.discard_branch(),
+            MoveAction::Restart => game.restart(),
+            MoveAction::GiveUp => game.give_up(),
+            action => game.make_move(action),
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn replays_follow_the_moves_that_were_made(moves in arb_moves()) {
+            let mut game = Game::load_tutorial();
+            for (i, action) in moves.into_iter().enumerate() {
+                game.elapsed_secs = i as f32;
+                play(&mut game, action);
+            }
+
+            let replay = Replay::new(&game);
+            let replayed = replay.game_at(replay.moves.len());
+            prop_assert_eq!(&replayed.current, &game.current);
+            prop_assert_eq!(&replayed.notes, &game.notes);
+            prop_assert_eq!(&replayed.corner_notes, &game.corner_notes);
+            prop_assert_eq!(&replayed.mistakes, &game.mistakes);
+            // Replays don't keep score, so only the boards of checkpoints match.
+            let boards = |game: &Game| -> Vec<_> {
+                game.checkpoints.iter().map(|checkpoint| checkpoint.current.clone()).collect()
+            };
+            prop_assert_eq!(boards(&replayed), boards(&game));
+
+            prop_assert_eq!(&replay.game_at(0).current, &game.start);
+        }
+
+        #[test]
+        fn notes_toggled_back_and_forth_leave_no_moves(x in 0..9u8, y in 0..9u8, n in 1..=9u8, num_toggles in 0..10usize) {
+            let mut game = Game::load_tutorial();
+            let n = NonZeroU8::new(n).unwrap();
+            for _ in 0..num_toggles {
+                game.toggle_note(x, y, n, NoteLayer::Center);
+            }
+
+            prop_assert_eq!(game.moves.len(), num_toggles % 2);
+        }
+
+        #[test]
+        fn placing_the_solution_solves_the_replay(game in arb_game()) {
+            let mut replay = Replay { moves: Vec::new(), ..Replay::new(&game) };
+            for pos in 0..81 {
+                let (x, y) = get_x_and_y_from_pos(pos);
+                if !game.start.has(x, y) {
+                    replay.moves.push(Move {
+                        elapsed_secs: pos as f32,
+                        action: MoveAction::Place {
+                            x,
+                            y,
+                            n: game.solution.get(x, y).unwrap(),
+                            is_hint: false,
+                            show_mistakes: true,
+                        },
+                    });
+                }
+            }
+
+            prop_assert!(replay.game_at(replay.moves.len()).is_solved());
+        }
+
+        #[test]
+        fn timelines_never_go_back(game in arb_game(), times in proptest::collection::vec(0..1000u32, 0..30)) {
+            let mut replay = Replay::new(&game);
+            replay.moves = times
+                .into_iter()
+                .map(|secs| Move { elapsed_secs: secs as f32, action: MoveAction::CheckBoard })
+                .collect();
+
+            let timeline = replay.timeline();
+            prop_assert!(timeline.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+    }
+
+    #[test]
+    fn replays_jump_over_unrecorded_moves_to_the_end() {
+        let mut game = Game::load_tutorial();
+        for i in 0..MAX_NUM_MOVES {
+            game.elapsed_secs = i as f32;
+            game.make_move(MoveAction::CheckBoard);
+        }
+        game.elapsed_secs = MAX_NUM_MOVES as f32;
+        game.toggle_note(0, 0, NonZeroU8::new(1).unwrap(), NoteLayer::Corner);
+        game.make_move(MoveAction::CheckNotes);
+        assert_eq!(game.moves.len(), MAX_NUM_MOVES);
+        assert_eq!(game.num_unrecorded_moves, 2);
+
+        let replay = Replay::new(&game);
+        assert_eq!(replay.num_steps(), MAX_NUM_MOVES + 1);
+        assert!(!replay.is_jump(MAX_NUM_MOVES));
+        assert!(replay.is_jump(replay.num_steps()));
+        assert_eq!(replay.timeline().len(), replay.num_steps());
+
+        let before_jump = replay.game_at(MAX_NUM_MOVES);
+        assert_ne!(before_jump.corner_notes, game.corner_notes);
+        let end = replay.game_at(replay.num_steps());
+        assert_eq!(end.current, game.current);
+        assert_eq!(end.corner_notes, game.corner_notes);
+        assert_eq!(end.elapsed_secs, game.elapsed_secs);
+    }
+}
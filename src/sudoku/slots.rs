@@ -0,0 +1,332 @@
+use super::Game;
+use crate::persistence::{self, Migration, Persistence};
+use crate::ui::report_error;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The directory with the games that are still in progress.
+const SAVED_GAMES_DIR: &str = "games";
+
+/// The directory with the games that were completed.
+const ARCHIVED_GAMES_DIR: &str = "archive";
+
+/// The file the game was saved to before there were multiple save slots.
+const LEGACY_GAME_FILE: &str = "game.json";
+
+/// The file with the slots of the games the player deleted, so the deletions
+/// can be synced to other devices.
+const DELETED_GAMES_FILE: &str = "deleted-games.json";
+
+/// The migrations for the deleted games, see
+/// `persistence::from_versioned_json()`.
+const DELETED_GAMES_MIGRATIONS: &[Migration] = &[];
+
+#[derive(Default, Deserialize, Serialize)]
+struct DeletedGames {
+    slots: BTreeSet<u64>,
+}
+
+/// Returns a new save slot.
+///
+/// Slots are based on the current time, so two games would need to be started
+/// within the same nanosecond to end up in the same slot.
+pub(super) fn new_slot() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default()
+        .max(1)
+}
+
+impl Game {
+    /// Loads the game that was played most recently, or returns
+    /// `Self::default()` if no game is in progress.
+    ///
+    /// Only the most recent game is read, unless it can't be restored.
+    pub fn load(persistence: &Persistence) -> Self {
+        Self::list_saved_slots(persistence)
+            .into_iter()
+            .find_map(|slot| Self::load_slot(persistence, slot))
+            .unwrap_or_default()
+    }
+
+    /// Loads the game in progress in the given save slot.
+    pub fn load_slot(persistence: &Persistence, slot: u64) -> Option<Self> {
+        read_game(
+            persistence,
+            &Path::new(SAVED_GAMES_DIR).join(slot_file_name(slot)),
+        )
+    }
+
+    /// Loads the completed game in the given save slot.
+    pub fn load_archived_slot(persistence: &Persistence, slot: u64) -> Option<Self> {
+        read_game(
+            persistence,
+            &Path::new(ARCHIVED_GAMES_DIR).join(slot_file_name(slot)),
+        )
+    }
+
+    /// Saves the game to its save slot.
+    ///
+    /// Completed games are moved to the archive.
+    pub fn save(&self, persistence: &Persistence) {
+        if self.slot == 0 || self.is_default() {
+            return; // Games without a slot, like the tutorial, aren't stored.
+        }
+
+        let saved_path = Path::new(SAVED_GAMES_DIR).join(slot_file_name(self.slot));
+        let result = if self.is_solved() {
+            write_game(
+                persistence,
+                self,
+                &Path::new(ARCHIVED_GAMES_DIR).join(slot_file_name(self.slot)),
+            )
+            .map(|_| persistence.remove(saved_path))
+        } else {
+            write_game(persistence, self, &saved_path)
+        };
+        result.unwrap_or_else(|err| report_error("Can't save game", err));
+    }
+
+    /// Saves the game if it changed since it was last saved, see
+    /// `Game::is_unsaved`.
+    pub fn save_if_changed(&mut self, persistence: &Persistence) {
+        if self.is_unsaved {
+            self.save(persistence);
+            self.is_unsaved = false;
+        }
+    }
+
+    /// Removes the game from its save slot, whether it's in progress or was
+    /// archived, because the player deleted it.
+    ///
+    /// The deletion is remembered, so other devices can follow suit.
+    pub fn delete(&self, persistence: &Persistence) {
+        Self::delete_slot(persistence, self.slot);
+        Self::remember_deletion(persistence, self.slot);
+    }
+
+    /// Removes the game in the given save slot, without remembering that it
+    /// was deleted, see `Game::delete()`.
+    pub fn delete_slot(persistence: &Persistence, slot: u64) {
+        for dir in [SAVED_GAMES_DIR, ARCHIVED_GAMES_DIR] {
+            persistence.remove(Path::new(dir).join(slot_file_name(slot)));
+        }
+    }
+
+    /// Remembers that the player got rid of the game in the given slot, so
+    /// other devices can follow suit, see `Game::list_deleted()`.
+    pub fn remember_deletion(persistence: &Persistence, slot: u64) {
+        if slot == 0 {
+            return;
+        }
+
+        let mut deleted_games = load_deleted_games(persistence);
+        if deleted_games.slots.insert(slot) {
+            persistence::to_versioned_json(&deleted_games, DELETED_GAMES_MIGRATIONS)
+                .map(|json| persistence.write(DELETED_GAMES_FILE, json))
+                .unwrap_or_else(|err| report_error("Can't record deleted game", err));
+        }
+    }
+
+    /// Names the game, or removes its name if the given one is blank.
+    pub fn rename(&mut self, persistence: &Persistence, name: &str) {
+        let name = name.trim();
+        self.name = (!name.is_empty()).then(|| name.to_owned());
+        self.save(persistence);
+    }
+
+    /// Returns whether there are any games in progress in the save slots.
+    pub fn has_saved_games(persistence: &Persistence) -> bool {
+        persistence.exists(Path::new(LEGACY_GAME_FILE))
+            || !persistence.list(Path::new(SAVED_GAMES_DIR)).is_empty()
+    }
+
+    /// Lists the games in progress, the most recently played first.
+    ///
+    /// Every game is read, so prefer `Game::list_saved_slots()` when only
+    /// some of them are needed.
+    pub fn list_saved(persistence: &Persistence) -> Vec<Self> {
+        Self::list_saved_slots(persistence)
+            .into_iter()
+            .filter_map(|slot| Self::load_slot(persistence, slot))
+            .collect()
+    }
+
+    /// Lists the completed games, the most recently completed first.
+    pub fn list_archived(persistence: &Persistence) -> Vec<Self> {
+        Self::list_archived_slots(persistence)
+            .into_iter()
+            .filter_map(|slot| Self::load_archived_slot(persistence, slot))
+            .collect()
+    }
+
+    /// Lists the slots of the games in progress, the most recently played
+    /// first, without reading the games.
+    pub fn list_saved_slots(persistence: &Persistence) -> Vec<u64> {
+        migrate_legacy_game(persistence);
+        list_slots(persistence, SAVED_GAMES_DIR)
+    }
+
+    /// Lists the slots of the completed games, the most recently completed
+    /// first, without reading the games.
+    pub fn list_archived_slots(persistence: &Persistence) -> Vec<u64> {
+        list_slots(persistence, ARCHIVED_GAMES_DIR)
+    }
+
+    /// Lists the slots of the games the player deleted, see `Game::delete()`.
+    pub fn list_deleted(persistence: &Persistence) -> BTreeSet<u64> {
+        load_deleted_games(persistence).slots
+    }
+
+    /// Returns the share of the open cells that have been filled in
+    /// correctly, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        let open_cells: Vec<usize> = (0..81)
+            .filter(|&pos| self.start.get_by_pos(pos).is_none())
+            .collect();
+        if open_cells.is_empty() {
+            return 1.;
+        }
+
+        let num_correct = open_cells
+            .iter()
+            .filter(|&&pos| {
+                self.current.get_by_pos(pos).is_some()
+                    && self.current.get_by_pos(pos) == self.solution.get_by_pos(pos)
+            })
+            .count();
+        num_correct as f32 / open_cells.len() as f32
+    }
+}
+
+/// Moves the game that was saved before there were multiple save slots into a
+/// slot of its own.
+fn migrate_legacy_game(persistence: &Persistence) {
+    let legacy_path = Path::new(LEGACY_GAME_FILE);
+    if !persistence.exists(legacy_path) {
+        return;
+    }
+
+    if let Some(mut game) = read_game(persistence, legacy_path) {
+        game.slot = new_slot();
+        game.save(persistence);
+        persistence.remove(legacy_path);
+    }
+}
+
+fn load_deleted_games(persistence: &Persistence) -> DeletedGames {
+    persistence.load_or_default(
+        Path::new(DELETED_GAMES_FILE),
+        |bytes| persistence::from_versioned_json(bytes, DELETED_GAMES_MIGRATIONS),
+        "Can't load deleted games",
+    )
+}
+
+/// Lists the slots of the games in the given directory, the most recently
+/// modified first.
+fn list_slots(persistence: &Persistence, dir_name: &str) -> Vec<u64> {
+    let mut slots: Vec<(SystemTime, u64)> = persistence
+        .list(Path::new(dir_name))
+        .into_iter()
+        .filter_map(|(path, modified)| {
+            let slot = path.file_stem()?.to_str()?.parse().ok()?;
+            Some((modified, slot))
+        })
+        .collect();
+    slots.sort_by(|(a, _), (b, _)| b.cmp(a));
+    slots.into_iter().map(|(_, slot)| slot).collect()
+}
+
+fn slot_file_name(slot: u64) -> String {
+    format!("{slot}.json")
+}
+
+/// Reads the game from the given path.
+///
+/// Games that can't be restored are set aside by `Persistence::load()`, and
+/// the player is told either way.
+fn read_game(persistence: &Persistence, path: &Path) -> Option<Game> {
+    persistence
+        .load(path, Game::from_json)
+        .map_err(|err| persistence.report_load_error(path, "Can't restore a saved game", err))
+        .ok()
+}
+
+fn write_game(persistence: &Persistence, game: &Game, path: &Path) -> Result<(), anyhow::Error> {
+    game.to_json().map(|json| persistence.write(path, json))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use crate::sudoku::strategies::arb_game;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn progress_goes_from_the_start_to_the_solution(game in arb_game()) {
+            let progress = game.progress();
+            prop_assert!((0. ..=1.).contains(&progress));
+
+            let fresh = Game {
+                start: game.start.clone(),
+                current: game.start.clone(),
+                solution: game.solution.clone(),
+                ..Game::default()
+            };
+            prop_assert_eq!(fresh.progress(), 0.);
+
+            let solved = Game {
+                start: game.start.clone(),
+                current: game.solution.clone(),
+                solution: game.solution.clone(),
+                ..Game::default()
+            };
+            prop_assert_eq!(solved.progress(), 1.);
+        }
+    }
+
+    #[test]
+    fn only_games_deleted_by_the_player_are_remembered() {
+        let persistence = Persistence::new(MemoryStorage::default());
+        for slot in [1, 2] {
+            Game {
+                slot,
+                ..Game::load_tutorial()
+            }
+            .save(&persistence);
+        }
+
+        Game::load_slot(&persistence, 1)
+            .unwrap()
+            .delete(&persistence);
+        Game::delete_slot(&persistence, 2);
+        assert!(Game::list_saved(&persistence).is_empty());
+        assert_eq!(Game::list_deleted(&persistence), BTreeSet::from([1]));
+    }
+
+    #[test]
+    fn the_most_recent_game_that_can_be_restored_is_loaded() {
+        let persistence = Persistence::new(MemoryStorage::default());
+        for slot in [1, 2] {
+            let game = Game {
+                slot,
+                ..Game::load_tutorial()
+            };
+            let path = Path::new(SAVED_GAMES_DIR).join(slot_file_name(slot));
+            persistence
+                .write_now(&path, &game.to_json().unwrap())
+                .unwrap();
+        }
+        let broken_path = Path::new(SAVED_GAMES_DIR).join(slot_file_name(3));
+        persistence.write_now(&broken_path, b"{").unwrap();
+
+        assert_eq!(Game::list_saved_slots(&persistence), vec![3, 2, 1]);
+        assert_eq!(Game::load(&persistence).slot, 2);
+        assert_eq!(Game::list_saved_slots(&persistence), vec![2, 1]);
+    }
+}
@@ -15,9 +15,57 @@ pub enum Difficulty {
     Expert,
 }
 
+impl Difficulty {
+    /// Returns the difficulty of a Sudoku that requires the given technique,
+    /// but nothing harder.
+    pub fn for_technique(technique: Technique) -> Self {
+        match technique {
+            Technique::OnlyNumber => Self::Trivial,
+            Technique::LoneRanger => Self::Easy,
+            Technique::Twins | Technique::Triplets => Self::Medium,
+            Technique::HiddenTwins | Technique::HiddenTriplets => Self::Advanced,
+        }
+    }
+}
+
+/// The logical techniques known to the solver, ordered from the easiest to the
+/// hardest. The solver always attempts the easiest technique first.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Technique {
+    OnlyNumber,
+    LoneRanger,
+    Twins,
+    Triplets,
+    HiddenTwins,
+    HiddenTriplets,
+}
+
 pub struct SolverResult {
     pub solution: Sudoku,
     pub difficulty: Difficulty,
+
+    /// The hardest technique the solver had to apply to find the solution.
+    ///
+    /// This is `None` if the Sudoku was already solved. If the difficulty is
+    /// `Expert`, the solver had to resort to brute force after applying this
+    /// technique.
+    pub technique: Option<Technique>,
+
+    /// The cells in the order the solver filled them in.
+    pub steps: Vec<SolveStep>,
+}
+
+/// A cell filled in by the solver.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SolveStep {
+    pub x: u8,
+    pub y: u8,
+    pub n: NonZeroU8,
+
+    /// The hardest technique the solver applied since it filled in the
+    /// previous cell, or `None` if it had to guess the number.
+    pub technique: Option<Technique>,
 }
 
 /// Rates a Sudoku by difficulty level.
@@ -29,17 +77,46 @@ pub fn rate_difficulty(sudoku: Sudoku) -> Option<Difficulty> {
 
 /// Solves the Sudoku, if possible, and returns one of its solutions, along with
 /// the rated difficulty.
-pub fn solve(mut sudoku: Sudoku) -> Option<SolverResult> {
+pub fn solve(sudoku: Sudoku) -> Option<SolverResult> {
+    if sudoku.has_conflicts() {
+        return None;
+    }
+
+    solve_without_conflicts(sudoku)
+}
+
+/// Solves a Sudoku that's known to be free of conflicts, see `solve()`.
+///
+/// Numbers that are filled in after checking `Sudoku::may_set()` never cause
+/// conflicts, so the brute force search and the generator don't need to check
+/// for them over and over again.
+pub(super) fn solve_without_conflicts(mut sudoku: Sudoku) -> Option<SolverResult> {
     let mut notes = Notes::from_sudoku(&sudoku);
 
     let mut difficulty = Difficulty::Trivial;
+    let mut technique = None;
+    let mut steps = Vec::new();
+    let mut step_technique = None;
     'outer: while notes.has_notes() {
+        // Give up as soon as there's an open cell that can't be filled in,
+        // instead of letting brute force try every combination for the others:
+        if (0..81).any(|pos| sudoku.get_by_pos(pos).is_none() && !notes.has_some_number(pos)) {
+            return None;
+        }
+
         // Fill in any places that only have a single number as the solution:
         for pos in 0..81 {
             if let Some(n) = notes.get_only_number(pos) {
                 let (x, y) = get_x_and_y_from_pos(pos);
                 sudoku = sudoku.set(x, y, n);
                 notes.remove_all_notes_affected_by_set(x, y, n);
+                technique = std::cmp::max(technique, Some(Technique::OnlyNumber));
+                steps.push(SolveStep {
+                    x,
+                    y,
+                    n,
+                    technique: std::cmp::max(step_technique.take(), Some(Technique::OnlyNumber)),
+                });
                 continue 'outer;
             }
         }
@@ -52,6 +129,13 @@ pub fn solve(mut sudoku: Sudoku) -> Option<SolverResult> {
                 let (x, y) = get_x_and_y_from_pos(pos);
                 sudoku = sudoku.set(x, y, n);
                 notes.remove_all_notes_affected_by_set(x, y, n);
+                technique = std::cmp::max(technique, Some(Technique::LoneRanger));
+                steps.push(SolveStep {
+                    x,
+                    y,
+                    n,
+                    technique: std::cmp::max(step_technique.take(), Some(Technique::LoneRanger)),
+                });
                 continue 'outer;
             }
         }
@@ -62,6 +146,8 @@ pub fn solve(mut sudoku: Sudoku) -> Option<SolverResult> {
         for pos in 0..81 {
             if let Some(twins) = notes.find_twins(pos) {
                 if notes.remove_all_notes_affected_by_twins(twins) {
+                    technique = std::cmp::max(technique, Some(Technique::Twins));
+                    step_technique = std::cmp::max(step_technique, Some(Technique::Twins));
                     continue 'outer;
                 }
             }
@@ -71,6 +157,8 @@ pub fn solve(mut sudoku: Sudoku) -> Option<SolverResult> {
         for pos in 0..81 {
             if let Some(triplets) = notes.find_triplets(pos) {
                 if notes.remove_all_notes_affected_by_triplets(triplets) {
+                    technique = std::cmp::max(technique, Some(Technique::Triplets));
+                    step_technique = std::cmp::max(step_technique, Some(Technique::Triplets));
                     continue 'outer;
                 }
             }
@@ -82,6 +170,8 @@ pub fn solve(mut sudoku: Sudoku) -> Option<SolverResult> {
         for pos in 0..81 {
             if let Some(twins) = notes.find_hidden_twins(pos) {
                 if notes.remove_all_notes_affected_by_twins(twins) {
+                    technique = std::cmp::max(technique, Some(Technique::HiddenTwins));
+                    step_technique = std::cmp::max(step_technique, Some(Technique::HiddenTwins));
                     continue 'outer;
                 }
             }
@@ -91,25 +181,105 @@ pub fn solve(mut sudoku: Sudoku) -> Option<SolverResult> {
         for pos in 0..81 {
             if let Some(triplets) = notes.find_hidden_triplets(pos) {
                 if notes.remove_all_notes_affected_by_triplets(triplets) {
+                    technique = std::cmp::max(technique, Some(Technique::HiddenTriplets));
+                    step_technique = std::cmp::max(step_technique, Some(Technique::HiddenTriplets));
                     continue 'outer;
                 }
             }
         }
 
         // Brute force is our last resort:
-        return solve_through_brute_force(sudoku).map(|solution| SolverResult {
-            solution,
-            difficulty: Difficulty::Expert,
+        return solve_through_brute_force(sudoku).map(|(solution, guessed_steps)| {
+            steps.extend(guessed_steps);
+            SolverResult {
+                solution,
+                difficulty: Difficulty::Expert,
+                technique,
+                steps,
+            }
         });
     }
 
     sudoku.is_solved().then_some(SolverResult {
         solution: sudoku,
         difficulty,
+        technique,
+        steps,
     })
 }
 
-fn solve_through_brute_force(sudoku: Sudoku) -> Option<Sudoku> {
+/// Finds up to `limit` distinct solutions to the Sudoku.
+///
+/// Unlike [`solve()`], this doesn't rate the difficulty, but keeps searching
+/// after the first solution is found, so it can tell whether a Sudoku has a
+/// unique solution, and if not, show how the solutions differ.
+pub fn find_solutions(sudoku: &Sudoku, limit: usize) -> Vec<Sudoku> {
+    let mut solutions = Vec::new();
+    if limit > 0 && !sudoku.has_conflicts() {
+        collect_solutions(sudoku.clone(), limit, &mut solutions);
+    }
+    solutions
+}
+
+fn collect_solutions(sudoku: Sudoku, limit: usize, solutions: &mut Vec<Sudoku>) {
+    let mut rows = [0u16; 9];
+    let mut columns = [0u16; 9];
+    let mut blocks = [0u16; 9];
+    for pos in 0..81 {
+        if let Some(n) = sudoku.get_by_pos(pos) {
+            let (x, y) = get_x_and_y_from_pos(pos);
+            rows[y as usize] |= 1 << n.get();
+            columns[x as usize] |= 1 << n.get();
+            blocks[get_block_index(x, y)] |= 1 << n.get();
+        }
+    }
+
+    // Continue with the open cell that has the fewest candidates, so we find
+    // dead ends as early as possible:
+    let mut open_cell: Option<(u8, u8, u16)> = None;
+    for pos in 0..81 {
+        if sudoku.get_by_pos(pos).is_some() {
+            continue;
+        }
+
+        let (x, y) = get_x_and_y_from_pos(pos);
+        let candidates =
+            !(rows[y as usize] | columns[x as usize] | blocks[get_block_index(x, y)]) & 0x3fe;
+        if candidates == 0 {
+            return; // Dead end.
+        }
+
+        if open_cell.is_none_or(|(.., best)| candidates.count_ones() < best.count_ones()) {
+            open_cell = Some((x, y, candidates));
+        }
+    }
+
+    let Some((x, y, candidates)) = open_cell else {
+        solutions.push(sudoku); // No open cells left, so it's solved.
+        return;
+    };
+
+    for n in 1..=9 {
+        if candidates & (1 << n) != 0 {
+            collect_solutions(
+                sudoku.set(x, y, NonZeroU8::new(n).unwrap()),
+                limit,
+                solutions,
+            );
+            if solutions.len() >= limit {
+                return;
+            }
+        }
+    }
+}
+
+fn get_block_index(x: u8, y: u8) -> usize {
+    (3 * (y / 3) + x / 3) as usize
+}
+
+/// Returns the solution, along with the steps taken from the first guess
+/// onwards.
+fn solve_through_brute_force(sudoku: Sudoku) -> Option<(Sudoku, Vec<SolveStep>)> {
     // Search for a cell without any number:
     for y in 0..9 {
         for x in 0..9 {
@@ -122,8 +292,15 @@ fn solve_through_brute_force(sudoku: Sudoku) -> Option<Sudoku> {
             for n in 1..=9 {
                 let n = NonZeroU8::new(n).unwrap();
                 if sudoku.may_set(x, y, n) {
-                    if let Some(result) = solve(sudoku.set(x, y, n)) {
-                        return Some(result.solution);
+                    if let Some(result) = solve_without_conflicts(sudoku.set(x, y, n)) {
+                        let guess = SolveStep {
+                            x,
+                            y,
+                            n,
+                            technique: None,
+                        };
+                        let steps = std::iter::once(guess).chain(result.steps).collect();
+                        return Some((result.solution, steps));
                     }
                 }
             }
@@ -133,5 +310,76 @@ fn solve_through_brute_force(sudoku: Sudoku) -> Option<Sudoku> {
     }
 
     // We didn't find any open cell, so it must already have been solved.
-    Some(sudoku)
+    Some((sudoku, Vec::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sudoku::strategies::arb_puzzle;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn solutions_respect_may_set((_, puzzle) in arb_puzzle()) {
+            let SolverResult { solution, .. } = solve(puzzle.clone()).unwrap();
+
+            for y in 0..9 {
+                for x in 0..9 {
+                    let n = solution.get(x, y).unwrap();
+                    prop_assert!(solution.may_set(x, y, n));
+                    if let Some(given) = puzzle.get(x, y) {
+                        prop_assert_eq!(n, given);
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn conflicting_numbers_have_no_solution((solution, puzzle) in arb_puzzle()) {
+            let n = solution.get(0, 0).unwrap();
+            let puzzle = puzzle.set(0, 0, n).set(1, 0, n);
+
+            prop_assert!(puzzle.has_conflicts());
+            prop_assert!(solve(puzzle.clone()).is_none());
+            prop_assert!(puzzle.find_unique_solution().is_none());
+        }
+
+        #[test]
+        fn all_solutions_are_distinct_and_valid((_, puzzle) in arb_puzzle()) {
+            let solutions = find_solutions(&puzzle, 3);
+            prop_assert!(!solutions.is_empty());
+            prop_assert_eq!(solutions.len() == 1, puzzle.find_unique_solution().is_some());
+
+            for (i, solution) in solutions.iter().enumerate() {
+                prop_assert!(solution.is_solved());
+                prop_assert!(!solutions[..i].contains(solution));
+                for pos in 0..81 {
+                    if let Some(n) = puzzle.get_by_pos(pos) {
+                        prop_assert_eq!(solution.get_by_pos(pos), Some(n));
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn steps_fill_in_every_open_cell((_, puzzle) in arb_puzzle()) {
+            let SolverResult { solution, steps, .. } = solve(puzzle.clone()).unwrap();
+
+            let mut sudoku = puzzle;
+            for step in steps {
+                prop_assert!(!sudoku.has(step.x, step.y));
+                prop_assert_eq!(solution.get(step.x, step.y), Some(step.n));
+                sudoku = sudoku.set(step.x, step.y, step.n);
+            }
+            prop_assert!(sudoku.is_solved());
+        }
+
+        #[test]
+        fn unique_solutions_are_found((solution, puzzle) in arb_puzzle()) {
+            if let Some(unique_solution) = puzzle.find_unique_solution() {
+                prop_assert_eq!(unique_solution, solution);
+            }
+        }
+    }
 }
@@ -0,0 +1,233 @@
+//! Strategies for generating the inputs of property tests.
+
+use super::{
+    get_x_and_y_from_pos, CellColors, Checkpoint, Difficulty, Game, Move, MoveAction, NoteLayer,
+    Notes, Sudoku, Symmetry, NUM_CELL_COLORS,
+};
+use proptest::collection::vec;
+use proptest::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::num::NonZeroU8;
+use std::sync::{LazyLock, OnceLock};
+
+static TUTORIAL_SOLUTION: LazyLock<Sudoku> = LazyLock::new(|| {
+    Sudoku::tutorial()
+        .find_unique_solution()
+        .expect("The tutorial should have a unique solution")
+});
+
+pub fn arb_difficulty() -> impl Strategy<Value = Difficulty> {
+    prop_oneof![
+        Just(Difficulty::Trivial),
+        Just(Difficulty::Easy),
+        Just(Difficulty::Medium),
+        Just(Difficulty::Advanced),
+        Just(Difficulty::Expert),
+    ]
+}
+
+pub fn arb_symmetry() -> impl Strategy<Value = Symmetry> {
+    any::<u64>().prop_map(|seed| Symmetry::random(&mut StdRng::seed_from_u64(seed)))
+}
+
+/// Returns a solved Sudoku, which is a variant of the tutorial's solution.
+pub fn arb_solution() -> impl Strategy<Value = Sudoku> {
+    arb_symmetry().prop_map(|symmetry| TUTORIAL_SOLUTION.transformed(&symmetry))
+}
+
+/// Returns a solved Sudoku, along with a puzzle that is created by digging a
+/// random selection of cells from it.
+///
+/// The puzzle is guaranteed to be solvable, but may have multiple solutions.
+pub fn arb_puzzle() -> impl Strategy<Value = (Sudoku, Sudoku)> {
+    (arb_solution(), 0.2..0.8f64)
+        .prop_flat_map(|(solution, density)| {
+            (Just(solution), vec(prop::bool::weighted(density), 81))
+        })
+        .prop_map(|(solution, keep)| {
+            let mut puzzle = solution.clone();
+            for (pos, keep) in keep.into_iter().enumerate() {
+                if !keep {
+                    puzzle = puzzle.unset_by_pos(pos);
+                }
+            }
+            (solution, puzzle)
+        })
+}
+
+/// Returns notes with arbitrary numbers in every cell.
+pub fn arb_notes() -> impl Strategy<Value = Notes> {
+    vec(any::<u16>().prop_map(|cell| cell & 0b11_1111_1110), 81).prop_map(|cells| Notes {
+        cells: cells.try_into().unwrap(),
+    })
+}
+
+/// Returns colors with an arbitrary selection of the cells painted.
+pub fn arb_colors() -> impl Strategy<Value = CellColors> {
+    vec(prop::option::of(1..=NUM_CELL_COLORS), 81).prop_map(|cells| CellColors {
+        cells: std::array::from_fn(|pos| cells[pos].and_then(NonZeroU8::new)),
+    })
+}
+
+/// Returns a sequence of moves on arbitrary cells of the board.
+pub fn arb_moves() -> impl Strategy<Value = Vec<MoveAction>> {
+    let cell = (0..9u8, 0..9u8);
+    let n = (1..=9u8).prop_map(|n| NonZeroU8::new(n).unwrap());
+    let layer = prop_oneof![Just(NoteLayer::Center), Just(NoteLayer::Corner)];
+
+    let action = prop_oneof![
+        4 => (cell.clone(), n.clone(), any::<bool>(), any::<bool>()).prop_map(
+            |((x, y), n, is_hint, show_mistakes)| MoveAction::Place {
+                x,
+                y,
+                n,
+                is_hint,
+                show_mistakes,
+            }
+        ),
+        1 => cell.clone().prop_map(|(x, y)| MoveAction::Clear { x, y }),
+        4 => (cell.clone(), n.clone(), layer.clone(), any::<bool>()).prop_map(
+            |((x, y), n, layer, is_set)| MoveAction::Note {
+                x,
+                y,
+                n,
+                layer,
+                is_set,
+            }
+        ),
+        1 => (vec(cell.clone(), 1..5), n, layer, any::<bool>()).prop_map(
+            |(cells, n, layer, is_set)| MoveAction::Notes {
+                cells,
+                n,
+                layer,
+                is_set,
+            }
+        ),
+        1 => any::<bool>().prop_map(|only_missing| MoveAction::FillCandidates { only_missing }),
+        1 => cell.clone().prop_map(|(x, y)| MoveAction::Hint { x, y }),
+        1 => Just(MoveAction::CheckNotes),
+        1 => Just(MoveAction::CheckBoard),
+        1 => cell.prop_map(|(x, y)| MoveAction::Reveal { x, y }),
+        1 => Just(MoveAction::AddCheckpoint),
+        1 => Just(MoveAction::CommitBranch),
+        1 => Just(MoveAction::DiscardBranch),
+        1 => Just(MoveAction::Restart),
+        1 => Just(MoveAction::GiveUp),
+    ];
+
+    vec(action, 0..40)
+}
+
+/// Returns a game based on a variant of the tutorial, with a random selection
+/// of the cells filled in.
+pub fn arb_game() -> impl Strategy<Value = Game> {
+    (
+        arb_symmetry(),
+        vec(any::<bool>(), 81),
+        arb_notes(),
+        arb_notes(),
+        arb_notes(),
+        arb_colors(),
+        arb_difficulty(),
+        any::<u32>(),
+        0..1_000_000u32,
+        (any::<u32>(), any::<[u32; 9]>(), any::<u32>(), any::<u32>()),
+        (
+            any::<u32>(),
+            any::<u32>(),
+            any::<bool>(),
+            arb_moves(),
+            1..u64::MAX,
+            proptest::option::of("[a-zA-Z0-9 ]{0,20}"),
+        ),
+        vec(
+            (
+                vec(any::<bool>(), 81),
+                arb_notes(),
+                arb_colors(),
+                any::<u32>(),
+            ),
+            0..3,
+        ),
+    )
+        .prop_map(
+            |(
+                symmetry,
+                filled,
+                notes,
+                corner_notes,
+                mistakes,
+                colors,
+                difficulty,
+                score,
+                elapsed_quarter_secs,
+                (num_mistakes, num_mistakes_by_number, num_hints, num_checks),
+                (num_reveals, num_restarts, gave_up, moves, slot, name),
+                checkpoints,
+            )| {
+                let start = Sudoku::tutorial().transformed(&symmetry);
+                let solution = TUTORIAL_SOLUTION.transformed(&symmetry);
+
+                let fill = |filled: Vec<bool>| {
+                    let mut current = start.clone();
+                    for (pos, filled) in filled.into_iter().enumerate() {
+                        if filled {
+                            let (x, y) = get_x_and_y_from_pos(pos);
+                            current = current.set(x, y, solution.get(x, y).unwrap());
+                        }
+                    }
+                    current
+                };
+
+                let current = fill(filled);
+                let checkpoints = checkpoints
+                    .into_iter()
+                    .map(|(filled, notes, colors, score)| Checkpoint {
+                        current: fill(filled),
+                        corner_notes: notes.clone(),
+                        mistakes: notes.clone(),
+                        notes,
+                        colors,
+                        score,
+                    })
+                    .collect();
+
+                Game {
+                    start,
+                    solution,
+                    current,
+                    notes,
+                    corner_notes,
+                    mistakes,
+                    colors,
+                    checkpoints,
+                    difficulty,
+                    score,
+                    // Quarter seconds are exactly representable, so they
+                    // survive a round-trip through JSON unharmed.
+                    elapsed_secs: elapsed_quarter_secs as f32 / 4.,
+                    num_mistakes,
+                    num_mistakes_by_number,
+                    num_hints,
+                    num_checks,
+                    num_reveals,
+                    num_restarts,
+                    gave_up,
+                    moves: moves
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, action)| Move {
+                            elapsed_secs: i as f32 / 4.,
+                            action,
+                        })
+                        .collect(),
+                    num_unrecorded_moves: 0,
+                    slot,
+                    name,
+                    puzzle_id: OnceLock::new(),
+                    is_unsaved: false,
+                }
+            },
+        )
+}
@@ -0,0 +1,412 @@
+use super::math::get_pos;
+use super::Sudoku;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::num::NonZeroU8;
+
+const IDENTITY: [u8; 9] = [0, 1, 2, 3, 4, 5, 6, 7, 8];
+
+/// A transform that may be applied to a Sudoku without affecting its validity.
+///
+/// Any combination of digit relabeling, band and stack swaps, row and column
+/// swaps within a band or stack, transposition and rotation can be expressed
+/// as a single `Symmetry`. Transforms are combined by chaining the builder
+/// methods, where every method is applied after the ones before it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Symmetry {
+    /// Maps every digit (minus one) to the digit (minus one) it is relabeled to.
+    digits: [u8; 9],
+
+    /// For every row in the result, the row in the source it is taken from.
+    rows: [u8; 9],
+
+    /// For every column in the result, the column in the source it is taken
+    /// from.
+    columns: [u8; 9],
+
+    /// Whether the source is transposed before rows and columns are taken.
+    transpose: bool,
+}
+
+impl Default for Symmetry {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Symmetry {
+    /// Returns the transform that leaves every Sudoku unchanged.
+    pub fn identity() -> Self {
+        Self {
+            digits: IDENTITY,
+            rows: IDENTITY,
+            columns: IDENTITY,
+            transpose: false,
+        }
+    }
+
+    /// Returns a random transform, which may be used to turn a single Sudoku
+    /// into a variant that looks different, but is just as difficult.
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let mut digits = IDENTITY;
+        digits.shuffle(rng);
+
+        Self {
+            digits,
+            rows: get_random_line_order(rng),
+            columns: get_random_line_order(rng),
+            transpose: rng.gen_bool(0.5),
+        }
+    }
+
+    /// Swaps the given digits.
+    pub fn swap_digits(mut self, a: NonZeroU8, b: NonZeroU8) -> Self {
+        for digit in &mut self.digits {
+            if *digit == a.get() - 1 {
+                *digit = b.get() - 1;
+            } else if *digit == b.get() - 1 {
+                *digit = a.get() - 1;
+            }
+        }
+        self
+    }
+
+    /// Swaps two bands, which are the horizontal groups of three blocks.
+    pub fn swap_bands(mut self, a: u8, b: u8) -> Self {
+        swap_groups(&mut self.rows, a, b);
+        self
+    }
+
+    /// Swaps two stacks, which are the vertical groups of three blocks.
+    pub fn swap_stacks(mut self, a: u8, b: u8) -> Self {
+        swap_groups(&mut self.columns, a, b);
+        self
+    }
+
+    /// Swaps rows `a` and `b` within the given band. Row indices are relative
+    /// to the band, so they range from 0 through 2.
+    pub fn swap_rows_in_band(mut self, band: u8, a: u8, b: u8) -> Self {
+        debug_assert!(band < 3 && a < 3 && b < 3);
+        self.rows
+            .swap((3 * band + a) as usize, (3 * band + b) as usize);
+        self
+    }
+
+    /// Swaps columns `a` and `b` within the given stack. Column indices are
+    /// relative to the stack, so they range from 0 through 2.
+    pub fn swap_columns_in_stack(mut self, stack: u8, a: u8, b: u8) -> Self {
+        debug_assert!(stack < 3 && a < 3 && b < 3);
+        self.columns
+            .swap((3 * stack + a) as usize, (3 * stack + b) as usize);
+        self
+    }
+
+    /// Mirrors the Sudoku along its main diagonal.
+    pub fn transpose(self) -> Self {
+        Self {
+            digits: self.digits,
+            rows: self.columns,
+            columns: self.rows,
+            transpose: !self.transpose,
+        }
+    }
+
+    /// Rotates the Sudoku by 90 degrees clockwise.
+    pub fn rotate(self) -> Self {
+        let mut rotated = self.transpose();
+        rotated.columns.reverse();
+        rotated
+    }
+
+    /// Returns a new Sudoku with the transform applied to the given one.
+    pub fn apply(&self, sudoku: &Sudoku) -> Sudoku {
+        let mut result = Sudoku::new();
+        for y in 0..9 {
+            for x in 0..9 {
+                let source_x = self.columns[x as usize];
+                let source_y = self.rows[y as usize];
+                let cell = if self.transpose {
+                    sudoku.get(source_y, source_x)
+                } else {
+                    sudoku.get(source_x, source_y)
+                };
+
+                if let Some(n) = cell {
+                    let n = NonZeroU8::new(self.digits[n.get() as usize - 1] + 1).unwrap();
+                    result = result.set(x, y, n);
+                }
+            }
+        }
+
+        result
+    }
+}
+
+impl Sudoku {
+    /// Returns a new Sudoku with the given transform applied.
+    pub fn transformed(&self, symmetry: &Symmetry) -> Self {
+        symmetry.apply(self)
+    }
+
+    /// Returns the canonical form of this Sudoku.
+    ///
+    /// All Sudokus that can be transformed into one another using a
+    /// [`Symmetry`] share the same canonical form. It is the lexicographically
+    /// smallest of all their variants, after relabeling the digits in order of
+    /// appearance.
+    pub fn canonical(&self) -> Self {
+        let mut best: Option<[u8; 81]> = None;
+        for transpose in [false, true] {
+            let mut source = [0; 81];
+            for y in 0..9 {
+                for x in 0..9 {
+                    let cell = if transpose {
+                        self.get(y, x)
+                    } else {
+                        self.get(x, y)
+                    };
+                    source[get_pos(x, y)] = cell.map(NonZeroU8::get).unwrap_or_default();
+                }
+            }
+
+            for columns in get_all_line_orders() {
+                let mut search = CanonicalSearch {
+                    source: &source,
+                    columns: &columns,
+                    current: [0; 81],
+                    best: &mut best,
+                };
+                search.search_rows(0, 0, [false; 9], [0; 10], 1);
+            }
+        }
+
+        let mut canonical = Sudoku::new();
+        if let Some(best) = best {
+            for (pos, value) in best.into_iter().enumerate() {
+                canonical.cells[pos] = NonZeroU8::new(value);
+            }
+        }
+        canonical
+    }
+
+    /// Returns a hash of the canonical form of this Sudoku, which can be used
+    /// to recognize Sudokus that are merely disguised copies of one another.
+    ///
+    /// The hash is stable across platforms and releases, so it is safe to
+    /// persist.
+    pub fn canonical_hash(&self) -> u64 {
+        self.canonical().stable_hash()
+    }
+
+    /// Returns whether this Sudoku can be transformed into the other one.
+    pub fn is_equivalent_to(&self, other: &Self) -> bool {
+        self.canonical() == other.canonical()
+    }
+
+    /// Returns an FNV-1a hash of the cells.
+    fn stable_hash(&self) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
+
+        self.cells.iter().fold(FNV_OFFSET_BASIS, |hash, cell| {
+            (hash ^ cell.map(NonZeroU8::get).unwrap_or_default() as u64).wrapping_mul(FNV_PRIME)
+        })
+    }
+}
+
+/// Depth-first search for the row order that produces the smallest variant for
+/// a given transposition and column order.
+struct CanonicalSearch<'a> {
+    source: &'a [u8; 81],
+    columns: &'a [u8; 9],
+    current: [u8; 81],
+    best: &'a mut Option<[u8; 81]>,
+}
+
+impl CanonicalSearch<'_> {
+    /// Fills in row `y` of `current` with every row that may be put there, and
+    /// recurses into the next row.
+    ///
+    /// `band_offset` is the first row of the band in the source from which the
+    /// row above was taken. `labels` maps digits to their relabeled value,
+    /// where `0` means the digit has not been encountered yet.
+    fn search_rows(
+        &mut self,
+        y: usize,
+        band_offset: usize,
+        used_rows: [bool; 9],
+        labels: [u8; 10],
+        next_label: u8,
+    ) {
+        if y == 9 {
+            if self.best.is_none_or(|best| self.current < best) {
+                *self.best = Some(self.current);
+            }
+            return;
+        }
+
+        // The first row of every band may be taken from any unused band, while
+        // the other rows must be taken from the same band as the row above.
+        let candidates = if y.is_multiple_of(3) {
+            0..9
+        } else {
+            band_offset..band_offset + 3
+        };
+
+        for source_y in candidates {
+            if used_rows[source_y] {
+                continue;
+            }
+
+            let mut labels = labels;
+            let mut next_label = next_label;
+            for x in 0..9 {
+                let value = self.source[source_y * 9 + self.columns[x] as usize];
+                self.current[y * 9 + x] = if value == 0 {
+                    0
+                } else {
+                    if labels[value as usize] == 0 {
+                        labels[value as usize] = next_label;
+                        next_label += 1;
+                    }
+                    labels[value as usize]
+                };
+            }
+
+            // Prune the search as soon as we know we can't beat the best
+            // variant found so far.
+            let end = (y + 1) * 9;
+            if let Some(best) = self.best {
+                if self.current[..end] > best[..end] {
+                    continue;
+                }
+            }
+
+            let mut used_rows = used_rows;
+            used_rows[source_y] = true;
+            let band_offset = source_y - source_y % 3;
+            self.search_rows(y + 1, band_offset, used_rows, labels, next_label);
+        }
+    }
+}
+
+/// Swaps two groups of three lines each.
+fn swap_groups(lines: &mut [u8; 9], a: u8, b: u8) {
+    debug_assert!(a < 3 && b < 3);
+    for i in 0..3 {
+        lines.swap((3 * a + i) as usize, (3 * b + i) as usize);
+    }
+}
+
+/// Returns a random order of lines that keeps lines in the same band or stack
+/// together.
+fn get_random_line_order(rng: &mut impl Rng) -> [u8; 9] {
+    let mut groups = [0, 1, 2];
+    groups.shuffle(rng);
+
+    let mut order = [0; 9];
+    for (i, group) in groups.into_iter().enumerate() {
+        let mut lines = [3 * group, 3 * group + 1, 3 * group + 2];
+        lines.shuffle(rng);
+        order[3 * i..3 * i + 3].copy_from_slice(&lines);
+    }
+    order
+}
+
+/// Returns all 1296 orders of lines that keep lines in the same band or stack
+/// together.
+fn get_all_line_orders() -> Vec<[u8; 9]> {
+    const PERMUTATIONS: [[u8; 3]; 6] = [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ];
+
+    let mut orders = Vec::with_capacity(1296);
+    for groups in PERMUTATIONS {
+        for first in PERMUTATIONS {
+            for second in PERMUTATIONS {
+                for third in PERMUTATIONS {
+                    let mut order = [0; 9];
+                    for (i, lines) in [first, second, third].into_iter().enumerate() {
+                        for (j, line) in lines.into_iter().enumerate() {
+                            order[3 * i + j] = 3 * groups[i] + line;
+                        }
+                    }
+                    orders.push(order);
+                }
+            }
+        }
+    }
+    orders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sudoku::math::get_x_and_y_from_pos;
+    use crate::sudoku::strategies::{arb_puzzle, arb_solution, arb_symmetry};
+    use proptest::prelude::*;
+    use std::time::{Duration, Instant};
+
+    proptest! {
+        #[test]
+        fn transformed_solutions_remain_solved(solution in arb_solution(), symmetry in arb_symmetry()) {
+            prop_assert!(solution.transformed(&symmetry).is_solved());
+        }
+
+        #[test]
+        fn transforms_preserve_unique_solutions(symmetry in arb_symmetry()) {
+            let tutorial = Sudoku::tutorial();
+            let solution = tutorial.find_unique_solution().unwrap();
+            prop_assert_eq!(
+                tutorial.transformed(&symmetry).find_unique_solution(),
+                Some(solution.transformed(&symmetry))
+            );
+        }
+
+        #[test]
+        fn rotating_four_times_is_the_identity(solution in arb_solution()) {
+            let symmetry = Symmetry::identity().rotate().rotate().rotate().rotate();
+            prop_assert_eq!(solution.transformed(&symmetry), solution);
+        }
+    }
+
+    proptest! {
+        // Finding the canonical form is relatively expensive.
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn canonical_form_is_invariant((_, puzzle) in arb_puzzle(), symmetry in arb_symmetry()) {
+            let transformed = puzzle.transformed(&symmetry);
+            prop_assert_eq!(transformed.canonical(), puzzle.canonical());
+            prop_assert_eq!(transformed.canonical_hash(), puzzle.canonical_hash());
+            prop_assert!(transformed.is_equivalent_to(&puzzle));
+        }
+    }
+
+    #[test]
+    fn sparse_puzzles_are_canonicalized_quickly() {
+        // One of the puzzles with the fewest numbers a unique solution allows,
+        // which leaves the search the most variants to consider.
+        let digits =
+            "000000010400000000020000000000050407008000300001090000300400200050100000000806000";
+        let mut puzzle = Sudoku::new();
+        for (pos, digit) in digits.bytes().enumerate() {
+            if let Some(n) = NonZeroU8::new(digit - b'0') {
+                let (x, y) = get_x_and_y_from_pos(pos);
+                puzzle = puzzle.set(x, y, n);
+            }
+        }
+        assert!(puzzle.find_unique_solution().is_some());
+
+        // Generous enough for unoptimized builds, while trying every variant
+        // would take far longer.
+        let started_at = Instant::now();
+        puzzle.canonical_hash();
+        assert!(started_at.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,1002 @@
+//! Syncs the player's data between devices through a shared folder, such as a
+//! Syncthing or network folder, without needing an account anywhere.
+//!
+//! Every device writes only its own journal to the folder, so no two devices
+//! ever write the same file. A journal is made up of numbered segments, each
+//! with the records that changed on the device since the segment before it.
+//! On startup, and then periodically in the background, the journals of all
+//! devices are merged with the local data. Conflicts are resolved per record,
+//! so that every device ends up with the same data regardless of the order in
+//! which they sync:
+//!
+//! - Highscores and library puzzles don't change once they're made, so those
+//!   of all devices are combined.
+//! - Of the statistics of a single game, the record that saw the game furthest
+//!   wins: an ended game beats one in progress, then more time played wins.
+//! - Of a saved game, a completed game beats one in progress, then the one
+//!   with more moves wins, then the one with more time played.
+//! - A game that the player deleted on any device stays deleted, see
+//!   `Game::delete()`. Games that are merely missing on a device, say because
+//!   their file couldn't be read, are restored from the other devices.
+//!
+//! Any remaining tie goes to the entry whose JSON sorts first. Settings are a
+//! matter of taste, and aren't synced. Completed games are synced without
+//! their move log, so replays are only available on the device that completed
+//! the game.
+//!
+//! Once a device has written `MAX_NUM_SEGMENTS` segments, it writes a single
+//! segment with all the records it knows of instead, and removes the others.
+
+use crate::backup::PlayerData;
+use crate::highscores::{HighscoreRecord, Highscores};
+use crate::library::{Library, LibraryPuzzle};
+use crate::persistence::{self, Migration, Persistence};
+use crate::statistics::{GameOutcome, GameRecord, Statistics};
+use crate::storage::{find_flag_value, Storage};
+use crate::sudoku::{Difficulty, Game};
+use crate::ui::report_error;
+use anyhow::Context;
+use bevy::prelude::Resource;
+use bevy::tasks::{futures::check_ready, AsyncComputeTaskPool, Task};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::btree_map::Entry as MapEntry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The command line flag that enables syncing with the given folder, used as
+/// either `--sync-dir <dir>` or `--sync-dir=<dir>`.
+const SYNC_DIR_FLAG: &str = "--sync-dir";
+
+/// The environment variable that enables syncing with the given folder.
+const SYNC_DIR_VAR: &str = "SUDOKU_SYNC_DIR";
+
+/// The directory in the sync folder with the journals of all devices.
+const JOURNALS_DIR: &str = "journals";
+
+/// The file in the data dir with the id of this device.
+const DEVICE_ID_FILE: &str = "device-id.txt";
+
+/// How often the player's data is synced while the app is running.
+const SYNC_INTERVAL_SECS: f32 = 120.;
+
+/// How many segments a device writes to its journal before it compacts them
+/// into a single one.
+const MAX_NUM_SEGMENTS: usize = 16;
+
+/// The migrations for journal segments, see
+/// `persistence::from_versioned_json()`.
+///
+/// Highscores may have been finished while showing conflicts since version 1,
+/// which older versions can't read.
+const SEGMENT_MIGRATIONS: &[Migration] = &[persistence::unchanged];
+
+/// A shared folder that the player's data is synced with.
+#[derive(Resource)]
+pub struct SyncFolder {
+    journals: Arc<Journals>,
+    secs_until_sync: f32,
+    /// The sync that's running in the background, if any, along with the slot
+    /// of the game that was in use when it started.
+    running: Option<(u64, Task<SyncResult>)>,
+}
+
+/// The outcome of a sync, which is `None` if nothing changed.
+pub type SyncResult = Result<Option<Synced>, anyhow::Error>;
+
+/// The player's data after a sync that changed it.
+pub struct Synced {
+    pub data: PlayerData,
+    /// The slots of the games that were deleted on any device.
+    pub deleted_slots: BTreeSet<u64>,
+}
+
+/// The journals of all devices in the sync folder.
+struct Journals {
+    /// Where the player's data on this device is kept.
+    persistence: Persistence,
+    storage: Box<dyn Storage>,
+    /// The id of this device, which names its journal.
+    device: String,
+    /// The entries of the segments that were read so far, by path. Segments
+    /// don't change once they're written, so each only needs to be read once.
+    segments: Mutex<BTreeMap<PathBuf, Vec<Entry>>>,
+    /// The problems that were reported to the player, so they aren't
+    /// reported again on every sync.
+    reported: Mutex<BTreeSet<String>>,
+}
+
+/// A part of a device's journal, with the records that changed on it.
+#[derive(Default, Deserialize, Serialize)]
+struct Segment {
+    device: String,
+    /// When the segment was written, in seconds since the Unix epoch.
+    written_at: u64,
+    entries: Vec<Entry>,
+}
+
+/// A single record in a journal.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Entry {
+    Highscore {
+        difficulty: Difficulty,
+        record: HighscoreRecord,
+    },
+    Statistics {
+        record: GameRecord,
+    },
+    Puzzle {
+        puzzle: LibraryPuzzle,
+    },
+    /// A saved game, along with what's needed to resolve conflicts, so games
+    /// only need to be parsed when they're taken over.
+    Game {
+        slot: u64,
+        is_completed: bool,
+        /// All the moves that were made, including the unrecorded ones.
+        num_moves: usize,
+        elapsed_secs: f32,
+        game: Value,
+    },
+    DeletedGame {
+        slot: u64,
+    },
+}
+
+/// Identifies the record an entry is about, so entries from different
+/// devices about the same record can be told apart from one another.
+#[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+enum Key {
+    Highscore(Difficulty, u64, u64),
+    Statistics(u64, u64),
+    Puzzle(u64),
+    Game(u64),
+}
+
+/// A part of the player's data that's saved on its own.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Part {
+    Highscores,
+    Statistics,
+    Library,
+    Game(u64),
+}
+
+impl Entry {
+    fn from_game(game: &Game) -> Result<Self, anyhow::Error> {
+        let mut json: Value = serde_json::from_slice(&game.to_json()?)?;
+        if game.is_solved() {
+            // The move log of a completed game is only needed for its replay,
+            // and can be long.
+            if let Some(moves) = json.get_mut("moves") {
+                *moves = Value::Array(Vec::new());
+            }
+        }
+
+        Ok(Self::Game {
+            slot: game.slot,
+            is_completed: game.is_solved(),
+            num_moves: game.moves.len() + game.num_unrecorded_moves as usize,
+            elapsed_secs: game.elapsed_secs,
+            game: json,
+        })
+    }
+
+    fn key(&self) -> Key {
+        match self {
+            Self::Highscore { difficulty, record } => {
+                Key::Highscore(*difficulty, record.finished_at, record.puzzle_id)
+            }
+            Self::Statistics { record } => Key::Statistics(record.started_at, record.puzzle_id),
+            Self::Puzzle { puzzle } => Key::Puzzle(puzzle.id),
+            Self::Game { slot, .. } | Self::DeletedGame { slot } => Key::Game(*slot),
+        }
+    }
+
+    /// Ranks the entry among the entries for the same record, where the
+    /// highest rank wins.
+    fn rank(&self) -> (bool, usize, f32) {
+        match self {
+            Self::Highscore { .. } | Self::Puzzle { .. } => (false, 0, 0.),
+            Self::Statistics { record } => (
+                record.outcome != GameOutcome::InProgress,
+                0,
+                record.elapsed_secs,
+            ),
+            Self::Game {
+                is_completed,
+                num_moves,
+                elapsed_secs,
+                ..
+            } => (*is_completed, *num_moves, *elapsed_secs),
+            // A deletion beats any version of the game.
+            Self::DeletedGame { .. } => (true, usize::MAX, f32::INFINITY),
+        }
+    }
+
+    fn wins_over(&self, other: &Entry) -> bool {
+        let (rank, other_rank) = (self.rank(), other.rank());
+        rank.0
+            .cmp(&other_rank.0)
+            .then(rank.1.cmp(&other_rank.1))
+            .then(rank.2.total_cmp(&other_rank.2))
+            // The JSON that sorts first wins any remaining tie.
+            .then_with(|| sort_key(other).cmp(&sort_key(self)))
+            .is_gt()
+    }
+}
+
+fn sort_key(entry: &Entry) -> String {
+    serde_json::to_string(entry).unwrap_or_default()
+}
+
+/// Returns whether the synced version of a game wins over the one that's in
+/// use, so it should take its place.
+pub fn takes_over(synced: &Game, current: &Game) -> bool {
+    match (Entry::from_game(synced), Entry::from_game(current)) {
+        (Ok(synced), Ok(current)) => synced.wins_over(&current),
+        _ => false,
+    }
+}
+
+impl SyncFolder {
+    pub fn new(persistence: Persistence, storage: impl Storage, device: impl Into<String>) -> Self {
+        Self {
+            journals: Arc::new(Journals {
+                persistence,
+                storage: Box::new(storage),
+                device: device.into(),
+                segments: Mutex::default(),
+                reported: Mutex::default(),
+            }),
+            // The first sync is due right away.
+            secs_until_sync: 0.,
+            running: None,
+        }
+    }
+
+    pub fn location(&self) -> String {
+        self.journals.storage.location()
+    }
+
+    /// Counts down to the next periodic sync, and returns whether it's due.
+    pub fn tick(&mut self, delta_secs: f32) -> bool {
+        self.secs_until_sync -= delta_secs;
+        if self.secs_until_sync > 0. {
+            return false;
+        }
+
+        self.secs_until_sync = SYNC_INTERVAL_SECS;
+        true
+    }
+
+    /// Starts syncing in the background, leaving the game in the given slot
+    /// alone, see `Journals::sync()`.
+    pub fn start(&mut self, active_slot: u64) {
+        let journals = self.journals.clone();
+        let task =
+            AsyncComputeTaskPool::get().spawn(async move { journals.sync(Some(active_slot)) });
+        self.running = Some((active_slot, task));
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Returns the outcome of the sync in the background once it's done,
+    /// along with the slot of the game that was in use when it started.
+    pub fn poll(&mut self) -> Option<(u64, SyncResult)> {
+        let (_, task) = self.running.as_mut()?;
+        let result = check_ready(task)?;
+        let (active_slot, _) = self.running.take()?;
+        Some((active_slot, result))
+    }
+}
+
+impl Journals {
+    /// Merges the player's data on disk with the other devices, and saves
+    /// whatever changed, except for the game in the given slot, which is in
+    /// use.
+    fn sync(&self, active_slot: Option<u64>) -> SyncResult {
+        let persistence = &self.persistence;
+        let local = PlayerData::load(persistence);
+        let before = saved_parts(&local)?;
+        let (merged, deleted_slots) = self.merge(local, &Game::list_deleted(persistence))?;
+        let after = saved_parts(&merged)?;
+        if before == after {
+            return Ok(None);
+        }
+
+        for (part, json) in &after {
+            if before.get(part) == Some(json) {
+                continue;
+            }
+
+            match part {
+                Part::Highscores => merged.highscores.save(persistence),
+                Part::Statistics => merged.statistics.save(persistence),
+                Part::Library => merged.library.save(persistence),
+                Part::Game(slot) if Some(*slot) != active_slot => {
+                    if let Some(game) = games_of(&merged).find(|game| game.slot == *slot) {
+                        game.save(persistence);
+                    }
+                }
+                Part::Game(_) => {}
+            }
+        }
+
+        for slot in &deleted_slots {
+            if before.contains_key(&Part::Game(*slot)) && Some(*slot) != active_slot {
+                Game::delete_slot(persistence, *slot);
+            }
+        }
+
+        Ok(Some(Synced {
+            data: merged,
+            deleted_slots,
+        }))
+    }
+
+    /// Merges the given data with the journals of all devices, and journals
+    /// the records that changed on this device. The given slots are those of
+    /// the games the player deleted on this device.
+    ///
+    /// Returns the merged data, along with the slots of the games that were
+    /// deleted on any device.
+    fn merge(
+        &self,
+        local: PlayerData,
+        deleted_slots: &BTreeSet<u64>,
+    ) -> Result<(PlayerData, BTreeSet<u64>), anyhow::Error> {
+        let mut segments = self
+            .segments
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.read_new_segments(&mut segments);
+
+        let mut merged = BTreeMap::new();
+        let mut own_segments = Vec::new();
+        for (path, entries) in segments.iter() {
+            if let Some((device, number)) = parse_segment_path(path) {
+                if device == self.device {
+                    own_segments.push((number, path.clone()));
+                }
+            }
+            for entry in entries {
+                add_entry(&mut merged, entry.clone());
+            }
+        }
+        own_segments.sort();
+
+        // What this device journaled so far, where later segments override
+        // earlier ones.
+        let mut journaled = BTreeMap::new();
+        for (_, path) in &own_segments {
+            for entry in &segments[path] {
+                journaled.insert(entry.key(), sort_key(entry));
+            }
+        }
+
+        let local_entries = entries_of(&local, deleted_slots)?;
+        let local_games: BTreeMap<u64, Value> = local_entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::Game { slot, game, .. } => Some((*slot, game.clone())),
+                _ => None,
+            })
+            .collect();
+        for entry in local_entries {
+            add_entry(&mut merged, entry);
+        }
+
+        let deleted_slots: BTreeSet<u64> = merged
+            .values()
+            .filter_map(|entry| match entry {
+                Entry::DeletedGame { slot } => Some(*slot),
+                _ => None,
+            })
+            .collect();
+        let data = into_data(local, local_games, merged, |slot, err| {
+            self.report_once(
+                format!("game {slot}"),
+                "Can't read a game synced from another device",
+                err,
+            )
+        });
+
+        let entries = entries_of(&data, &deleted_slots)?;
+        let is_changed = entries
+            .iter()
+            .any(|entry| journaled.get(&entry.key()) != Some(&sort_key(entry)));
+        if is_changed {
+            let number = own_segments.last().map_or(0, |(number, _)| number + 1);
+            if own_segments.len() + 1 < MAX_NUM_SEGMENTS {
+                let changed_entries = entries
+                    .into_iter()
+                    .filter(|entry| journaled.get(&entry.key()) != Some(&sort_key(entry)))
+                    .collect();
+                self.write_segment(&mut segments, number, changed_entries)?;
+            } else {
+                self.write_segment(&mut segments, number, entries)?;
+                for (_, path) in own_segments {
+                    self.storage
+                        .remove(&path)
+                        .context("Can't remove compacted journal segment")?;
+                    segments.remove(&path);
+                }
+            }
+        }
+
+        Ok((data, deleted_slots))
+    }
+
+    /// Reads the segments that weren't read before, and forgets those that
+    /// were removed since.
+    fn read_new_segments(&self, segments: &mut BTreeMap<PathBuf, Vec<Entry>>) {
+        let paths: BTreeSet<PathBuf> = self
+            .storage
+            .list(Path::new(JOURNALS_DIR))
+            .into_iter()
+            .map(|(path, _)| path)
+            .filter(|path| parse_segment_path(path).is_some())
+            .collect();
+        segments.retain(|path, _| paths.contains(path));
+
+        for path in paths {
+            if segments.contains_key(&path) {
+                continue;
+            }
+
+            // Segments that can't be read, say because they're still being
+            // copied over, are tried again on the next sync.
+            match self.read_segment(&path) {
+                Ok(segment) => {
+                    segments.insert(path, segment.entries);
+                }
+                Err(err) => self.report_once(
+                    path.display().to_string(),
+                    "Can't read data synced from another device",
+                    err,
+                ),
+            }
+        }
+    }
+
+    /// Tells the player about a problem with the given subject, unless it was
+    /// reported before.
+    fn report_once(&self, subject: String, message: &str, err: anyhow::Error) {
+        let is_new = self
+            .reported
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(subject);
+        if is_new {
+            report_error(message, err);
+        }
+    }
+
+    fn read_segment(&self, path: &Path) -> Result<Segment, anyhow::Error> {
+        let bytes = self.storage.read(path)?;
+        persistence::from_versioned_json(&bytes, SEGMENT_MIGRATIONS)
+    }
+
+    fn write_segment(
+        &self,
+        segments: &mut BTreeMap<PathBuf, Vec<Entry>>,
+        number: u64,
+        entries: Vec<Entry>,
+    ) -> Result<(), anyhow::Error> {
+        let path = segment_path(&self.device, number);
+        let segment = Segment {
+            device: self.device.clone(),
+            written_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            entries,
+        };
+        let json = persistence::to_versioned_json(&segment, SEGMENT_MIGRATIONS)?;
+        self.storage
+            .write(&path, &json)
+            .context("Can't write journal segment")?;
+        segments.insert(path, segment.entries);
+        Ok(())
+    }
+}
+
+fn segment_path(device: &str, number: u64) -> PathBuf {
+    Path::new(JOURNALS_DIR).join(format!("{device}.{number:08}.json"))
+}
+
+/// Returns the device and the number of the journal segment at the given
+/// path, if it is one.
+fn parse_segment_path(path: &Path) -> Option<(&str, u64)> {
+    if path.extension()? != "json" {
+        return None;
+    }
+
+    let (device, number) = path.file_stem()?.to_str()?.rsplit_once('.')?;
+    Some((device, number.parse().ok()?))
+}
+
+/// Adds the entry to the merged entries, unless there's one for the same
+/// record that wins over it.
+fn add_entry(merged: &mut BTreeMap<Key, Entry>, entry: Entry) {
+    match merged.entry(entry.key()) {
+        MapEntry::Vacant(vacant) => {
+            vacant.insert(entry);
+        }
+        MapEntry::Occupied(mut occupied) => {
+            if entry.wins_over(occupied.get()) {
+                occupied.insert(entry);
+            }
+        }
+    }
+}
+
+fn games_of(data: &PlayerData) -> impl Iterator<Item = &Game> {
+    data.saved_games.iter().chain(&data.archived_games)
+}
+
+/// Returns the entries for all the records in the data, and for the games
+/// that were deleted.
+fn entries_of(
+    data: &PlayerData,
+    deleted_slots: &BTreeSet<u64>,
+) -> Result<Vec<Entry>, anyhow::Error> {
+    let mut entries = Vec::new();
+    for (difficulty, highscores) in &data.highscores.difficulties {
+        for record in highscores.best_scores.iter().chain(&highscores.best_times) {
+            entries.push(Entry::Highscore {
+                difficulty: *difficulty,
+                record: record.clone(),
+            });
+        }
+    }
+    for record in &data.statistics.games {
+        entries.push(Entry::Statistics {
+            record: record.clone(),
+        });
+    }
+    for puzzle in &data.library.puzzles {
+        entries.push(Entry::Puzzle {
+            puzzle: puzzle.clone(),
+        });
+    }
+    for game in games_of(data) {
+        entries.push(Entry::from_game(game)?);
+    }
+    for slot in deleted_slots {
+        entries.push(Entry::DeletedGame { slot: *slot });
+    }
+    Ok(entries)
+}
+
+/// Turns the merged entries back into the player's data.
+///
+/// Records are added in the order of their keys, so that ties in the
+/// highscores are ranked the same on every device. Local games that weren't
+/// changed by other devices are kept as they are, as are those whose synced
+/// version can't be read, which is passed to `on_unreadable_game`.
+fn into_data(
+    mut local: PlayerData,
+    local_games: BTreeMap<u64, Value>,
+    merged: BTreeMap<Key, Entry>,
+    mut on_unreadable_game: impl FnMut(u64, anyhow::Error),
+) -> PlayerData {
+    let mut games: BTreeMap<u64, Game> = local
+        .saved_games
+        .drain(..)
+        .chain(local.archived_games.drain(..))
+        .map(|game| (game.slot, game))
+        .collect();
+
+    let mut data = PlayerData {
+        settings: local.settings,
+        highscores: Highscores {
+            legacy: local.highscores.legacy,
+            ..Default::default()
+        },
+        statistics: Statistics::default(),
+        library: local.library,
+        saved_games: Vec::new(),
+        archived_games: Vec::new(),
+    };
+    let mut puzzles = Vec::new();
+
+    for entry in merged.into_values() {
+        match entry {
+            Entry::Highscore { difficulty, record } => {
+                data.highscores.insert(difficulty, record);
+            }
+            Entry::Statistics { record } => data.statistics.games.push(record),
+            Entry::Puzzle { puzzle } => puzzles.push(puzzle),
+            Entry::Game { slot, game, .. } => {
+                let game = match games.remove(&slot) {
+                    Some(local_game) if local_games.get(&slot) == Some(&game) => local_game,
+                    local_game => match serde_json::to_vec(&game)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|json| Game::from_json(&json))
+                    {
+                        Ok(game) => game,
+                        Err(err) => {
+                            on_unreadable_game(slot, err);
+                            // A game that can't be read doesn't replace the
+                            // local one, if there is one.
+                            match local_game {
+                                Some(local_game) => local_game,
+                                None => continue,
+                            }
+                        }
+                    },
+                };
+
+                if game.is_solved() {
+                    data.archived_games.push(game);
+                } else {
+                    data.saved_games.push(game);
+                }
+            }
+            Entry::DeletedGame { .. } => {}
+        }
+    }
+
+    data.library.merge(Library { puzzles });
+    data
+}
+
+/// Returns the saved form of every part of the data, so it can be told which
+/// parts were changed by a merge.
+fn saved_parts(data: &PlayerData) -> Result<BTreeMap<Part, Vec<u8>>, anyhow::Error> {
+    let mut parts = BTreeMap::from([
+        (Part::Highscores, data.highscores.to_json()?),
+        (Part::Statistics, data.statistics.to_json()?),
+        (Part::Library, data.library.to_json()?),
+    ]);
+    for game in games_of(data) {
+        parts.insert(Part::Game(game.slot), game.to_json()?);
+    }
+    Ok(parts)
+}
+
+/// Returns the folder to sync the player's data with, if syncing is enabled.
+///
+/// The folder is chosen with the `--sync-dir` flag or the `SUDOKU_SYNC_DIR`
+/// environment variable.
+pub fn sync_dir() -> Option<PathBuf> {
+    find_flag_value(std::env::args_os().skip(1), SYNC_DIR_FLAG)
+        .or_else(|| std::env::var_os(SYNC_DIR_VAR).filter(|dir| !dir.is_empty()))
+        .map(PathBuf::from)
+}
+
+/// Returns the id of this device, which names its journal in the sync folder.
+///
+/// The id is made up on first use, and kept in the data dir.
+pub fn device_id(persistence: &Persistence) -> String {
+    let path = Path::new(DEVICE_ID_FILE);
+    let stored_id = persistence
+        .read(path)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .map(|id| id.trim().to_owned())
+        .filter(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric()));
+    if let Some(id) = stored_id {
+        return id;
+    }
+
+    let id = format!("{:016x}", rand::random::<u64>());
+    if let Err(err) = persistence.write_now(path, id.as_bytes()) {
+        report_error("Can't store the id of this device", err);
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::{self, MergeStrategy};
+    use crate::highscores::GameMode;
+    use crate::storage::{FileStorage, MemoryStorage};
+    use crate::sudoku::MoveAction;
+    use std::fs;
+
+    /// Returns a sync folder in a fresh temporary directory.
+    fn sync_folder(name: &str, device: &str) -> SyncFolder {
+        let root = std::env::temp_dir().join(format!("sudoku-sync-test-{name}"));
+        let _ = fs::remove_dir_all(&root);
+        SyncFolder::new(
+            Persistence::new(MemoryStorage::default()),
+            FileStorage::new(root),
+            device,
+        )
+    }
+
+    /// Copies the journals from one folder to the other, like a tool such as
+    /// Syncthing would, including the removal of compacted segments.
+    fn replicate(from: &SyncFolder, to: &SyncFolder) {
+        let (from, to) = (&from.journals.storage, &to.journals.storage);
+        let paths: BTreeSet<PathBuf> = from
+            .list(Path::new(JOURNALS_DIR))
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        for (path, _) in to.list(Path::new(JOURNALS_DIR)) {
+            if !paths.contains(&path) {
+                to.remove(&path).unwrap();
+            }
+        }
+        for path in paths {
+            to.write(&path, &from.read(&path).unwrap()).unwrap();
+        }
+    }
+
+    fn merge(folder: &SyncFolder, data: PlayerData) -> PlayerData {
+        merge_deleting(folder, data, &[])
+    }
+
+    /// Merges the data after the player deleted the games in the given slots.
+    fn merge_deleting(folder: &SyncFolder, data: PlayerData, deleted_slots: &[u64]) -> PlayerData {
+        let deleted_slots = deleted_slots.iter().copied().collect();
+        folder.journals.merge(data, &deleted_slots).unwrap().0
+    }
+
+    /// Returns the paths of the segments in the journal of the given device.
+    fn own_segments(folder: &SyncFolder, device: &str) -> Vec<PathBuf> {
+        folder
+            .journals
+            .storage
+            .list(Path::new(JOURNALS_DIR))
+            .into_iter()
+            .map(|(path, _)| path)
+            .filter(|path| parse_segment_path(path).is_some_and(|(owner, _)| owner == device))
+            .collect()
+    }
+
+    /// Returns the entries of the newest segment in the journal of the given
+    /// device.
+    fn newest_entries(folder: &SyncFolder, device: &str) -> Vec<Entry> {
+        let newest = own_segments(folder, device)
+            .into_iter()
+            .max_by_key(|path| parse_segment_path(path).unwrap().1)
+            .unwrap();
+        folder.journals.read_segment(&newest).unwrap().entries
+    }
+
+    fn game(slot: u64, elapsed_secs: f32) -> Game {
+        Game {
+            slot,
+            elapsed_secs,
+            ..Game::load_tutorial()
+        }
+    }
+
+    fn data(scores: &[u32], games: Vec<Game>) -> PlayerData {
+        let mut data = PlayerData::default();
+        for &score in scores {
+            let record = HighscoreRecord {
+                finished_at: score as u64,
+                score,
+                elapsed_secs: 100.,
+                num_mistakes: 0,
+                num_hints: 0,
+                puzzle_id: score as u64,
+                mode: GameMode::RevealMistakes,
+            };
+            data.highscores.insert(Difficulty::Easy, record);
+        }
+        for game in games {
+            data.statistics.games.push(GameRecord {
+                started_at: game.slot,
+                difficulty: game.difficulty,
+                puzzle_id: game.puzzle_id(),
+                outcome: if game.is_solved() {
+                    GameOutcome::Solved
+                } else {
+                    GameOutcome::InProgress
+                },
+                elapsed_secs: game.elapsed_secs,
+                num_mistakes: 0,
+                num_mistakes_by_number: [0; 9],
+                num_hints: 0,
+            });
+            if game.is_solved() {
+                data.archived_games.push(game);
+            } else {
+                data.saved_games.push(game);
+            }
+        }
+        data
+    }
+
+    fn summarize(data: &PlayerData) -> Vec<(u64, f32, bool)> {
+        let mut games: Vec<_> = games_of(data)
+            .map(|game| (game.slot, game.elapsed_secs, game.is_solved()))
+            .collect();
+        games.sort_by_key(|(slot, _, _)| *slot);
+        games
+    }
+
+    #[test]
+    fn devices_end_up_with_the_same_data() {
+        let folder_a = sync_folder("converge-a", "a");
+        let folder_b = sync_folder("converge-b", "b");
+
+        let mut completed_game = game(1, 50.);
+        completed_game.current = completed_game.solution.clone();
+        let data_a = data(&[100], vec![game(1, 300.), game(2, 10.)]);
+        let data_b = data(&[200], vec![completed_game, game(2, 20.), game(3, 30.)]);
+
+        let data_a = merge(&folder_a, data_a);
+        replicate(&folder_a, &folder_b);
+        let data_b = merge(&folder_b, data_b);
+        replicate(&folder_b, &folder_a);
+        let data_a = merge(&folder_a, data_a);
+
+        let expected = vec![(1, 50., true), (2, 20., false), (3, 30., false)];
+        assert_eq!(summarize(&data_a), expected);
+        assert_eq!(summarize(&data_b), expected);
+        assert_eq!(saved_parts(&data_a).unwrap(), saved_parts(&data_b).unwrap());
+
+        let best_scores: Vec<u32> = data_a
+            .highscores
+            .get(Difficulty::Easy)
+            .unwrap()
+            .best_scores
+            .iter()
+            .map(|record| record.score)
+            .collect();
+        assert_eq!(best_scores, vec![200, 100]);
+
+        let outcomes: Vec<GameOutcome> = data_a
+            .statistics
+            .games
+            .iter()
+            .map(|record| record.outcome)
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                GameOutcome::Solved,
+                GameOutcome::InProgress,
+                GameOutcome::InProgress
+            ]
+        );
+    }
+
+    #[test]
+    fn deleted_games_stay_deleted() {
+        let folder_a = sync_folder("delete-a", "a");
+        let folder_b = sync_folder("delete-b", "b");
+
+        let data_a = merge(&folder_a, data(&[], vec![game(1, 10.)]));
+        replicate(&folder_a, &folder_b);
+        let data_b = merge(&folder_b, PlayerData::default());
+        assert_eq!(summarize(&data_b), vec![(1, 10., false)]);
+
+        // The game is deleted on one device, while it's played on the other.
+        let mut data_a = data_a;
+        data_a.saved_games.clear();
+        merge_deleting(&folder_a, data_a, &[1]);
+        let mut data_b = data_b;
+        data_b.saved_games[0].elapsed_secs = 60.;
+        replicate(&folder_a, &folder_b);
+
+        let data_b = merge(&folder_b, data_b);
+        assert!(summarize(&data_b).is_empty());
+    }
+
+    #[test]
+    fn games_replaced_by_an_import_stay_gone() {
+        let folder_a = sync_folder("import-a", "a");
+        let folder_b = sync_folder("import-b", "b");
+
+        let persistence = &folder_a.journals.persistence;
+        game(1, 10.).save(persistence);
+        game(2, 20.).save(persistence);
+        folder_a.journals.sync(None).unwrap();
+        replicate(&folder_a, &folder_b);
+        let data_b = merge(&folder_b, PlayerData::default());
+        assert_eq!(summarize(&data_b), vec![(1, 10., false), (2, 20., false)]);
+
+        backup::import(
+            persistence,
+            data(&[], vec![game(3, 30.)]),
+            MergeStrategy::Replace,
+        );
+        folder_a.journals.sync(None).unwrap();
+        let slots: Vec<u64> = Game::list_saved(persistence)
+            .iter()
+            .map(|game| game.slot)
+            .collect();
+        assert_eq!(slots, vec![3]);
+
+        replicate(&folder_a, &folder_b);
+        let data_b = merge(&folder_b, data_b);
+        assert_eq!(summarize(&data_b), vec![(3, 30., false)]);
+    }
+
+    #[test]
+    fn missing_games_are_restored_rather_than_deleted() {
+        let folder = sync_folder("restore", "a");
+
+        let mut data = merge(&folder, data(&[], vec![game(1, 10.)]));
+        // The game's file couldn't be read, for example.
+        data.saved_games.clear();
+
+        let data = merge(&folder, data);
+        assert_eq!(summarize(&data), vec![(1, 10., false)]);
+    }
+
+    #[test]
+    fn only_changed_records_are_journaled() {
+        let folder = sync_folder("incremental", "a");
+
+        let mut data = merge(&folder, data(&[100], vec![game(1, 10.), game(2, 20.)]));
+        assert_eq!(own_segments(&folder, "a").len(), 1);
+
+        // Without any changes, nothing is journaled.
+        data = merge(&folder, data);
+        assert_eq!(own_segments(&folder, "a").len(), 1);
+
+        data.saved_games[0].elapsed_secs = 15.;
+        data = merge(&folder, data);
+        let entries = newest_entries(&folder, "a");
+        assert_eq!(entries.len(), 1);
+        assert!(matches!(entries[0], Entry::Game { slot: 1, .. }));
+
+        // Once there are too many segments, they're compacted into one with
+        // every record.
+        for elapsed_secs in 0..MAX_NUM_SEGMENTS {
+            data.saved_games[1].elapsed_secs = 30. + elapsed_secs as f32;
+            data = merge(&folder, data);
+        }
+        let num_segments = own_segments(&folder, "a").len();
+        assert!(num_segments < MAX_NUM_SEGMENTS);
+
+        let folder_b = sync_folder("incremental-b", "b");
+        replicate(&folder, &folder_b);
+        let data_b = merge(&folder_b, PlayerData::default());
+        assert_eq!(saved_parts(&data_b).unwrap(), saved_parts(&data).unwrap());
+    }
+
+    #[test]
+    fn completed_games_are_journaled_without_their_moves() {
+        let folder_a = sync_folder("moves-a", "a");
+        let folder_b = sync_folder("moves-b", "b");
+
+        let mut completed_game = game(1, 50.);
+        for (x, y) in (0..9).flat_map(|y| (0..9).map(move |x| (x, y))) {
+            if !completed_game.start.has(x, y) {
+                completed_game.make_move(MoveAction::Place {
+                    x,
+                    y,
+                    n: completed_game.solution.get(x, y).unwrap(),
+                    is_hint: false,
+                    show_mistakes: false,
+                });
+            }
+        }
+        assert!(completed_game.is_solved());
+        let num_moves = completed_game.moves.len();
+        assert!(num_moves > 0);
+
+        let data_a = merge(&folder_a, data(&[], vec![completed_game]));
+        assert_eq!(data_a.archived_games[0].moves.len(), num_moves);
+        match &newest_entries(&folder_a, "a")
+            .into_iter()
+            .find(|entry| entry.key() == Key::Game(1))
+            .unwrap()
+        {
+            Entry::Game { game, .. } => assert_eq!(game["moves"], Value::Array(Vec::new())),
+            _ => panic!("Expected the completed game"),
+        }
+
+        replicate(&folder_a, &folder_b);
+        let data_b = merge(&folder_b, PlayerData::default());
+        assert!(data_b.archived_games[0].is_solved());
+        assert!(data_b.archived_games[0].moves.is_empty());
+    }
+}
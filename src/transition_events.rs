@@ -1,16 +1,44 @@
-use crate::{game::*, sudoku::*, GameTimer, ScreenState, Settings};
+use crate::backup::{self, MergeStrategy};
+use crate::highscores::Highscores;
+use crate::library::Library;
+use crate::menus::{PracticeGeneration, SavedGames};
+use crate::persistence::Persistence;
+use crate::statistics::{GameOutcome, Statistics};
+use crate::ui::{report_error, Severity, Toasts};
+use crate::{editor::PuzzleEditor, game::*, sudoku::*};
+use crate::{GameTimer, ScreenState, Settings};
 use bevy::app::AppExit;
 use bevy::prelude::*;
 use std::num::NonZeroU8;
 
 #[derive(Clone, Copy, Message)]
 pub enum Transition {
+    /// Deletes the game in progress, instead of keeping it in its save slot.
+    AbandonGame,
     ContinueGame,
+    /// Deletes the saved game in the given slot, after the player confirmed
+    /// it.
+    DeleteSavedGame(u64),
     Exit,
     FinishOnboarding,
+    /// Shows the solution of the game in progress, after the player confirmed
+    /// it.
+    GiveUp,
+    /// Imports the backup in the data dir, merging it with the player's data.
+    ImportData(MergeStrategy),
     LearnNotes,
     LearnNumbers,
+    OpenEditor,
+    PlayPuzzle,
+    /// Restarts the game in progress, after the player confirmed it.
+    RestartGame,
+    ResumeGame(u64),
+    /// Fills in the solution for the given cell, after the player confirmed
+    /// it.
+    RevealCell(u8, u8),
     StartGame(Difficulty),
+    StartPractice(Technique),
+    WatchReplay,
 }
 
 pub fn on_transition(
@@ -21,16 +49,47 @@ pub fn on_transition(
     mut mode_state: ResMut<NextState<ModeState>>,
     mut game: ResMut<Game>,
     mut game_timer: ResMut<GameTimer>,
+    mut editor: ResMut<PuzzleEditor>,
+    mut replay_player: ResMut<ReplayPlayer>,
     mut selection: ResMut<Selection>,
     mut settings: ResMut<Settings>,
+    mut statistics: ResMut<Statistics>,
+    (mut highscores, mut library, persistence): (
+        ResMut<Highscores>,
+        ResMut<Library>,
+        Res<Persistence>,
+    ),
+    mut toasts: ResMut<Toasts>,
+    mut practice_generation: ResMut<PracticeGeneration>,
+    mut saved_games: ResMut<SavedGames>,
 ) {
     for event in reader.read() {
         use Transition::*;
+
+        // Any other transition leads away from the practice menu, so the
+        // practice game that's being generated is no longer wanted.
+        if !matches!(event, StartPractice(_)) && practice_generation.is_running() {
+            practice_generation.cancel();
+        }
+
         match event {
+            AbandonGame => {
+                if game.may_continue() {
+                    game.elapsed_secs = game_timer.elapsed_secs;
+                    game.delete(&persistence);
+                    statistics.record_end(&persistence, &game, GameOutcome::Abandoned);
+                    *game = Game::default();
+                }
+            }
             ContinueGame => {
-                *selection = Selection::new_for_game(&game);
-                mode_state.set(ModeState::Normal);
-                screen_state.set(ScreenState::Game);
+                // Store the current game first, so it shows up among the
+                // saved games with its latest progress.
+                game.elapsed_secs = game_timer.elapsed_secs;
+                game.save(&persistence);
+                screen_state.set(ScreenState::SavedGames);
+            }
+            DeleteSavedGame(slot) => {
+                saved_games.delete(&persistence, *slot, &mut game, &mut statistics)
             }
             Exit => match current_state.get() {
                 ScreenState::MainMenu => {
@@ -46,11 +105,52 @@ pub fn on_transition(
                     &mut selection,
                 ),
                 ScreenState::LearnNotes => {
-                    finish_onboarding(&mut screen_state, &mut game, &mut settings)
+                    finish_onboarding(&persistence, &mut screen_state, &mut game, &mut settings)
+                }
+                ScreenState::Editor => {
+                    // Restore the game that was borrowed by the editor.
+                    *game = Game::load(&persistence);
+                    game_timer.elapsed_secs = game.elapsed_secs;
+                    screen_state.set(ScreenState::MainMenu);
                 }
+                ScreenState::Replay => {
+                    // Restore the game that was borrowed by the replay.
+                    *game = replay_player.stop();
+                    screen_state.set(ScreenState::Highscores);
+                }
+                ScreenState::Analysis => screen_state.set(ScreenState::Highscores),
+                ScreenState::SavedGames => screen_state.set(ScreenState::MainMenu),
                 _ => screen_state.set(ScreenState::MainMenu),
             },
-            FinishOnboarding => finish_onboarding(&mut screen_state, &mut game, &mut settings),
+            FinishOnboarding => {
+                finish_onboarding(&persistence, &mut screen_state, &mut game, &mut settings)
+            }
+            GiveUp => game.give_up(),
+            ImportData(strategy) => {
+                // Store the current game first, so its latest progress is
+                // merged with the imported games.
+                game.elapsed_secs = game_timer.elapsed_secs;
+                game.save(&persistence);
+
+                match backup::read_backup(&persistence) {
+                    Ok(imported) => {
+                        let data = backup::import(&persistence, imported, *strategy);
+                        *settings = data.settings;
+                        *highscores = data.highscores;
+                        *statistics = data.statistics;
+                        *library = data.library;
+
+                        // The game in progress may have been replaced, or
+                        // removed altogether.
+                        *game = Game::load_slot(&persistence, game.slot)
+                            .unwrap_or_else(|| Game::load(&persistence));
+                        game_timer.elapsed_secs = game.elapsed_secs;
+                        *selection = Selection::new_for_game(&game);
+                        toasts.push(Severity::Info, "Data imported");
+                    }
+                    Err(err) => report_error("Can't import data", err),
+                }
+            }
             LearnNotes => how_to_play_notes(
                 &mut screen_state,
                 &mut mode_state,
@@ -63,30 +163,101 @@ pub fn on_transition(
                 &mut game,
                 &mut selection,
             ),
+            OpenEditor => {
+                // The board in the editor shows the puzzle through the game
+                // resource, so make sure the current game is safely stored
+                // first.
+                game.elapsed_secs = game_timer.elapsed_secs;
+                game.save(&persistence);
+
+                *editor = PuzzleEditor::default();
+                *game = editor.to_board_game();
+                *selection = Selection::default();
+                mode_state.set(ModeState::Normal);
+                screen_state.set(ScreenState::Editor);
+            }
+            PlayPuzzle => match Game::from_puzzle(editor.puzzle.clone()) {
+                Ok(puzzle_game) => {
+                    *game = puzzle_game;
+                    statistics.record_start(&persistence, &game);
+                    *selection = Selection::new_for_game(&game);
+                    mode_state.set(ModeState::Normal);
+                    screen_state.set(ScreenState::Game);
+                    game_timer.elapsed_secs = 0.;
+                }
+                Err(err) => report_error("Can't play puzzle", err),
+            },
+            RestartGame => {
+                game.restart();
+                game_timer.elapsed_secs = 0.;
+                *selection = Selection::new_for_game(&game);
+            }
+            ResumeGame(slot) => {
+                if game.slot != *slot {
+                    game.elapsed_secs = game_timer.elapsed_secs;
+                    game.save(&persistence);
+
+                    match Game::load_slot(&persistence, *slot) {
+                        Some(saved_game) => *game = saved_game,
+                        None => continue,
+                    }
+                }
+
+                game_timer.elapsed_secs = game.elapsed_secs;
+                *selection = Selection::new_for_game(&game);
+                mode_state.set(ModeState::Normal);
+                screen_state.set(ScreenState::Game);
+            }
+            RevealCell(x, y) => {
+                if game.reveal(*x, *y) && selection.hint == Some((*x, *y)) {
+                    selection.hint = None;
+                }
+            }
             StartGame(difficulty) => {
+                // The current game stays in its own save slot.
+                game.elapsed_secs = game_timer.elapsed_secs;
+                game.save(&persistence);
                 *game = Game::generate(*difficulty).expect("Could not generate game");
+                statistics.record_start(&persistence, &game);
                 *selection = Selection::new_for_game(&game);
                 mode_state.set(ModeState::Normal);
                 screen_state.set(ScreenState::Game);
                 game_timer.elapsed_secs = 0.;
             }
+            StartPractice(technique) => {
+                // The current game stays in its own save slot, while the
+                // practice game is generated in the background.
+                game.elapsed_secs = game_timer.elapsed_secs;
+                game.save(&persistence);
+                practice_generation.start(*technique);
+            }
+            WatchReplay => {
+                // The board in the replay shows the moves through the game
+                // resource, so the finished game is handed to the player until
+                // the replay is closed.
+                *game = replay_player.start(std::mem::take(&mut *game));
+                *selection = Selection::default();
+                mode_state.set(ModeState::Normal);
+                screen_state.set(ScreenState::Replay);
+            }
         }
     }
 }
 
 fn finish_onboarding(
+    persistence: &Persistence,
     screen_state: &mut ResMut<NextState<ScreenState>>,
     game: &mut ResMut<Game>,
     settings: &mut ResMut<Settings>,
 ) {
     // Had the onboarding been finished before?
     if settings.onboarding_finished {
-        **game = Game::load();
+        **game = Game::load(persistence);
 
         screen_state.set(ScreenState::MainMenu);
     } else {
         settings.onboarding_finished = true;
-        settings.save();
+        settings.save(persistence);
 
         **game = Game::default();
 
@@ -111,7 +282,10 @@ fn how_to_play_notes(
         selected_cell: Some((6, 4)),
         selected_note: None,
         hint: Some((3, 2)),
+        notes_check: None,
         note_toggle: None,
+        extra_cells: Vec::new(),
+        drag_select: None,
     };
     mode_state.set(ModeState::Notes);
     screen_state.set(ScreenState::LearnNotes);
@@ -128,7 +302,10 @@ fn how_to_play_numbers(
         selected_cell: None,
         selected_note: None,
         hint: Some((6, 4)),
+        notes_check: None,
         note_toggle: None,
+        extra_cells: Vec::new(),
+        drag_select: None,
     };
     mode_state.set(ModeState::Normal);
     screen_state.set(ScreenState::LearnNumbers);
@@ -4,6 +4,8 @@ use bevy::prelude::*;
 
 use crate::{constants::*, game::Wheel, pointer_query::*, Screen, ScreenState};
 
+use super::modal::{DialogButton, ModalDialog};
+use super::toast::ToastBanner;
 use super::{Button, ButtonBackground, ButtonType, ComputedPosition};
 
 pub type InteractionEntity<'a> = (
@@ -27,30 +29,43 @@ pub enum Interaction {
 
 pub fn keyboard_interaction(
     mut interaction_query: InteractionQuery,
+    mut modal_dialog: ResMut<ModalDialog>,
+    dialog_buttons: Query<(), With<DialogButton>>,
     screen: Res<State<ScreenState>>,
     keys: Res<ButtonInput<KeyCode>>,
 ) {
-    if screen.get() == &ScreenState::Game {
-        return; // Game screen has its own controls.
+    // An open dialog captures the keyboard, even on screens that have their
+    // own controls.
+    let is_dialog_open = modal_dialog.is_open();
+    if !is_dialog_open && matches!(screen.get(), ScreenState::Game | ScreenState::Editor) {
+        return; // Game and editor screens have their own controls.
     }
 
+    let is_focusable = |entity: Entity| !is_dialog_open || dialog_buttons.contains(entity);
+
     for key in keys.get_just_pressed() {
         use KeyCode::*;
         match key {
             ArrowUp | ArrowRight | ArrowDown | ArrowLeft => {
-                move_selection(&mut interaction_query, screen.get(), *key)
+                move_selection(&mut interaction_query, screen.get(), *key, is_focusable)
             }
-            Enter => confirm_selection(&mut interaction_query, screen.get()),
+            Enter => confirm_selection(&mut interaction_query, screen.get(), is_focusable),
+            Escape if is_dialog_open => modal_dialog.close(),
             _ => {}
         }
     }
 }
 
-fn move_selection(interaction_query: &mut InteractionQuery, screen: &ScreenState, key: KeyCode) {
+fn move_selection(
+    interaction_query: &mut InteractionQuery,
+    screen: &ScreenState,
+    key: KeyCode,
+    is_focusable: impl Fn(Entity) -> bool,
+) {
     let mut screen_entities: Vec<_> = interaction_query
         .iter_mut()
-        .filter(|(_, _, computed_position, visibility)| {
-            computed_position.screens.contains(screen) && visibility.get()
+        .filter(|(entity, _, computed_position, visibility)| {
+            computed_position.screens.contains(screen) && visibility.get() && is_focusable(*entity)
         })
         .collect();
 
@@ -119,14 +134,19 @@ fn is_in_direction(position: &ComputedPosition, origin: &ComputedPosition, key:
     }
 }
 
-fn confirm_selection(interaction_query: &mut InteractionQuery, screen: &ScreenState) {
+fn confirm_selection(
+    interaction_query: &mut InteractionQuery,
+    screen: &ScreenState,
+    is_focusable: impl Fn(Entity) -> bool,
+) {
     if let Some(mut selected_entity) =
         interaction_query
             .iter_mut()
-            .find(|(_, interaction, computed_position, visibility)| {
+            .find(|(entity, interaction, computed_position, visibility)| {
                 computed_position.screens.contains(screen)
                     && visibility.get()
                     && **interaction == Interaction::Selected
+                    && is_focusable(*entity)
             })
     {
         *selected_entity.1 = Interaction::Pressed;
@@ -135,6 +155,9 @@ fn confirm_selection(interaction_query: &mut InteractionQuery, screen: &ScreenSt
 
 pub fn pointer_interaction(
     mut interaction_query: InteractionQuery,
+    modal_dialog: Res<ModalDialog>,
+    dialog_buttons: Query<(), With<DialogButton>>,
+    toast_banners: Query<(), With<ToastBanner>>,
     screen: Res<State<ScreenState>>,
     pointer_query: PointerQuery,
     wheel_query: Query<(&Wheel, &ScreenState)>,
@@ -159,16 +182,26 @@ pub fn pointer_interaction(
         return;
     };
 
+    // Nothing below an open dialog can be reached.
+    let is_focusable = |entity: Entity| !modal_dialog.is_open() || dialog_buttons.contains(entity);
+
+    // Toasts are laid out on top of the screen, so they take precedence.
     let selected_entity = interaction_query
         .iter()
-        .find(|(_, _, computed_position, visibility)| {
+        .filter(|(entity, _, computed_position, visibility)| {
             computed_position.screens.contains(screen.get())
                 && visibility.get()
                 && computed_position.contains(position)
+                && is_focusable(*entity)
         })
+        .min_by_key(|(entity, ..)| !toast_banners.contains(*entity))
         .map(|(entity, ..)| entity);
 
     for (entity, mut interaction, computed_position, _) in &mut interaction_query {
+        if !is_focusable(entity) {
+            continue;
+        }
+
         let new_interaction = match selected_entity {
             Some(selected_entity) => {
                 if selected_entity == entity {
@@ -4,9 +4,11 @@ mod flex;
 mod images;
 mod interaction;
 mod layout;
+mod modal;
 mod props;
 mod style_enhancers;
 mod styles;
+mod toast;
 mod widgets;
 
 use bevy::{prelude::*, transform::TransformSystems};
@@ -17,8 +19,10 @@ pub use child_spawner_commands_ext::*;
 pub use flex::*;
 pub use images::*;
 pub use interaction::*;
+pub use modal::{dialog_closed, Dialog, DialogChoice, ModalDialog};
 pub use props::*;
 pub use styles::*;
+pub use toast::{notify, report_error, Severity, Toasts};
 pub use widgets::*;
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq, SystemSet)]
@@ -30,6 +34,8 @@ pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<ModalDialog>().init_resource::<Toasts>();
+
         app.configure_sets(
             PostUpdate,
             LayoutSystem::ApplyLayout.before(TransformSystems::Propagate),
@@ -52,6 +58,18 @@ impl Plugin for UiPlugin {
                 interaction::button_interaction
                     .after(interaction::keyboard_interaction)
                     .after(interaction::pointer_interaction),
+                modal::dialog_button_actions
+                    .after(interaction::keyboard_interaction)
+                    .after(interaction::pointer_interaction),
+                modal::render_modal_dialog.after(modal::dialog_button_actions),
+                toast::collect_reported_toasts,
+                toast::toast_interaction
+                    .after(interaction::keyboard_interaction)
+                    .after(interaction::pointer_interaction),
+                toast::on_toast_timer
+                    .after(toast::collect_reported_toasts)
+                    .after(toast::toast_interaction),
+                toast::render_toasts.after(toast::on_toast_timer),
             ),
         );
     }
@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+
+use super::*;
+use crate::{constants::*, ResourceBag, Screen, ScreenSizing, ScreenState, Transition};
+
+/// Keeps the dialog on top of everything on the screen below it.
+const DIALOG_Z_INDEX: f32 = 50.;
+
+/// A question that is asked on top of the current screen. The screen below
+/// can't be used until one of the choices is made.
+#[derive(Clone)]
+pub struct Dialog {
+    pub title: String,
+    pub message: String,
+    pub choices: Vec<DialogChoice>,
+}
+
+#[derive(Clone)]
+pub struct DialogChoice {
+    pub label: &'static str,
+    pub button_type: ButtonType,
+    /// The transitions that happen, in order, when the choice is made.
+    pub transitions: Vec<Transition>,
+}
+
+impl DialogChoice {
+    pub fn new(label: &'static str, button_type: ButtonType, transitions: Vec<Transition>) -> Self {
+        Self {
+            label,
+            button_type,
+            transitions,
+        }
+    }
+
+    /// Closes the dialog without anything happening.
+    pub fn cancel(label: &'static str) -> Self {
+        Self::new(label, ButtonType::Secondary, Vec::new())
+    }
+}
+
+/// The dialog that is open, if any.
+#[derive(Default, Resource)]
+pub struct ModalDialog(Option<Dialog>);
+
+impl ModalDialog {
+    pub fn open(&mut self, dialog: Dialog) {
+        self.0 = Some(dialog);
+    }
+
+    pub fn close(&mut self) {
+        self.0 = None;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+/// Run condition for systems that handle input for the screen below the
+/// dialog.
+pub fn dialog_closed(modal_dialog: Res<ModalDialog>) -> bool {
+    !modal_dialog.is_open()
+}
+
+/// The button for the choice with the given index.
+#[derive(Component)]
+pub struct DialogButton(usize);
+
+/// The root of the dialog, which dims the screen below it.
+#[derive(Component)]
+pub(super) struct DialogOverlay;
+
+fn dialog_box(dialog: Dialog) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    let num_choices = dialog.choices.len();
+
+    column(
+        fixed_size(Val::Vmin(80.), Val::Vmin(28. + 12. * num_choices as f32)),
+        (
+            background_color(COLOR_MODAL_BACKGROUND),
+            padding(Sides::all(Val::Vmin(4.))),
+            gap(Val::Vmin(2.)),
+        ),
+        fragment3(
+            row(
+                fixed_size(Val::Percent(100.), Val::Vmin(10.)),
+                (),
+                text(dialog.title, title_text_style),
+            ),
+            row(available_size, (), text(dialog.message, message_text_style)),
+            move |props: &Props, spawner: &mut ChildSpawnerCommands| {
+                for (index, choice) in dialog.choices.into_iter().enumerate() {
+                    let action = DialogButton(index);
+                    let size = fixed_size(Val::Percent(100.), Val::Vmin(10.));
+                    let label = text(choice.label, button_text);
+                    match choice.button_type {
+                        ButtonType::Primary => {
+                            spawner.spawn_with_children(props, primary_button(action, size, label));
+                        }
+                        ButtonType::Secondary => {
+                            spawner
+                                .spawn_with_children(props, secondary_button(action, size, label));
+                        }
+                        ButtonType::Ternary => {
+                            spawner.spawn_with_children(props, ternary_button(action, size, label));
+                        }
+                    }
+                }
+            },
+        ),
+    )
+}
+
+fn title_text_style(bundle: &mut FlexTextBundle, resources: &ResourceBag) {
+    button_text_size(bundle, resources);
+    font_bold(bundle, resources);
+    text_color(COLOR_MAIN_DARKER)(bundle, resources);
+}
+
+fn message_text_style(bundle: &mut FlexTextBundle, resources: &ResourceBag) {
+    font_medium(bundle, resources);
+    font_size(25.)(bundle, resources);
+    text_color(COLOR_MAIN_DARKER)(bundle, resources);
+}
+
+/// Makes the transitions for the chosen answer happen and closes the dialog.
+pub(super) fn dialog_button_actions(
+    query: Query<(&Interaction, &DialogButton), Changed<Interaction>>,
+    mut modal_dialog: ResMut<ModalDialog>,
+    mut transitions: MessageWriter<Transition>,
+) {
+    for (interaction, button) in &query {
+        if *interaction == Interaction::Pressed {
+            let Some(dialog) = modal_dialog.0.take() else {
+                continue;
+            };
+
+            if let Some(choice) = dialog.choices.get(button.0) {
+                for transition in &choice.transitions {
+                    transitions.write(*transition);
+                }
+            }
+        }
+    }
+}
+
+/// Spawns the dialog on top of the current screen when it's opened, and
+/// removes it again when it's closed.
+pub(super) fn render_modal_dialog(
+    mut commands: Commands,
+    mut modal_dialog: ResMut<ModalDialog>,
+    overlays: Query<Entity, With<DialogOverlay>>,
+    props: PropsTuple,
+    screen_sizing: Res<ScreenSizing>,
+    screen_state: Res<State<ScreenState>>,
+) {
+    // Dialogs belong to the screen they were opened on.
+    if screen_state.is_changed() && modal_dialog.is_open() {
+        modal_dialog.close();
+    }
+
+    if !modal_dialog.is_changed() {
+        return;
+    }
+
+    for entity in &overlays {
+        commands.entity(entity).despawn();
+    }
+
+    let Some(dialog) = modal_dialog.0.clone() else {
+        return;
+    };
+
+    let props = Props::from_tuple(&props);
+
+    // The dialog gets a root of its own, so it can cover the screen's padding
+    // too.
+    let (mut bundle, spawn_children) = container(
+        (),
+        column(
+            (available_size, z_index(DIALOG_Z_INDEX)),
+            (
+                background_color(COLOR_MODAL_BACKDROP),
+                padding(Sides::vertical(Val::Auto)),
+            ),
+            dialog_box(dialog),
+        ),
+    );
+    bundle.transform = Transform {
+        scale: Vec3::new(screen_sizing.width, screen_sizing.height, 1.),
+        translation: Vec3::new(0., 0., 1.),
+        ..default()
+    };
+
+    let screen = Screen {
+        width: screen_sizing.width,
+        height: screen_sizing.height,
+        ..Screen::for_state(*screen_state.get())
+    };
+    commands.spawn_with_children(
+        &props,
+        ((DialogOverlay, screen, Flex, bundle), spawn_children),
+    );
+}
@@ -1,4 +1,5 @@
 use crate::highscores::Highscores;
+use crate::persistence::Persistence;
 use crate::resource_bag::ResourceTuple;
 use crate::{Game, ResourceBag, Settings};
 use bevy::prelude::*;
@@ -6,6 +7,7 @@ use bevy::prelude::*;
 pub type PropsTuple<'w> = (
     Res<'w, Game>,
     Res<'w, Highscores>,
+    Res<'w, Persistence>,
     ResourceTuple<'w>,
     Res<'w, Settings>,
 );
@@ -14,15 +16,19 @@ pub type PropsTuple<'w> = (
 pub struct Props<'w> {
     pub game: &'w Game,
     pub highscores: &'w Highscores,
+    pub persistence: &'w Persistence,
     pub resources: ResourceBag<'w>,
     pub settings: &'w Settings,
 }
 
 impl<'a> Props<'a> {
-    pub fn from_tuple((game, highscores, resources, settings): &'a PropsTuple<'a>) -> Self {
+    pub fn from_tuple(
+        (game, highscores, persistence, resources, settings): &'a PropsTuple<'a>,
+    ) -> Self {
         Self {
             game,
             highscores,
+            persistence,
             resources: ResourceBag::from_tuple(resources),
             settings,
         }
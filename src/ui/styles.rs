@@ -141,6 +141,79 @@ pub fn font_size(font_size: f32) -> impl FnOnce(&mut FlexTextBundle, &ResourceBa
     }
 }
 
+pub fn game_screen_notes_button_size(style: &mut FlexItemStyle) {
+    style.dynamic_styles.push(Arc::new(
+        |style: &mut FlexItemStyle, resources: &ResourceBag| {
+            let (width, height) = if resources.screen_sizing.is_tablet() {
+                (Val::Vmin(25.), Val::Pixel(50))
+            } else {
+                (Val::Vmin(28.), Val::Pixel(35))
+            };
+            fixed_size(width, height)(style)
+        },
+    ));
+}
+
+pub fn game_screen_action_button_size(style: &mut FlexItemStyle) {
+    style.dynamic_styles.push(Arc::new(
+        |style: &mut FlexItemStyle, resources: &ResourceBag| {
+            let (width, height) = if resources.screen_sizing.is_tablet() {
+                (Val::Vmin(19.), Val::Pixel(50))
+            } else {
+                (Val::Vmin(21.), Val::Pixel(35))
+            };
+            fixed_size(width, height)(style)
+        },
+    ));
+}
+
+pub fn game_screen_action_message_size(style: &mut FlexItemStyle) {
+    style.dynamic_styles.push(Arc::new(
+        |style: &mut FlexItemStyle, resources: &ResourceBag| {
+            let (width, height) = if resources.screen_sizing.is_tablet() {
+                (Val::Vmin(38.), Val::Pixel(50))
+            } else {
+                (Val::Vmin(42.), Val::Pixel(35))
+            };
+            fixed_size(width, height)(style)
+        },
+    ));
+}
+
+pub fn game_screen_paint_swatch_size(style: &mut FlexItemStyle) {
+    style.dynamic_styles.push(Arc::new(
+        |style: &mut FlexItemStyle, resources: &ResourceBag| {
+            let (width, height) = if resources.screen_sizing.is_tablet() {
+                (Val::Vmin(8.), Val::Pixel(50))
+            } else {
+                (Val::Vmin(9.), Val::Pixel(35))
+            };
+            fixed_size(width, height)(style)
+        },
+    ));
+}
+
+pub fn replay_control_button_size(style: &mut FlexItemStyle) {
+    style.dynamic_styles.push(Arc::new(
+        |style: &mut FlexItemStyle, resources: &ResourceBag| {
+            let (width, height) = if resources.screen_sizing.is_tablet() {
+                (Val::Vmin(15.), Val::Pixel(50))
+            } else {
+                (Val::Vmin(17.), Val::Pixel(35))
+            };
+            fixed_size(width, height)(style)
+        },
+    ));
+}
+
+pub fn game_screen_notes_button_text_size(bundle: &mut FlexTextBundle, resources: &ResourceBag) {
+    bundle.font.font_size = if resources.screen_sizing.is_tablet() {
+        36.7
+    } else {
+        25.
+    };
+}
+
 pub fn game_screen_score_font_size(bundle: &mut FlexTextBundle, resources: &ResourceBag) {
     bundle.font.font_size = if resources.screen_sizing.is_tablet() {
         71.7
@@ -238,6 +311,59 @@ pub fn highscore_screen_button_size(style: &mut FlexItemStyle) {
     ));
 }
 
+/// Fits two buttons side by side in the space of one highscore screen button.
+pub fn highscore_screen_half_button_size(style: &mut FlexItemStyle) {
+    style.dynamic_styles.push(Arc::new(
+        |style: &mut FlexItemStyle, resources: &ResourceBag| {
+            let (width, height) = if resources.screen_sizing.is_tablet() {
+                (Val::Vmin(29.), Val::Vmin(6.))
+            } else {
+                (Val::Vmin(34.), Val::Vmin(10.))
+            };
+            fixed_size(width, height)(style)
+        },
+    ));
+}
+
+pub fn highscore_switcher_size(style: &mut FlexItemStyle) {
+    style.dynamic_styles.push(Arc::new(
+        |style: &mut FlexItemStyle, resources: &ResourceBag| {
+            let (width, height) = if resources.screen_sizing.is_tablet() {
+                (Val::Vmin(60.), Val::Vmin(6.))
+            } else {
+                (Val::Vmin(70.), Val::Vmin(10.))
+            };
+            fixed_size(width, height)(style)
+        },
+    ));
+}
+
+pub fn highscore_switcher_button_size(style: &mut FlexItemStyle) {
+    style.dynamic_styles.push(Arc::new(
+        |style: &mut FlexItemStyle, resources: &ResourceBag| {
+            let (width, height) = if resources.screen_sizing.is_tablet() {
+                (Val::Vmin(8.), Val::Vmin(6.))
+            } else {
+                (Val::Vmin(12.), Val::Vmin(10.))
+            };
+            fixed_size(width, height)(style)
+        },
+    ));
+}
+
+pub fn highscore_switcher_title_size(style: &mut FlexItemStyle) {
+    style.dynamic_styles.push(Arc::new(
+        |style: &mut FlexItemStyle, resources: &ResourceBag| {
+            let (width, height) = if resources.screen_sizing.is_tablet() {
+                (Val::Vmin(40.), Val::Vmin(6.))
+            } else {
+                (Val::Vmin(42.), Val::Vmin(10.))
+            };
+            fixed_size(width, height)(style)
+        },
+    ));
+}
+
 pub fn highscore_screen_wall_size(style: &mut FlexItemStyle) {
     style.dynamic_styles.push(Arc::new(
         |style: &mut FlexItemStyle, resources: &ResourceBag| {
@@ -379,7 +505,10 @@ pub fn score_board_padding(bundle: &mut FlexContainerBundle) {
 
 pub fn screen_gap(screen: ScreenState) -> impl FnOnce(&mut FlexContainerBundle) {
     move |bundle: &mut FlexContainerBundle| {
-        bundle.style.gap = if screen == ScreenState::Game {
+        bundle.style.gap = if matches!(
+            screen,
+            ScreenState::Game | ScreenState::Editor | ScreenState::Replay
+        ) {
             Val::Auto
         } else {
             Val::None
@@ -393,18 +522,22 @@ pub fn screen_padding(
 ) -> impl FnOnce(&mut FlexContainerBundle) {
     let is_tablet = resources.screen_sizing.is_tablet();
     let top_padding = resources.screen_sizing.top_padding;
+    let has_board = matches!(
+        screen,
+        ScreenState::Game | ScreenState::Editor | ScreenState::Replay
+    );
 
     move |bundle: &mut FlexContainerBundle| {
         bundle.style.padding = Sides {
             top: if screen == ScreenState::MainMenu {
                 Val::None
-            } else if is_tablet && screen == ScreenState::Game {
+            } else if is_tablet && has_board {
                 Val::Auto
             } else {
                 Val::Pixel(top_padding)
             },
             right: Val::None,
-            bottom: if is_tablet && screen == ScreenState::Game {
+            bottom: if is_tablet && has_board {
                 Val::Auto
             } else {
                 Val::None
@@ -0,0 +1,283 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_tweening::{Lens, Tween, TweenAnim};
+
+use super::*;
+use crate::{constants::*, ResourceBag, Screen, ScreenSizing, ScreenState};
+
+/// How long a toast stays up before it's dismissed automatically.
+const TOAST_DURATION: f32 = 4.;
+
+/// How long it takes a toast to slide into or out of view.
+const TOAST_SLIDE_DURATION: Duration = Duration::from_millis(250);
+
+/// How far above the top of the screen a toast slides in from.
+const TOAST_SLIDE_OFFSET: f32 = 0.2;
+
+/// Keeps toasts on top of the screen, but below an open dialog.
+const TOAST_Z_INDEX: f32 = 40.;
+
+/// Toasts that were reported by code that can't reach the `Toasts` resource.
+///
+/// Files are loaded and saved from the engine, like when a game saves itself
+/// after every move, from the background thread that writes them, and from
+/// the sync that runs in the background. None of those run as systems, so
+/// they report here, and `collect_reported_toasts()` hands the toasts over to the
+/// resource. Systems push to `Toasts` directly.
+static REPORTED_TOASTS: Mutex<Vec<Toast>> = Mutex::new(Vec::new());
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct Toast {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Shows the given message to the player, from anywhere.
+pub fn notify(severity: Severity, message: impl Into<String>) {
+    if let Ok(mut toasts) = REPORTED_TOASTS.lock() {
+        toasts.push(Toast {
+            severity,
+            message: message.into(),
+        });
+    }
+}
+
+/// Logs the error, and tells the player what went wrong.
+pub fn report_error(message: &str, err: impl Display) {
+    error!("{message}: {err}");
+    notify(Severity::Error, message);
+}
+
+/// The toast that is shown, and the ones that are waiting for it to go away.
+#[derive(Default, Resource)]
+pub struct Toasts {
+    queue: VecDeque<Toast>,
+    shown: Option<ShownToast>,
+    next_id: u64,
+}
+
+struct ShownToast {
+    id: u64,
+    toast: Toast,
+    elapsed_secs: f32,
+    /// The time since the toast started sliding out of view, if it did.
+    leaving_secs: Option<f32>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, severity: Severity, message: impl Into<String>) {
+        self.queue.push_back(Toast {
+            severity,
+            message: message.into(),
+        });
+    }
+
+    fn dismiss(&mut self) {
+        if let Some(shown) = &mut self.shown {
+            shown.leaving_secs.get_or_insert(0.);
+        }
+    }
+}
+
+/// The root of a toast, which is laid out on top of the current screen.
+#[derive(Component)]
+pub(super) struct ToastOverlay(u64);
+
+/// The banner with the toast's message, which is dismissed when tapped.
+#[derive(Component)]
+pub(super) struct ToastBanner;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct ToastSlideLens {
+    start: f32,
+    end: f32,
+}
+
+impl Lens<FlexItemStyle> for ToastSlideLens {
+    fn lerp(&mut self, mut target: Mut<FlexItemStyle>, ratio: f32) {
+        target.transform.translation.y = self.start + (self.end - self.start) * ratio;
+    }
+}
+
+fn slide(start: f32, end: f32) -> TweenAnim {
+    TweenAnim::new(Tween::new(
+        EaseFunction::QuadraticInOut,
+        TOAST_SLIDE_DURATION,
+        ToastSlideLens { start, end },
+    ))
+}
+
+fn toast_banner(
+    toast: Toast,
+    slide_in: bool,
+) -> (impl Bundle, impl FnOnce(&Props, &mut ChildSpawnerCommands)) {
+    let start = if slide_in { TOAST_SLIDE_OFFSET } else { 0. };
+
+    column_t(
+        (ToastBanner, Interaction::default(), slide(start, 0.)),
+        (
+            fixed_size(Val::Vmin(90.), Val::Vmin(12.)),
+            translation(Vec3::new(0., start, TOAST_Z_INDEX)),
+        ),
+        (
+            background_color(get_severity_color(toast.severity)),
+            padding(Sides::all(Val::Vmin(2.))),
+        ),
+        text(toast.message, toast_text_style),
+    )
+}
+
+fn get_severity_color(severity: Severity) -> Color {
+    match severity {
+        Severity::Info => COLOR_TOAST_INFO,
+        Severity::Warning => COLOR_TOAST_WARNING,
+        Severity::Error => COLOR_TOAST_ERROR,
+    }
+}
+
+fn toast_text_style(bundle: &mut FlexTextBundle, resources: &ResourceBag) {
+    font_medium(bundle, resources);
+    font_size(25.)(bundle, resources);
+    text_color(COLOR_TOAST_TEXT)(bundle, resources);
+}
+
+/// Queues the toasts that were reported through `notify()`.
+pub(super) fn collect_reported_toasts(mut toasts: ResMut<Toasts>) {
+    let Ok(mut reported) = REPORTED_TOASTS.lock() else {
+        return;
+    };
+
+    if !reported.is_empty() {
+        toasts.queue.extend(reported.drain(..));
+    }
+}
+
+pub(super) fn toast_interaction(
+    query: Query<&Interaction, (Changed<Interaction>, With<ToastBanner>)>,
+    mut toasts: ResMut<Toasts>,
+) {
+    if query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        toasts.dismiss();
+    }
+}
+
+/// Dismisses toasts when their time is up, and shows the next one in line.
+pub(super) fn on_toast_timer(mut toasts: ResMut<Toasts>, time: Res<Time>) {
+    let delta_secs = time.delta().as_secs_f32();
+
+    // Counting down shouldn't trigger change detection, or the toast would be
+    // rerendered every frame.
+    if let Some(shown) = &mut toasts.bypass_change_detection().shown {
+        shown.elapsed_secs += delta_secs;
+        if let Some(leaving_secs) = &mut shown.leaving_secs {
+            *leaving_secs += delta_secs;
+        }
+    }
+
+    let (is_gone, is_expired) = match &toasts.shown {
+        Some(shown) => (
+            shown
+                .leaving_secs
+                .is_some_and(|secs| secs >= TOAST_SLIDE_DURATION.as_secs_f32()),
+            shown.leaving_secs.is_none() && shown.elapsed_secs >= TOAST_DURATION,
+        ),
+        None => (false, false),
+    };
+    if is_gone {
+        toasts.shown = None;
+    } else if is_expired {
+        toasts.dismiss();
+    }
+
+    if toasts.shown.is_none() && !toasts.queue.is_empty() {
+        let id = toasts.next_id;
+        toasts.next_id += 1;
+        toasts.shown = toasts.queue.pop_front().map(|toast| ShownToast {
+            id,
+            toast,
+            elapsed_secs: 0.,
+            leaving_secs: None,
+        });
+    }
+}
+
+/// Spawns the shown toast on top of the current screen, and slides it out of
+/// view when it's dismissed.
+pub(super) fn render_toasts(
+    mut commands: Commands,
+    overlays: Query<(Entity, &ToastOverlay)>,
+    banners: Query<Entity, With<ToastBanner>>,
+    toasts: Res<Toasts>,
+    props: PropsTuple,
+    screen_sizing: Res<ScreenSizing>,
+    screen_state: Res<State<ScreenState>>,
+) {
+    if !toasts.is_changed() && !screen_state.is_changed() {
+        return;
+    }
+
+    // Toasts follow the player to other screens, so they get respawned on
+    // the new screen without sliding in again.
+    let mut is_spawned = false;
+    for (entity, overlay) in &overlays {
+        if !screen_state.is_changed() && toasts.shown.as_ref().is_some_and(|s| s.id == overlay.0) {
+            is_spawned = true;
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    let Some(shown) = &toasts.shown else {
+        return;
+    };
+
+    if is_spawned {
+        if shown.leaving_secs.is_some() {
+            for entity in &banners {
+                commands
+                    .entity(entity)
+                    .insert(slide(0., TOAST_SLIDE_OFFSET));
+            }
+        }
+        return;
+    }
+
+    let props = Props::from_tuple(&props);
+
+    let (mut bundle, spawn_children) = container(
+        padding(Sides::vertical(Val::Vmin(3.))),
+        toast_banner(shown.toast.clone(), !screen_state.is_changed()),
+    );
+    bundle.transform = Transform {
+        scale: Vec3::new(screen_sizing.width, screen_sizing.height, 1.),
+        translation: Vec3::new(0., 0., 1.),
+        ..default()
+    };
+
+    let screen = Screen {
+        width: screen_sizing.width,
+        height: screen_sizing.height,
+        ..Screen::for_state(*screen_state.get())
+    };
+    commands.spawn_with_children(
+        &props,
+        (
+            (ToastOverlay(shown.id), screen, Flex, bundle),
+            spawn_children,
+        ),
+    );
+}
@@ -271,6 +271,32 @@ where
     }
 }
 
+pub fn fragment6<B1, B2, B3, B4, B5, B6>(
+    child1: impl Into<BundleWithChildren<B1>>,
+    child2: impl Into<BundleWithChildren<B2>>,
+    child3: impl Into<BundleWithChildren<B3>>,
+    child4: impl Into<BundleWithChildren<B4>>,
+    child5: impl Into<BundleWithChildren<B5>>,
+    child6: impl Into<BundleWithChildren<B6>>,
+) -> impl FnOnce(&Props, &mut ChildSpawnerCommands)
+where
+    B1: Bundle,
+    B2: Bundle,
+    B3: Bundle,
+    B4: Bundle,
+    B5: Bundle,
+    B6: Bundle,
+{
+    |props, spawner| {
+        spawner.spawn_with_children(props, child1);
+        spawner.spawn_with_children(props, child2);
+        spawner.spawn_with_children(props, child3);
+        spawner.spawn_with_children(props, child4);
+        spawner.spawn_with_children(props, child5);
+        spawner.spawn_with_children(props, child6);
+    }
+}
+
 pub fn fragment7<B1, B2, B3, B4, B5, B6, B7>(
     child1: impl Into<BundleWithChildren<B1>>,
     child2: impl Into<BundleWithChildren<B2>>,
@@ -1,7 +1,7 @@
-use std::{fs, path::PathBuf};
-
 use bevy::prelude::*;
 
+use crate::sudoku::{Difficulty, Technique};
+
 const DEFAULT_TRANSLATION: Vec3 = Vec3::new(0., 0., 1.);
 
 pub trait TransformExt {
@@ -26,30 +26,31 @@ impl TransformExt for Transform {
     }
 }
 
-pub fn ensure_sudoku_dir() -> PathBuf {
-    #[allow(deprecated)]
-    let parent_dir = std::env::home_dir().unwrap_or(PathBuf::from("/tmp"));
-
-    let sudoku_dir = parent_dir.join(if cfg!(target_os = "ios") {
-        "Library/Application support"
-    } else {
-        ".sudoku"
-    });
-    if sudoku_dir.exists() {
-        return sudoku_dir;
-    }
-
-    match fs::create_dir_all(&sudoku_dir) {
-        Ok(()) => sudoku_dir,
-        Err(err) => {
-            warn!("Falling back to parent dir ({parent_dir:?}): {err:?}");
-            parent_dir
-        }
-    }
-}
-
 pub fn format_time(time_secs: f32) -> String {
     let minutes = (time_secs / 60.).floor();
     let seconds = (time_secs - minutes * 60.).floor();
     format!("{minutes}:{seconds:02}")
 }
+
+/// Returns the label under which the difficulty is presented to the player.
+pub fn format_difficulty(difficulty: Difficulty) -> &'static str {
+    match difficulty {
+        Difficulty::Trivial => "Trivial",
+        Difficulty::Easy => "Easy",
+        Difficulty::Medium => "Medium",
+        Difficulty::Advanced => "Hard",
+        Difficulty::Expert => "Extreme",
+    }
+}
+
+/// Returns the label under which the technique is presented to the player.
+pub fn format_technique(technique: Technique) -> &'static str {
+    match technique {
+        Technique::OnlyNumber => "Only Numbers",
+        Technique::LoneRanger => "Lone Rangers",
+        Technique::Twins => "Twins",
+        Technique::Triplets => "Triplets",
+        Technique::HiddenTwins => "Hidden Twins",
+        Technique::HiddenTriplets => "Hidden Triplets",
+    }
+}